@@ -124,6 +124,15 @@ impl Characters {
         self.characters.values().map(|ch| &ch.key).chain(self.duplicates.iter().map(|ch| &ch.key))
     }
 
+    /// Iterate over the `name` fields of the characters, which are usually plain names rather than
+    /// localization keys.
+    pub fn iter_names(&self) -> impl Iterator<Item = &Token> {
+        self.characters
+            .values()
+            .chain(&self.duplicates)
+            .filter_map(|ch| ch.block.get_field_value("name"))
+    }
+
     pub fn is_alive(&self, item: &Token, date: Date) -> bool {
         if let Some(item) = self.characters.get(item.as_str()) {
             item.is_alive(date)
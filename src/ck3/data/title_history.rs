@@ -1,11 +1,11 @@
 use std::path::PathBuf;
 
-use crate::block::Block;
+use crate::block::{Block, Field};
 use crate::ck3::data::titles::Tier;
 use crate::date::Date;
 use crate::everything::Everything;
 use crate::fileset::{FileEntry, FileHandler};
-use crate::helpers::TigerHashMap;
+use crate::helpers::{TigerHashMap, TigerHashSet};
 use crate::item::Item;
 use crate::parse::ParserMemory;
 use crate::pdxfile::PdxFile;
@@ -66,6 +66,224 @@ impl TitleHistories {
             err(ErrorKey::MissingItem).msg(msg).loc(key).push();
         }
     }
+
+    /// Replay the title history up to each bookmark date and check that the realms it sets up
+    /// make sense on that date. Problems are reported only for the first bookmark they affect.
+    pub fn check_timeline(&self, data: &Everything) {
+        let mut dates: Vec<Date> = data
+            .database
+            .iter_key_block(Item::Bookmark)
+            .filter_map(|(_, block)| block.get_field_date("start_date"))
+            .collect();
+        dates.sort_unstable();
+        dates.dedup();
+
+        let mut heads_of_faith: TigerHashMap<&str, Vec<&Token>> = TigerHashMap::default();
+        for (faith, block) in data.database.iter_key_block(Item::Faith) {
+            if let Some(title) = block.get_field_value("religious_head") {
+                heads_of_faith.entry(title.as_str()).or_default().push(faith);
+            }
+        }
+
+        let mut reported = TigerHashSet::default();
+        for date in dates {
+            self.check_date(date, &heads_of_faith, &mut reported, data);
+        }
+    }
+
+    fn check_date<'a>(
+        &'a self,
+        date: Date,
+        heads_of_faith: &TigerHashMap<&str, Vec<&Token>>,
+        reported: &mut TigerHashSet<(&'a str, &'static str)>,
+        data: &Everything,
+    ) {
+        // The living holder of each title on this date
+        let mut holders: TigerHashMap<&'a str, &'a Token> = TigerHashMap::default();
+        for (&key, item) in &self.histories {
+            let Some(entry) = item.holder_at(date) else {
+                continue;
+            };
+            if data.characters.is_alive(entry.value, date) {
+                holders.insert(key, entry.value);
+                if entry.key.is("holder") {
+                    if let Some(faiths) = heads_of_faith.get(key) {
+                        check_head_of_faith(key, entry.value, faiths, date, reported, data);
+                    }
+                }
+            } else if data.characters.exists(entry.value.as_str())
+                && reported.insert((key, "dead holder"))
+            {
+                // Being born too late is reported by `validate_history`.
+                let msg = format!("holder of {key} is no longer alive on {date}");
+                let info = "the title will have no holder at the start of the bookmark";
+                warn(ErrorKey::History).msg(msg).info(info).loc(entry.value).push();
+            }
+        }
+
+        // The highest tier held by each character
+        let mut tiers: TigerHashMap<&str, Tier> = TigerHashMap::default();
+        for (&key, holder) in &holders {
+            let tier = self.histories[key].tier;
+            let highest = tiers.entry(holder.as_str()).or_insert(tier);
+            *highest = (*highest).max(tier);
+        }
+
+        // Who each character is a direct vassal of, and through which title
+        let mut lieges: TigerHashMap<&'a str, Vec<(&'a Token, &'a Token)>> =
+            TigerHashMap::default();
+        for (&key, &holder) in &holders {
+            let item = &self.histories[key];
+            let Some(liege) = item.entry_at_date(&["liege"], date) else {
+                continue;
+            };
+            if liege.value.is("0") {
+                continue;
+            }
+            let Some(&liege_holder) = holders.get(liege.value.as_str()) else {
+                // If the liege had no holder when it was set, that was already reported.
+                let had_holder = self.histories.get(liege.value.as_str()).is_some_and(|l| {
+                    l.holder_at(liege.date.unwrap_or(date)).is_some_and(|e| {
+                        data.characters.is_alive(e.value, liege.date.unwrap_or(date))
+                    })
+                });
+                if had_holder && reported.insert((key, "liege without holder")) {
+                    let msg = format!("liege {} of {key} has no holder on {date}", liege.value);
+                    let info = "the vassal will be independent at the start of the bookmark";
+                    err(ErrorKey::History).msg(msg).info(info).loc(liege.value).push();
+                }
+                continue;
+            };
+            if liege_holder == holder {
+                continue;
+            }
+            let tier = tiers[holder.as_str()];
+            let liege_tier = tiers[liege_holder.as_str()];
+            if tier >= liege_tier && reported.insert((key, "liege tier")) {
+                let msg = format!(
+                    "{holder} holds titles up to {tier} tier but their liege {liege_holder} only up to {liege_tier} tier on {date}"
+                );
+                err(ErrorKey::TitleTier).msg(msg).loc(liege.value).loc_msg(holder, "holder").push();
+            }
+            lieges.entry(holder.as_str()).or_default().push((liege_holder, liege.value));
+        }
+
+        // Go through the characters in order, so that each cycle is reported from its
+        // lexicographically smallest member and along the same path every run.
+        for liege_list in lieges.values_mut() {
+            liege_list.sort_unstable_by_key(|(_, title)| title.loc);
+        }
+        let mut vassals: Vec<&str> = lieges.keys().copied().collect();
+        vassals.sort_unstable();
+        for vassal in vassals {
+            if reported.contains(&(vassal, "liege cycle")) {
+                continue;
+            }
+            for &(liege, title) in &lieges[vassal] {
+                if let Some(path) = find_path(&lieges, liege.as_str(), vassal) {
+                    let msg = format!("{vassal} is indirectly their own liege on {date}");
+                    let mut report = err(ErrorKey::History).msg(msg).loc(title);
+                    for &(_, title) in &path {
+                        report = report.loc_msg(title, "liege from here");
+                    }
+                    report.push();
+                    // Don't report the same loop again from the other characters in it.
+                    reported.insert((vassal, "liege cycle"));
+                    reported.insert((liege.as_str(), "liege cycle"));
+                    for (member, _) in path {
+                        reported.insert((member.as_str(), "liege cycle"));
+                    }
+                    break;
+                }
+            }
+        }
+
+        self.check_governments(date, &holders, reported);
+    }
+
+    /// A character has one government, so all the governments set on the titles they hold
+    /// since they got them should agree.
+    fn check_governments<'a>(
+        &'a self,
+        date: Date,
+        holders: &TigerHashMap<&'a str, &'a Token>,
+        reported: &mut TigerHashSet<(&'a str, &'static str)>,
+    ) {
+        let mut governments: TigerHashMap<&str, &Token> = TigerHashMap::default();
+        let mut titles: Vec<(&str, &Token)> = holders.iter().map(|(&k, &h)| (k, h)).collect();
+        titles.sort_unstable_by_key(|&(key, _)| key);
+        for (key, holder) in titles {
+            let item = &self.histories[key];
+            let (Some(held), Some(government)) =
+                (item.holder_at(date), item.entry_at_date(&["government"], date))
+            else {
+                continue;
+            };
+            if government.date < held.date {
+                continue;
+            }
+            if let Some(other) = governments.get(holder.as_str()) {
+                if *other != government.value && reported.insert((key, "government")) {
+                    let msg =
+                        format!("{holder} has both {other} and {} on {date}", government.value);
+                    let info = "a character can have only one government";
+                    warn(ErrorKey::History)
+                        .msg(msg)
+                        .info(info)
+                        .loc(government.value)
+                        .loc_msg(*other, "other government")
+                        .push();
+                }
+            } else {
+                governments.insert(holder.as_str(), government.value);
+            }
+        }
+    }
+}
+
+fn check_head_of_faith<'a>(
+    title: &'a str,
+    holder: &Token,
+    faiths: &[&Token],
+    date: Date,
+    reported: &mut TigerHashSet<(&'a str, &'static str)>,
+    data: &Everything,
+) {
+    if let Some(faith) = data.characters.get_faith(holder, date) {
+        if !faiths.contains(&faith) && reported.insert((title, "head of faith")) {
+            let msg = format!("{holder} holds {title} on {date} but has faith {faith}");
+            let info = format!(
+                "{title} is the head of faith title for {}; use `holder_ignore_head_of_faith_requirement` if this is intended",
+                faiths[0]
+            );
+            warn(ErrorKey::History).msg(msg).info(info).loc(holder).loc_msg(faith, "faith").push();
+        }
+    }
+}
+
+/// Follow the liege relations from `from` and return the lieges and titles that lead to `to`,
+/// if any.
+fn find_path<'a>(
+    lieges: &TigerHashMap<&str, Vec<(&'a Token, &'a Token)>>,
+    from: &str,
+    to: &str,
+) -> Option<Vec<(&'a Token, &'a Token)>> {
+    let mut seen = TigerHashSet::default();
+    let mut stack: Vec<(&str, Vec<(&Token, &Token)>)> = vec![(from, Vec::new())];
+    while let Some((vassal, path)) = stack.pop() {
+        if !seen.insert(vassal) {
+            continue;
+        }
+        for &(liege, title) in lieges.get(vassal).map_or(&[][..], Vec::as_slice) {
+            let mut path = path.clone();
+            path.push((liege, title));
+            if liege.is(to) {
+                return Some(path);
+            }
+            stack.push((liege.as_str(), path));
+        }
+    }
+    None
 }
 
 impl FileHandler<Block> for TitleHistories {
@@ -92,6 +310,14 @@ impl FileHandler<Block> for TitleHistories {
     }
 }
 
+/// A history field as it stands on some date.
+struct HistoryEntry<'a> {
+    /// The date it was set, or `None` if it was set outside of any history block.
+    date: Option<Date>,
+    key: &'a Token,
+    value: &'a Token,
+}
+
 #[derive(Clone, Debug)]
 pub struct TitleHistory {
     key: Token,
@@ -105,6 +331,40 @@ impl TitleHistory {
         Self { key, block, tier }
     }
 
+    /// Return the latest value set for any of the `fields` at or before `date`.
+    fn entry_at_date(&self, fields: &[&str], date: Date) -> Option<HistoryEntry<'_>> {
+        let mut found: Option<HistoryEntry> = None;
+        for Field(key, _, bv) in self.block.iter_fields() {
+            if fields.iter().any(|f| key.is(f)) {
+                if found.as_ref().is_none_or(|f| f.date.is_none()) {
+                    if let Some(value) = bv.get_value() {
+                        found = Some(HistoryEntry { date: None, key, value });
+                    }
+                }
+            } else if let Ok(isdate) = Date::try_from(key) {
+                if isdate > date || found.as_ref().is_some_and(|f| f.date > Some(isdate)) {
+                    continue;
+                }
+                if let Some(block) = bv.get_block() {
+                    for Field(key, _, bv) in block.iter_fields() {
+                        if fields.iter().any(|f| key.is(f)) {
+                            if let Some(value) = bv.get_value() {
+                                found = Some(HistoryEntry { date: Some(isdate), key, value });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    /// Return the holder of this title on `date`, if it has one.
+    fn holder_at(&self, date: Date) -> Option<HistoryEntry<'_>> {
+        self.entry_at_date(&["holder", "holder_ignore_head_of_faith_requirement"], date)
+            .filter(|entry| !entry.value.is("0"))
+    }
+
     pub fn verify_has_holder(&self, token: &Token, date: Date, data: &Everything) {
         let info = "setting the liege will not have effect here";
 
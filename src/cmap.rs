@@ -0,0 +1,223 @@
+//! Reader for the character maps of TrueType and OpenType font files, to find out which
+//! characters the fonts can show.
+
+use std::fs::read;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+/// The characters that a font has glyphs for.
+#[derive(Clone, Debug, Default)]
+pub struct Cmap {
+    /// Sorted and non-overlapping inclusive ranges of code points.
+    ranges: Vec<(u32, u32)>,
+}
+
+impl Cmap {
+    pub fn read(path: &Path) -> Result<Self> {
+        let bytes = read(path).with_context(|| format!("could not read {}", path.display()))?;
+        Self::parse(&bytes)
+    }
+
+    /// Parse the `cmap` table of a font file. Of a font collection, only the first font is used.
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut font = 0;
+        if bytes.starts_with(b"ttcf") {
+            font = be32(bytes, 12)? as usize;
+        } else if bytes.starts_with(b"wOFF") || bytes.starts_with(b"wOF2") {
+            bail!("compressed WOFF fonts are not supported");
+        }
+        let num_tables = usize::from(be16(bytes, font + 4)?);
+        let cmap = (0..num_tables)
+            .map(|i| font + 12 + i * 16)
+            .find(|&record| bytes.get(record..record + 4) == Some(b"cmap"))
+            .map(|record| be32(bytes, record + 8))
+            .context("font has no cmap table")?? as usize;
+
+        let mut ranges = Vec::new();
+        let mut found = false;
+        let num_subtables = usize::from(be16(bytes, cmap + 2)?);
+        for i in 0..num_subtables {
+            let record = cmap + 4 + i * 8;
+            let platform = be16(bytes, record)?;
+            let encoding = be16(bytes, record + 2)?;
+            // Platform 0 is Unicode, and platform 3 is Windows with encoding 1 for the BMP
+            // and 10 for the full range of Unicode.
+            if platform != 0 && !(platform == 3 && (encoding == 1 || encoding == 10)) {
+                continue;
+            }
+            let subtable = cmap + be32(bytes, record + 4)? as usize;
+            match be16(bytes, subtable)? {
+                4 => parse_format4(bytes, subtable, &mut ranges)?,
+                12 => parse_format12(bytes, subtable, &mut ranges)?,
+                _ => continue,
+            }
+            found = true;
+        }
+        if !found {
+            bail!("font has no Unicode character map");
+        }
+        let mut cmap = Self { ranges };
+        cmap.normalize();
+        Ok(cmap)
+    }
+
+    /// Add the characters of `other` to this map.
+    pub fn merge(&mut self, other: &Cmap) {
+        self.ranges.extend_from_slice(&other.ranges);
+        self.normalize();
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        let c = u32::from(c);
+        let idx = self.ranges.partition_point(|&(_, end)| end < c);
+        self.ranges.get(idx).is_some_and(|&(start, _)| start <= c)
+    }
+
+    fn normalize(&mut self) {
+        self.ranges.sort_unstable();
+        let mut merged: Vec<(u32, u32)> = Vec::with_capacity(self.ranges.len());
+        for &(start, end) in &self.ranges {
+            match merged.last_mut() {
+                Some((_, last_end)) if start <= last_end.saturating_add(1) => {
+                    *last_end = (*last_end).max(end);
+                }
+                _ => merged.push((start, end)),
+            }
+        }
+        self.ranges = merged;
+    }
+}
+
+/// Format 4 maps the Basic Multilingual Plane in segments. Characters in a segment can still
+/// map to glyph 0, which is the "missing glyph" box, so every character is checked.
+fn parse_format4(bytes: &[u8], subtable: usize, ranges: &mut Vec<(u32, u32)>) -> Result<()> {
+    // The field holds twice the segment count.
+    let seg_count = usize::from(be16(bytes, subtable + 6)? >> 1);
+    let end_codes = subtable + 14;
+    let start_codes = end_codes + seg_count * 2 + 2;
+    let id_deltas = start_codes + seg_count * 2;
+    let id_range_offsets = id_deltas + seg_count * 2;
+    for seg in 0..seg_count {
+        let end = be16(bytes, end_codes + seg * 2)?;
+        let start = be16(bytes, start_codes + seg * 2)?;
+        let delta = be16(bytes, id_deltas + seg * 2)?;
+        let range_offset_pos = id_range_offsets + seg * 2;
+        let range_offset = usize::from(be16(bytes, range_offset_pos)?);
+        if start > end || start == 0xFFFF {
+            continue;
+        }
+        for c in start..=end {
+            let glyph = if range_offset == 0 {
+                c.wrapping_add(delta)
+            } else {
+                let pos = range_offset_pos + range_offset + usize::from(c - start) * 2;
+                match be16(bytes, pos)? {
+                    0 => 0,
+                    glyph => glyph.wrapping_add(delta),
+                }
+            };
+            if glyph != 0 {
+                ranges.push((u32::from(c), u32::from(c)));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Format 12 maps all of Unicode in groups of consecutive characters with consecutive glyphs.
+fn parse_format12(bytes: &[u8], subtable: usize, ranges: &mut Vec<(u32, u32)>) -> Result<()> {
+    let num_groups = be32(bytes, subtable + 12)? as usize;
+    for i in 0..num_groups {
+        let group = subtable + 16 + i * 12;
+        let mut start = be32(bytes, group)?;
+        let end = be32(bytes, group + 4)?;
+        if be32(bytes, group + 8)? == 0 {
+            // The first character maps to the missing glyph.
+            start += 1;
+        }
+        if start <= end {
+            ranges.push((start, end));
+        }
+    }
+    Ok(())
+}
+
+fn be16(bytes: &[u8], offset: usize) -> Result<u16> {
+    match bytes.get(offset..offset + 2) {
+        Some(&[a, b]) => Ok(u16::from_be_bytes([a, b])),
+        _ => bail!("font file is truncated"),
+    }
+}
+
+fn be32(bytes: &[u8], offset: usize) -> Result<u32> {
+    match bytes.get(offset..offset + 4) {
+        Some(&[a, b, c, d]) => Ok(u32::from_be_bytes([a, b, c, d])),
+        _ => bail!("font file is truncated"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a font file that has only a `cmap` table with the given subtable.
+    fn font_with_subtable(platform: u16, encoding: u16, subtable: &[u8]) -> Vec<u8> {
+        let mut font = Vec::new();
+        font.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        font.extend_from_slice(&1u16.to_be_bytes()); // numTables
+        font.extend_from_slice(&[0; 6]);
+        font.extend_from_slice(b"cmap");
+        font.extend_from_slice(&0u32.to_be_bytes()); // checksum
+        font.extend_from_slice(&28u32.to_be_bytes()); // offset
+        font.extend_from_slice(&0u32.to_be_bytes()); // length
+        font.extend_from_slice(&0u16.to_be_bytes()); // cmap version
+        font.extend_from_slice(&1u16.to_be_bytes()); // numTables
+        font.extend_from_slice(&platform.to_be_bytes());
+        font.extend_from_slice(&encoding.to_be_bytes());
+        font.extend_from_slice(&12u32.to_be_bytes());
+        font.extend_from_slice(subtable);
+        font
+    }
+
+    fn words(words: &[u16]) -> Vec<u8> {
+        words.iter().flat_map(|w| w.to_be_bytes()).collect()
+    }
+
+    #[test]
+    fn test_format4() {
+        // Two segments: 'A'..='C' with a delta, and the obligatory 0xFFFF end segment.
+        #[rustfmt::skip] // one line per field of the subtable
+        let subtable = words(&[
+            4, 32, 0, 4, 4, 1, 0, // header, with segCountX2 = 4
+            0x43, 0xFFFF, // endCode
+            0, // reservedPad
+            0x41, 0xFFFF, // startCode
+            1u16.wrapping_sub(0x41), 1, // idDelta
+            0, 0, // idRangeOffset
+        ]);
+        let cmap = Cmap::parse(&font_with_subtable(3, 1, &subtable)).unwrap();
+        assert!(cmap.contains('A'));
+        assert!(cmap.contains('C'));
+        assert!(!cmap.contains('D'));
+        assert!(!cmap.contains('\u{201C}'));
+    }
+
+    #[test]
+    fn test_format12() {
+        let mut subtable = words(&[12, 0]);
+        subtable.extend_from_slice(&40u32.to_be_bytes()); // length
+        subtable.extend_from_slice(&0u32.to_be_bytes()); // language
+        subtable.extend_from_slice(&2u32.to_be_bytes()); // numGroups
+        for (start, end, glyph) in [(0x20, 0x7E, 1), (0x2_0000, 0x2_0010, 100)] {
+            subtable.extend_from_slice(&u32::to_be_bytes(start));
+            subtable.extend_from_slice(&u32::to_be_bytes(end));
+            subtable.extend_from_slice(&u32::to_be_bytes(glyph));
+        }
+        let cmap = Cmap::parse(&font_with_subtable(3, 10, &subtable)).unwrap();
+        assert!(cmap.contains('~'));
+        assert!(!cmap.contains('\u{7F}'));
+        assert!(cmap.contains('\u{20005}'));
+        assert!(!cmap.contains('\u{20011}'));
+    }
+}
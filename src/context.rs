@@ -4,6 +4,7 @@ use std::borrow::Cow;
 #[cfg(any(feature = "ck3", feature = "vic3"))]
 use std::mem::take;
 
+use crate::explain::{ScopeExplanation, ScopeFact};
 use crate::game::Game;
 use crate::helpers::{stringify_choices, ActionOrEvent, TigerHashMap};
 use crate::report::{err, warn, ErrorKey, ReportBuilderStage3};
@@ -1181,6 +1182,65 @@ impl ScopeContext {
         }
     }
 
+    /// Take a snapshot of everything this `ScopeContext` knows, for display to the user.
+    /// `key` is the field in the script where the snapshot is taken.
+    pub(crate) fn explain(&self, key: &Token) -> ScopeExplanation {
+        fn fact(scopes: Scopes, reason: &Reason) -> ScopeFact {
+            ScopeFact { scopes, reason: reason.msg().into_owned(), loc: reason.token().loc }
+        }
+
+        let (scopes, reason) = self.resolve_root();
+        let root = fact(scopes, reason);
+        let (scopes, reason) = self.scopes_reason();
+        let this = fact(scopes, reason);
+
+        let mut prev = Vec::new();
+        let mut depth = 0;
+        let mut ptr = &self.prev;
+        while let Some(entry) = ptr {
+            // The last level of an unrooted context is a placeholder for the unknown caller.
+            if self.is_unrooted && entry.prev.is_none() {
+                break;
+            }
+            let (scopes, reason) = self.scopes_reason_backref(depth);
+            prev.push(fact(scopes, reason));
+            ptr = &entry.prev;
+            depth += 1;
+        }
+
+        let mut named: Vec<_> = self
+            .names
+            .iter()
+            .map(|(&name, &idx)| {
+                let (scopes, reason) = self.resolve_named(idx);
+                (name, fact(scopes, reason), self.is_input[idx].is_some())
+            })
+            .collect();
+        named.sort_unstable_by_key(|(name, _, _)| *name);
+
+        let mut lists: Vec<_> = self
+            .list_names
+            .iter()
+            .map(|(&name, &idx)| {
+                let (scopes, reason) = self.resolve_named(idx);
+                (name, fact(scopes, reason), self.is_input[idx].is_some())
+            })
+            .collect();
+        lists.sort_unstable_by_key(|(name, _, _)| *name);
+
+        ScopeExplanation {
+            key: key.clone(),
+            root,
+            this,
+            prev,
+            named,
+            lists,
+            strict: self.strict_scopes,
+            source: self.source.clone(),
+            traceback: self.traceback.iter().map(|elem| elem.token().clone()).collect(),
+        }
+    }
+
     /// Safely destroy a `ScopeContext` without fully unwinding its stack.
     /// This is useful when a `ScopeContext` needed to be cloned for some reason.
     #[allow(dead_code)]
@@ -1,4 +1,7 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use anyhow::Result;
 
 use crate::block::{Block, BV};
 use crate::everything::Everything;
@@ -8,7 +11,8 @@ use crate::helpers::{dup_error, TigerHashMap, TigerHashSet};
 use crate::item::Item;
 use crate::parse::ParserMemory;
 use crate::pdxfile::PdxFile;
-use crate::report::{warn, ErrorKey};
+use crate::pdxmodel::{Anim, Mesh};
+use crate::report::{err, warn, ErrorKey};
 #[cfg(feature = "jomini")]
 use crate::report::{Confidence, Severity};
 use crate::token::Token;
@@ -17,7 +21,7 @@ use crate::util::SmartJoin;
 use crate::validate::validate_numeric_range;
 use crate::validator::Validator;
 
-#[derive(Clone, Debug, Default)]
+#[derive(Debug, Default)]
 #[allow(clippy::struct_field_names)]
 pub struct Assets {
     assets: TigerHashMap<&'static str, Asset>,
@@ -25,6 +29,10 @@ pub struct Assets {
     blend_shapes: TigerHashSet<Token>,
     musics: TigerHashSet<Token>,
     textures: TigerHashMap<String, (FileEntry, Token)>,
+    /// The `.mesh` and `.anim` files that were read during validation, by path. `None` if the
+    /// file could not be read.
+    meshes: RwLock<TigerHashMap<PathBuf, Option<Arc<Mesh>>>>,
+    anims: RwLock<TigerHashMap<PathBuf, Option<Arc<Anim>>>>,
 }
 
 impl Assets {
@@ -113,6 +121,18 @@ impl Assets {
         self.textures.get(key).map(|(entry, _)| entry)
     }
 
+    fn get_mesh_asset(&self, key: &str) -> Option<&Asset> {
+        self.assets.get(key).filter(|asset| asset.key.is("pdxmesh"))
+    }
+
+    fn read_mesh(&self, path: &Path, data: &Everything) -> Option<Arc<Mesh>> {
+        read_cached(&self.meshes, path, data, Mesh::read)
+    }
+
+    fn read_anim(&self, path: &Path, data: &Everything) -> Option<Arc<Anim>> {
+        read_cached(&self.anims, path, data, Anim::read)
+    }
+
     pub fn validate(&self, data: &Everything) {
         for item in self.assets.values() {
             item.validate(data);
@@ -120,6 +140,30 @@ impl Assets {
     }
 }
 
+/// Read a binary model file, or get it from the cache. A file that can't be parsed is reported
+/// only the first time. A missing file is reported by the caller.
+fn read_cached<T>(
+    cache: &RwLock<TigerHashMap<PathBuf, Option<Arc<T>>>>,
+    path: &Path,
+    data: &Everything,
+    read: fn(&Path) -> Result<T>,
+) -> Option<Arc<T>> {
+    if let Some(model) = cache.read().unwrap().get(path) {
+        return model.clone();
+    }
+    let entry = data.fileset.get_entry(path)?;
+    let model = match read(entry.fullpath()) {
+        Ok(model) => Some(Arc::new(model)),
+        Err(e) => {
+            let msg = "could not read binary model file";
+            err(ErrorKey::ParseError).msg(msg).info(format!("{e:#}")).loc(entry).push();
+            None
+        }
+    };
+    cache.write().unwrap().insert(path.to_path_buf(), model.clone());
+    model
+}
+
 impl FileHandler<Option<Block>> for Assets {
     fn subpath(&self) -> PathBuf {
         PathBuf::from("gfx/models")
@@ -198,7 +242,17 @@ impl Asset {
         Self { key, name, block }
     }
 
+    /// Read the `.mesh` file of a `pdxmesh` asset.
+    fn read_mesh(&self, data: &Everything) -> Option<Arc<Mesh>> {
+        let file = self.block.get_field_value("file")?;
+        let path = self.key.loc.pathname().smart_join_parent(file.as_str());
+        data.assets.read_mesh(&path, data)
+    }
+
     pub fn validate_mesh(&self, data: &Everything) {
+        // Only the mod's binary files are checked, because reading all of vanilla's takes long.
+        let mesh = if self.key.loc.kind.counts_as_vanilla() { None } else { self.read_mesh(data) };
+
         let mut vd = Validator::new(&self.block, data);
         vd.field_value("name");
         vd.req_field("file");
@@ -220,7 +274,12 @@ impl Asset {
             });
         });
 
-        vd.multi_field_validated_block("meshsettings", validate_meshsettings);
+        vd.multi_field_validated_block("meshsettings", |block, data| {
+            validate_meshsettings(block, data);
+            if let Some(mesh) = &mesh {
+                check_meshsettings(block, mesh, &self.name);
+            }
+        });
         vd.multi_field_validated_block("blend_shape", |block, data| {
             let mut vd = Validator::new(block, data);
             vd.req_field("id");
@@ -229,6 +288,11 @@ impl Asset {
             if let Some(token) = vd.field_value("type") {
                 let path = self.key.loc.pathname().smart_join_parent(token.as_str());
                 data.verify_exists_implied(Item::File, &path.to_string_lossy(), token);
+                if let Some(mesh) = &mesh {
+                    if let Some(blend_shape) = data.assets.read_mesh(&path, data) {
+                        check_blend_shape(&blend_shape, mesh, &self.name, token);
+                    }
+                }
             }
             vd.field_value("data"); // TODO
         });
@@ -241,6 +305,11 @@ impl Asset {
             if let Some(token) = vd.field_value("type") {
                 let path = self.key.loc.pathname().smart_join_parent(token.as_str());
                 data.fileset.verify_exists_implied_crashes(&path.to_string_lossy(), token);
+                if let Some(mesh) = &mesh {
+                    if let Some(anim) = data.assets.read_anim(&path, data) {
+                        check_bones(&anim, mesh, &self.name, token);
+                    }
+                }
             }
         });
         vd.multi_field_validated_block("additive_animation", |block, data| {
@@ -251,6 +320,11 @@ impl Asset {
             if let Some(token) = vd.field_value("type") {
                 let path = self.key.loc.pathname().smart_join_parent(token.as_str());
                 data.verify_exists_implied(Item::File, &path.to_string_lossy(), token);
+                if let Some(mesh) = &mesh {
+                    if let Some(anim) = data.assets.read_anim(&path, data) {
+                        check_bones(&anim, mesh, &self.name, token);
+                    }
+                }
             }
         });
 
@@ -262,6 +336,15 @@ impl Asset {
     }
 
     pub fn validate_entity(&self, data: &Everything) {
+        let mesh = if self.key.loc.kind.counts_as_vanilla() {
+            None
+        } else {
+            self.block
+                .get_field_value("pdxmesh")
+                .and_then(|name| data.assets.get_mesh_asset(name.as_str()))
+                .and_then(|asset| Some((asset.read_mesh(data)?, &asset.name)))
+        };
+
         let mut vd = Validator::new(&self.block, data);
         vd.set_case_sensitive(false);
 
@@ -283,7 +366,12 @@ impl Asset {
                 vd.field_numeric("default");
             });
         }
-        vd.multi_field_validated_block("meshsettings", validate_meshsettings);
+        vd.multi_field_validated_block("meshsettings", |block, data| {
+            validate_meshsettings(block, data);
+            if let Some((mesh, mesh_name)) = &mesh {
+                check_meshsettings(block, mesh, mesh_name);
+            }
+        });
         #[cfg(feature = "jomini")]
         vd.multi_field_validated_block("game_data", |block, data| {
             let mut vd = Validator::new(block, data);
@@ -386,6 +474,22 @@ impl Asset {
         vd.field_value("name");
         vd.req_field("reference_skeleton");
         vd.multi_field_item("reference_skeleton", Item::Pdxmesh);
+
+        let mut skeletons = Vec::new();
+        if !self.key.loc.kind.counts_as_vanilla() {
+            for name in self.block.get_field_values("reference_skeleton") {
+                let asset = data.assets.get_mesh_asset(name.as_str());
+                if let Some(mesh) = asset.and_then(|asset| asset.read_mesh(data)) {
+                    if mesh.has_skeleton() {
+                        skeletons.push((mesh, name));
+                    } else {
+                        let msg = format!("`{name}` has no skeleton");
+                        warn(ErrorKey::MissingItem).msg(msg).loc(name).push();
+                    }
+                }
+            }
+        }
+
         vd.multi_field_validated_block("animation", |block, data| {
             let mut vd = Validator::new(block, data);
             vd.req_field("id");
@@ -394,6 +498,13 @@ impl Asset {
             if let Some(token) = vd.field_value("type") {
                 let path = self.key.loc.pathname().smart_join_parent(token.as_str());
                 data.verify_exists_implied(Item::File, &path.to_string_lossy(), token);
+                if !skeletons.is_empty() {
+                    if let Some(anim) = data.assets.read_anim(&path, data) {
+                        for (mesh, name) in &skeletons {
+                            check_bones(&anim, mesh, name, token);
+                        }
+                    }
+                }
             }
         });
     }
@@ -498,6 +609,72 @@ fn validate_meshsettings(block: &Block, data: &Everything) {
     }
 }
 
+/// Check that the `meshsettings` name a shape in the mesh file, and a mesh in that shape.
+fn check_meshsettings(block: &Block, mesh: &Mesh, mesh_name: &Token) {
+    let Some(name) = block.get_field_value("name") else {
+        return;
+    };
+    match mesh.shape_meshes(name.as_str()) {
+        None => {
+            let msg = format!("shape `{name}` is not in the mesh file of `{mesh_name}`");
+            let mut shapes: Vec<String> =
+                mesh.iter_shapes().map(|shape| format!("`{shape}`")).collect();
+            shapes.sort_unstable();
+            let info = format!("it has {}", shapes.join(", "));
+            warn(ErrorKey::MissingItem).msg(msg).info(info).loc(name).push();
+        }
+        Some(count) => {
+            if let Some(token) = block.get_field_value("index") {
+                if token
+                    .get_integer()
+                    .is_some_and(|index| usize::try_from(index).is_ok_and(|i| i >= count))
+                {
+                    let msg = format!("shape `{name}` has no mesh with index {token}");
+                    let info = match count {
+                        0 => "it has no meshes".to_owned(),
+                        1 => "it has only one mesh, with index 0".to_owned(),
+                        _ => format!("its meshes have indexes 0 to {}", count - 1),
+                    };
+                    err(ErrorKey::MissingItem).msg(msg).info(info).loc(token).push();
+                }
+            }
+        }
+    }
+}
+
+/// Check that a blend shape only has shapes that are also in the mesh it changes.
+fn check_blend_shape(blend_shape: &Mesh, mesh: &Mesh, mesh_name: &Token, token: &Token) {
+    let mut missing: Vec<&str> = blend_shape
+        .iter_shapes()
+        .map(String::as_str)
+        .filter(|shape| mesh.shape_meshes(shape).is_none())
+        .collect();
+    missing.sort_unstable();
+    if let Some(shape) = missing.first() {
+        let msg = format!("blend shape has shape `{shape}`, which is not in `{mesh_name}`");
+        let info =
+            (missing.len() > 1).then(|| format!("also missing: {}", missing[1..].join(", ")));
+        warn(ErrorKey::MissingItem).msg(msg).opt_info(info).loc(token).push();
+    }
+}
+
+/// Check that the bones that an animation moves are in the skeleton of the mesh.
+fn check_bones(anim: &Anim, mesh: &Mesh, mesh_name: &Token, token: &Token) {
+    let mut missing: Vec<&str> =
+        anim.iter_bones().map(String::as_str).filter(|bone| !mesh.has_bone(bone)).collect();
+    missing.sort_unstable();
+    if !mesh.has_skeleton() {
+        let msg = format!("animation is for `{mesh_name}`, which has no skeleton");
+        warn(ErrorKey::MissingItem).msg(msg).loc(token).push();
+    } else if let Some(bone) = missing.first() {
+        let msg =
+            format!("animation moves bone `{bone}`, which is not in the skeleton of `{mesh_name}`");
+        let info =
+            (missing.len() > 1).then(|| format!("also missing: {}", missing[1..].join(", ")));
+        warn(ErrorKey::MissingItem).msg(msg).opt_info(info).loc(token).push();
+    }
+}
+
 fn validate_time_offset(bv: &BV, data: &Everything) {
     match bv {
         BV::Value(token) => {
@@ -1,7 +1,13 @@
+use std::path::Path;
+use std::str::FromStr;
+
 use crate::block::Block;
+use crate::cmap::Cmap;
+use crate::data::localization::Language;
 use crate::db::{Db, DbKind};
 use crate::everything::Everything;
 use crate::game::GameFlags;
+use crate::helpers::{TigerHashMap, TigerHashSet};
 use crate::item::{Item, ItemLoader, LoadAsFile, Recursive};
 use crate::pdxfile::PdxEncoding;
 use crate::report::{untidy, warn, ErrorKey, Severity};
@@ -81,3 +87,70 @@ impl DbKind for Fontfiles {
         });
     }
 }
+
+/// Return the font files that the game uses for `lang`.
+fn font_files(data: &Everything, lang: Language) -> Vec<&Token> {
+    let mut result = Vec::new();
+    for (_, block) in data.database.iter_key_block(Item::Fontfiles) {
+        for group in block.get_field_blocks("group") {
+            // A group without languages is used for all of them.
+            if let Some(languages) = group.get_field_list("languages") {
+                if !languages.iter().any(|l| {
+                    Language::from_str(l.as_str().strip_prefix("l_").unwrap_or(l.as_str()))
+                        == Ok(lang)
+                }) {
+                    continue;
+                }
+            }
+            if let Some(files) = group.get_field_block("files") {
+                result.extend(files.iter_values());
+            }
+        }
+    }
+    result
+}
+
+/// Read the character maps of the font files that the game uses for any of `langs`.
+/// Each file is read only once, even if it's used for several languages. Files that can't be read
+/// are left out.
+pub fn read_cmaps(data: &Everything, langs: &[Language]) -> TigerHashMap<&'static str, Cmap> {
+    let mut cmaps = TigerHashMap::default();
+    let mut seen = TigerHashSet::default();
+    for &lang in langs {
+        for file in font_files(data, lang) {
+            if !seen.insert(file.as_str()) {
+                continue;
+            }
+            let Some(entry) = data.fileset.get_entry(Path::new(file.as_str())) else {
+                continue;
+            };
+            match Cmap::read(entry.fullpath()) {
+                Ok(cmap) => {
+                    cmaps.insert(file.as_str(), cmap);
+                }
+                Err(e) => {
+                    let msg = format!("could not read the font's character map: {e:#}");
+                    warn(ErrorKey::ReadError).msg(msg).loc(file).push();
+                }
+            }
+        }
+    }
+    cmaps
+}
+
+/// Combine the character maps, from those read by [`read_cmaps`], of all the font files that the
+/// game uses for `lang`.
+/// Returns `None` if no font files are defined for the language or none of them could be read.
+pub fn glyph_coverage(
+    data: &Everything,
+    lang: Language,
+    cmaps: &TigerHashMap<&'static str, Cmap>,
+) -> Option<Cmap> {
+    let mut coverage: Option<Cmap> = None;
+    for file in font_files(data, lang) {
+        if let Some(cmap) = cmaps.get(file.as_str()) {
+            coverage.get_or_insert_with(Cmap::default).merge(cmap);
+        }
+    }
+    coverage
+}
@@ -2,36 +2,43 @@
 
 use std::borrow::Borrow;
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::ffi::OsStr;
-use std::fs::read_to_string;
-#[cfg(any(feature = "ck3", feature = "vic3"))]
-use std::io::Cursor;
+use std::fs::{read_to_string, write};
+use std::io::{Cursor, ErrorKind};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering::Relaxed;
-use std::sync::LazyLock;
+use std::sync::{LazyLock, Mutex};
 
+use anyhow::{Context, Result};
 use bitvec::order::Lsb0;
 use bitvec::{bitarr, BitArr};
-#[cfg(any(feature = "ck3", feature = "vic3"))]
 use murmur3::murmur3_32;
 use rayon::scope;
+use serde::{Deserialize, Serialize};
 use strum::{EnumCount, IntoEnumIterator};
 use strum_macros::{Display, EnumCount, EnumIter, EnumString, FromRepr, IntoStaticStr};
 
 use crate::block::Block;
 #[cfg(feature = "ck3")]
-use crate::ck3::tables::localization::{BUILTIN_MACROS_CK3, COMPLEX_TOOLTIPS_CK3};
+use crate::ck3::tables::localization::{
+    BUILTIN_MACROS_CK3, COMPLEX_TOOLTIPS_CK3, FORMAT_CODES_CK3,
+};
+#[cfg(feature = "jomini")]
+use crate::cmap::Cmap;
 use crate::context::ScopeContext;
+#[cfg(feature = "jomini")]
+use crate::data::fonts::{glyph_coverage, read_cmaps};
 use crate::datatype::{validate_datatypes, CodeChain, Datatype};
 use crate::everything::Everything;
 use crate::fileset::{FileEntry, FileHandler, FileKind};
 use crate::game::Game;
-use crate::helpers::{dup_error, stringify_list, TigerHashMap};
+use crate::helpers::{dup_error, stringify_choices, stringify_list, TigerHashMap, TigerHashSet};
 #[cfg(feature = "hoi4")]
-use crate::hoi4::tables::localization::BUILTIN_MACROS_HOI4;
+use crate::hoi4::tables::localization::{BUILTIN_MACROS_HOI4, FORMAT_CODES_HOI4};
 #[cfg(feature = "imperator")]
-use crate::imperator::tables::localization::BUILTIN_MACROS_IMPERATOR;
+use crate::imperator::tables::localization::{BUILTIN_MACROS_IMPERATOR, FORMAT_CODES_IMPERATOR};
 use crate::item::Item;
 use crate::macros::{MacroMapIndex, MACRO_MAP};
 use crate::parse::localization::{parse_loca, ValueParser};
@@ -40,9 +47,10 @@ use crate::report::{
     err, report, tips, warn, warn_abbreviated, warn_header, will_maybe_log, ErrorKey, Severity,
 };
 use crate::scopes::Scopes;
-use crate::token::Token;
+use crate::spelling::{should_check, split_words, SpellingConfig};
+use crate::token::{Loc, Token};
 #[cfg(feature = "vic3")]
-use crate::vic3::tables::localization::BUILTIN_MACROS_VIC3;
+use crate::vic3::tables::localization::{BUILTIN_MACROS_VIC3, FORMAT_CODES_VIC3};
 
 /// Database of all loaded localization keys and their values, for all supported languages.
 #[derive(Debug)]
@@ -55,6 +63,19 @@ pub struct Localization {
     mod_langs: BitArr!(for Language::COUNT, in u16),
     /// Database of all localizations, indexed first by language and then by localization key.
     locas: Box<[TigerHashMap<String, LocaEntry>; Language::COUNT]>,
+    /// Vanilla localizations that were replaced by mod localizations with the same key.
+    /// They are only kept if `keep_overridden` is set.
+    overridden: Box<[TigerHashMap<String, LocaEntry>; Language::COUNT]>,
+    keep_overridden: bool,
+    /// The language that translations are compared against, according to the config file.
+    reference_lang: Language,
+    /// For the mod's keys in the reference language, the first place in script that uses them.
+    /// This is only collected if `collect_used_at` is set, because it's only needed for exporting
+    /// translations and it takes a lock on every lookup.
+    used_at: Mutex<TigerHashMap<String, Loc>>,
+    collect_used_at: bool,
+    /// Settings for spell checking, which is only done if the config file asks for it.
+    spelling: Option<SpellingConfig>,
 }
 
 /// List of languages that are supported by the game engine.
@@ -129,6 +150,119 @@ fn is_builtin_macro<S: Borrow<str>>(s: S) -> bool {
     }
 }
 
+/// What a formatting code after the `|` in `[code|format]` does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FormatKind {
+    /// Number of decimals, signs, percentages, and abbreviations of large numbers.
+    Number,
+    /// Capitalization of text.
+    #[allow(dead_code)] // Not used by all games
+    Text,
+    /// Link to a game concept.
+    #[allow(dead_code)] // Not used by all games
+    Concept,
+    /// Text color.
+    Color,
+}
+
+/// The formatting codes that are built into the game.
+fn format_codes() -> &'static [(char, FormatKind)] {
+    match Game::game() {
+        #[cfg(feature = "ck3")]
+        Game::Ck3 => FORMAT_CODES_CK3,
+        #[cfg(feature = "vic3")]
+        Game::Vic3 => FORMAT_CODES_VIC3,
+        #[cfg(feature = "imperator")]
+        Game::Imperator => FORMAT_CODES_IMPERATOR,
+        #[cfg(feature = "hoi4")]
+        Game::Hoi4 => FORMAT_CODES_HOI4,
+    }
+}
+
+fn format_kind(code: char, data: &Everything) -> Option<FormatKind> {
+    if let Some((_, kind)) = format_codes().iter().find(|(c, _)| *c == code) {
+        return Some(*kind);
+    }
+    // Hoi4 mods can define their own text colors in `interface/core.gfx`, which are not loaded.
+    let mut buf = [0; 4];
+    (code.is_ascii_alphabetic()
+        && (Game::is_hoi4() || data.item_exists(Item::TextFormat, code.encode_utf8(&mut buf))))
+    .then_some(FormatKind::Color)
+}
+
+fn is_number_datatype(dtype: Datatype) -> bool {
+    matches!(
+        dtype,
+        Datatype::CFixedPoint
+            | Datatype::double
+            | Datatype::float
+            | Datatype::int8
+            | Datatype::int16
+            | Datatype::int32
+            | Datatype::int64
+            | Datatype::uint8
+            | Datatype::uint16
+            | Datatype::uint32
+            | Datatype::uint64
+    )
+}
+
+/// Check the formatting codes in `[code|format]`, and whether they suit `dtype`, which is the
+/// datatype that the code returns. Unknown codes are shown as-is in the game.
+pub(crate) fn validate_loca_format(format: &Token, dtype: Datatype, data: &Everything) {
+    check_loca_format(format, dtype, |c| format_kind(c, data));
+}
+
+/// Do the checks of [`validate_loca_format`], with `format_kind` saying what each code is for.
+fn check_loca_format(
+    format: &Token,
+    dtype: Datatype,
+    format_kind: impl Fn(char) -> Option<FormatKind>,
+) {
+    let mut number = None;
+    let mut text = None;
+    for (col, c) in format.as_str().chars().enumerate() {
+        let col = u32::try_from(col).expect("internal error: 2^32 columns");
+        let mut loc = format.loc;
+        loc.column += col;
+        match format_kind(c) {
+            Some(FormatKind::Number) => {
+                number.get_or_insert((c, loc));
+            }
+            Some(FormatKind::Text | FormatKind::Concept) => {
+                text.get_or_insert((c, loc));
+            }
+            Some(FormatKind::Color) => (),
+            // Some games allow separating the codes with more `|`
+            None if c == '|' => (),
+            None => {
+                let msg = format!("unknown formatting code `{c}`");
+                let info = "the game will show the code as part of the text";
+                warn(ErrorKey::LocaFormat).msg(msg).info(info).loc(loc).push();
+            }
+        }
+    }
+    match (number, text) {
+        (Some((n, nloc)), Some((t, tloc))) => {
+            let msg = format!("formatting code `{n}` is for numbers but `{t}` is for text");
+            warn(ErrorKey::LocaFormat)
+                .msg(msg)
+                .loc(nloc)
+                .loc_msg(tloc, "text formatting here")
+                .push();
+        }
+        (Some((n, loc)), None) if matches!(dtype, Datatype::CString | Datatype::CUTF8String) => {
+            let msg = format!("formatting code `{n}` is for numbers but this returns {dtype}");
+            warn(ErrorKey::LocaFormat).msg(msg).loc(loc).push();
+        }
+        (None, Some((t, loc))) if is_number_datatype(dtype) => {
+            let msg = format!("formatting code `{t}` is for text but this returns {dtype}");
+            warn(ErrorKey::LocaFormat).msg(msg).loc(loc).push();
+        }
+        _ => (),
+    }
+}
+
 /// One parsed key: value line from the localization values.
 #[derive(Debug)]
 pub struct LocaEntry {
@@ -137,6 +271,9 @@ pub struct LocaEntry {
     /// The original unparsed value, with enclosing `"` stripped.
     /// This is used for macro replacement.
     orig: Option<Token>,
+    /// The version number after the `:`, which is raised when the text changes so that
+    /// translators know to update it.
+    version: u32,
     /// Whether this entry has been "used" (looked up) by anything in the mod
     used: AtomicBool,
     /// Whether this entry has been validated with a `ScopeContext`
@@ -164,8 +301,24 @@ impl Ord for LocaEntry {
 }
 
 impl LocaEntry {
-    pub fn new(key: Token, value: LocaValue, orig: Option<Token>) -> Self {
-        Self { key, value, orig, used: AtomicBool::new(false), validated: AtomicBool::new(false) }
+    pub fn new(key: Token, value: LocaValue, orig: Option<Token>, version: u32) -> Self {
+        Self {
+            key,
+            value,
+            orig,
+            version,
+            used: AtomicBool::new(false),
+            validated: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) fn value(&self) -> &LocaValue {
+        &self.value
+    }
+
+    /// The original unparsed value.
+    pub(crate) fn orig(&self) -> Option<&Token> {
+        self.orig.as_ref()
     }
 
     // returns false to abort expansion in case of an error
@@ -238,7 +391,8 @@ pub enum LocaValue {
     Concat(Vec<LocaValue>),
     #[allow(dead_code)] // the Token is only used for ck3
     Text(Token),
-    Markup,
+    /// The markup text, starting with `#`.
+    Markup(Token),
     MarkupEnd,
     Tooltip(Token),
     // Tag, key, value. Tag can influence how tooltip is looked up. If tag is `GAME_TRAIT`,
@@ -265,6 +419,233 @@ pub enum MacroValue {
     Keyword(Token),
 }
 
+/// Return false for the languages that don't put spaces between words, which can't be spell
+/// checked word by word.
+fn separates_words(lang: Language) -> bool {
+    match lang {
+        Language::SimpChinese => false,
+        #[cfg(any(feature = "vic3", feature = "hoi4"))]
+        Language::Japanese => false,
+        _ => true,
+    }
+}
+
+/// Collect the tokens of plain text in `value`, which are the parts that are shown as-is.
+fn collect_text<'a>(value: &'a LocaValue, texts: &mut Vec<&'a Token>) {
+    match value {
+        LocaValue::Concat(values) => {
+            for value in values {
+                collect_text(value, texts);
+            }
+        }
+        LocaValue::Macro(values) => {
+            for value in values {
+                if let MacroValue::Text(token) = value {
+                    texts.push(token);
+                }
+            }
+        }
+        LocaValue::Text(token) => texts.push(token),
+        _ => (),
+    }
+}
+
+/// The parts of a localization value that should be the same in every translation of it.
+#[derive(Debug, Default)]
+struct LocaStructure {
+    /// The keywords between `$` `$`.
+    macros: Vec<String>,
+    /// The code between `[` `]`, except for game concepts. Only the scope that a chain is about
+    /// is kept, not the function at the end, because translations often need a different
+    /// function to get the grammar right, such as a different case or gender.
+    codes: Vec<String>,
+    /// The game concepts linked with `|E`.
+    concepts: Vec<String>,
+    /// The icons between `@` `!`, or flags for hoi4.
+    icons: Vec<String>,
+    /// The number of `#markup` starts.
+    markup: usize,
+    /// The number of `#!` markup ends.
+    markup_ends: usize,
+    /// The number of `\n` line breaks.
+    newlines: usize,
+    /// Whether the value contains macros, in which case only those can be compared because the
+    /// rest was not parsed.
+    has_macros: bool,
+    /// Whether the value could not be parsed, in which case it should not be compared at all.
+    has_error: bool,
+}
+
+impl LocaStructure {
+    fn new(value: &LocaValue) -> Self {
+        let mut structure = Self::default();
+        structure.add(value);
+        for list in [
+            &mut structure.macros,
+            &mut structure.codes,
+            &mut structure.concepts,
+            &mut structure.icons,
+        ] {
+            list.sort_unstable();
+        }
+        structure
+    }
+
+    fn add(&mut self, value: &LocaValue) {
+        match value {
+            LocaValue::Macro(values) => {
+                self.has_macros = true;
+                for value in values {
+                    if let MacroValue::Keyword(keyword) = value {
+                        self.macros.push(format!("${keyword}$"));
+                    }
+                }
+            }
+            LocaValue::Concat(values) => {
+                for value in values {
+                    self.add(value);
+                }
+            }
+            LocaValue::CalculatedIcon(_) => self.icons.push("@[...]!".to_string()),
+            LocaValue::Text(token) => self.newlines += token.as_str().matches('\n').count(),
+            LocaValue::Markup(_) => self.markup += 1,
+            LocaValue::MarkupEnd => self.markup_ends += 1,
+            LocaValue::Code(chain, format) => {
+                if format.as_ref().is_some_and(|f| f.as_str().contains(['E', 'e'])) {
+                    #[cfg(feature = "ck3")]
+                    if let Some(concept) = chain.as_gameconcept() {
+                        self.concepts.push(format!("[{}|E]", concept.as_str().to_lowercase()));
+                        return;
+                    }
+                    self.concepts.push(format!("[{}|E]", chain.to_string().to_lowercase()));
+                } else if let [scope @ .., _] = &chain.codes[..] {
+                    if scope.is_empty() {
+                        self.codes.push(format!("[{chain}]"));
+                    } else {
+                        let scope = CodeChain { codes: scope.into() };
+                        self.codes.push(format!("[{scope}.…]"));
+                    }
+                }
+            }
+            LocaValue::Icon(token) => self.icons.push(format!("@{token}!")),
+            LocaValue::Flag(token) => self.icons.push(format!("@{token}")),
+            LocaValue::Error => self.has_error = true,
+            LocaValue::Tooltip(_) | LocaValue::ComplexTooltip(_, _) => (),
+        }
+    }
+
+    /// Describe how `self` differs from `reference`, as a list of phrases.
+    fn differences(&self, reference: &Self) -> Vec<String> {
+        let mut result = Vec::new();
+        list_differences(&reference.macros, &self.macros, &mut result);
+        if self.has_macros || reference.has_macros {
+            return result;
+        }
+        list_differences(&reference.codes, &self.codes, &mut result);
+        list_differences(&reference.concepts, &self.concepts, &mut result);
+        list_differences(&reference.icons, &self.icons, &mut result);
+        count_difference(reference.markup, self.markup, "`#markup`", &mut result);
+        count_difference(reference.markup_ends, self.markup_ends, "`#!`", &mut result);
+        count_difference(reference.newlines, self.newlines, "`\\n`", &mut result);
+        result
+    }
+}
+
+/// Add phrases to `result` for the items of `reference` missing from `other`, and for the extra
+/// items in `other`. Both lists must be sorted.
+fn list_differences(reference: &[String], other: &[String], result: &mut Vec<String>) {
+    let mut missing = Vec::new();
+    let mut extra = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < reference.len() || j < other.len() {
+        if j == other.len() || (i < reference.len() && reference[i] < other[j]) {
+            missing.push(format!("`{}`", reference[i]));
+            i += 1;
+        } else if i == reference.len() || other[j] < reference[i] {
+            extra.push(format!("`{}`", other[j]));
+            j += 1;
+        } else {
+            i += 1;
+            j += 1;
+        }
+    }
+    if !missing.is_empty() {
+        result.push(format!("missing {}", missing.join(", ")));
+    }
+    if !extra.is_empty() {
+        result.push(format!("extra {}", extra.join(", ")));
+    }
+}
+
+fn count_difference(reference: usize, other: usize, what: &str, result: &mut Vec<String>) {
+    if other < reference {
+        result.push(format!("{} fewer {what}", reference - other));
+    } else if other > reference {
+        result.push(format!("{} more {what}", other - reference));
+    }
+}
+
+/// Recorded hashes of translations and of the reference language texts they were translated from,
+/// by language and then by localization key.
+type TranslationHashes = BTreeMap<String, BTreeMap<String, TranslationHash>>;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TranslationHash {
+    source: String,
+    translation: String,
+}
+
+/// A key that is missing or out of date in some language, for the translators.
+#[derive(Debug)]
+pub struct Untranslated<'a> {
+    /// The key in the reference language. Its location is the file of origin.
+    pub key: &'a Token,
+    /// The unparsed reference language text.
+    pub source: &'a str,
+    /// The unparsed text of the out of date translation, if any.
+    pub current: Option<&'a str>,
+    /// The first place in the script that uses this key, if known.
+    pub used_at: Option<Loc>,
+}
+
+fn read_translation_hashes(path: &Path) -> Result<TranslationHashes> {
+    match read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content)
+            .with_context(|| format!("could not parse {}", path.display())),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(TranslationHashes::default()),
+        Err(e) => Err(e).with_context(|| format!("could not read {}", path.display())),
+    }
+}
+
+/// Return the path of the `lang` file that corresponds to the `reference_lang` file at `path`, by
+/// replacing the language in the directory names and in the `_l_language` part of the filename.
+fn translated_path(path: &Path, reference_lang: Language, lang: Language) -> PathBuf {
+    let reference_lang = reference_lang.to_string();
+    let lang = lang.to_string();
+    let mut result = PathBuf::new();
+    for component in path.parent().into_iter().flatten() {
+        if component == reference_lang.as_str() {
+            result.push(&lang);
+        } else {
+            result.push(component);
+        }
+    }
+    let filename = path.file_name().map_or(String::new(), |f| f.to_string_lossy().into_owned());
+    let suffix = format!("l_{reference_lang}");
+    let filename = match filename.rfind(&suffix) {
+        Some(i) => format!("{}l_{lang}{}", &filename[..i], &filename[i + suffix.len()..]),
+        None => format!("{}_l_{lang}.yml", filename.trim_end_matches(".yml")),
+    };
+    result.push(filename);
+    result
+}
+
+/// Return a hash of the text of a localization entry that will stay the same between runs.
+fn text_hash(entry: &LocaEntry) -> String {
+    let text = entry.orig.as_ref().map_or("", Token::as_str);
+    format!("{:08x}", murmur3_32(&mut Cursor::new(text), 0).unwrap())
+}
+
 fn get_file_lang(filename: &OsStr) -> Option<Language> {
     // Deliberate discrepancy here between the check and the error msg below.
     // `l_{}` anywhere in the filename works, but `_l_{}.yml` is still recommended.
@@ -276,6 +657,18 @@ fn get_file_lang(filename: &OsStr) -> Option<Language> {
 }
 
 impl Localization {
+    /// Remember the vanilla localizations that mod localizations replace, instead of dropping
+    /// them. This has to be set before loading.
+    pub fn keep_overridden(&mut self) {
+        self.keep_overridden = true;
+    }
+
+    /// Remember where the mod's own keys are first used, for the translators. This has to be set
+    /// before validating.
+    pub fn collect_used_at(&mut self) {
+        self.collect_used_at = true;
+    }
+
     // TODO: Remove `+ '_` in edition 2024
     fn iter_lang_idx(&self) -> impl Iterator<Item = usize> + '_ {
         (0..Language::COUNT).filter(|i| self.mod_langs[*i])
@@ -311,11 +704,30 @@ impl Localization {
             .flat_map(|hash| hash.values().map(|item| &item.key))
     }
 
+    /// Iterate over the vanilla localization keys in one language and their unparsed values,
+    /// including the ones that were replaced by the mod if they were kept.
+    pub fn iter_vanilla(&self, lang: Language) -> impl Iterator<Item = (&Token, &str)> {
+        self.locas[lang.to_idx()]
+            .values()
+            .filter(|entry| entry.key.loc.kind.counts_as_vanilla())
+            .chain(self.overridden[lang.to_idx()].values())
+            .map(|entry| (&entry.key, entry.orig.as_ref().map_or("", Token::as_str)))
+    }
+
+    pub(crate) fn get_entry(&self, lang: Language, key: &str) -> Option<&LocaEntry> {
+        self.locas[lang.to_idx()].get(key)
+    }
+
+    /// Return the key token of the localization in use for this key and language, if any.
+    pub fn get_key(&self, lang: Language, key: &str) -> Option<&Token> {
+        self.locas[lang.to_idx()].get(key).map(|entry| &entry.key)
+    }
+
     pub fn verify_exists_implied(&self, key: &str, token: &Token, max_sev: Severity) {
         if key.is_empty() {
             return;
         }
-        self.mark_used(key);
+        self.mark_used_at(key, token.loc);
         let mut langs_missing = Vec::new();
         for lang in self.iter_lang_idx() {
             if !self.locas[lang].contains_key(key) {
@@ -341,7 +753,7 @@ impl Localization {
                 .push();
             return;
         }
-        self.mark_used(name.as_str());
+        self.mark_used_at(name.as_str(), name.loc);
         let mut langs_missing = Vec::new();
         for lang in self.iter_lang_idx() {
             if !self.locas[lang].contains_key(name.as_str()) {
@@ -390,7 +802,7 @@ impl Localization {
             return;
         }
         if let Some(lang) = lang {
-            self.mark_used(key);
+            self.mark_used_at(key, token.loc);
             if !self.exists_lang(key, lang) {
                 let msg = format!("missing {lang} localization key {key}");
                 // TODO: get confidence level from caller
@@ -401,6 +813,28 @@ impl Localization {
         }
     }
 
+    /// Like [`Self::mark_used`], but also remember where the mod's own keys are used, for the
+    /// translators, if `collect_used_at` is set.
+    fn mark_used_at(&self, key: &str, loc: Loc) {
+        self.mark_used(key);
+        if !self.collect_used_at {
+            return;
+        }
+        let is_mod_key = self.locas[self.reference_lang.to_idx()]
+            .get(key)
+            .is_some_and(|entry| entry.key.loc.kind == FileKind::Mod);
+        if is_mod_key {
+            // Keep the first use in file order, so that the result doesn't depend on the order
+            // of validation.
+            let order = |loc: Loc| (loc.kind.counts_as_vanilla(), loc.pathname(), loc.line);
+            let mut used_at = self.used_at.lock().unwrap();
+            let first = used_at.entry(key.to_string()).or_insert(loc);
+            if order(loc) < order(*first) {
+                *first = loc;
+            }
+        }
+    }
+
     pub fn mark_used(&self, key: &str) {
         for lang in self.iter_lang_idx() {
             if let Some(entry) = self.locas[lang].get(key) {
@@ -446,7 +880,6 @@ impl Localization {
                     Self::check_loca_code(value, data, sc, lang);
                 }
             }
-            // TODO: validate the formatting codes
             LocaValue::Code(chain, format) => {
                 // |E is the formatting used for game concepts in ck3
                 #[cfg(feature = "ck3")]
@@ -457,6 +890,7 @@ impl Localization {
                                 if !is_builtin_macro(name) {
                                     data.verify_exists(Item::GameConcept, name);
                                 }
+                                validate_loca_format(format, Datatype::Unknown, data);
                                 return;
                             }
                         }
@@ -465,7 +899,7 @@ impl Localization {
 
                 // TODO: datatype is not really Unknown here, it should be a CString or CFixedPoint or some kind of number.
                 // But we can't express that yet.
-                validate_datatypes(
+                let dtype = validate_datatypes(
                     chain,
                     data,
                     sc,
@@ -474,6 +908,9 @@ impl Localization {
                     format.as_ref(),
                     false,
                 );
+                if let Some(format) = format {
+                    validate_loca_format(format, dtype, data);
+                }
             }
             LocaValue::Tooltip(token) => {
                 // TODO: should this be validated with validate_localization_sc ? (remember to avoid infinite loops)
@@ -586,6 +1023,272 @@ impl Localization {
         }
     }
 
+    /// Return the mod's entries in `lang` together with the same keys in the reference language,
+    /// in order.
+    fn translations(&self, lang: Language) -> Vec<(&LocaEntry, &LocaEntry)> {
+        let reference = &self.locas[self.reference_lang.to_idx()];
+        let mut entries: Vec<(&LocaEntry, &LocaEntry)> = self.locas[lang.to_idx()]
+            .values()
+            .filter(|entry| !entry.key.loc.kind.counts_as_vanilla())
+            .filter_map(|entry| reference.get(entry.key.as_str()).map(|r| (entry, r)))
+            .collect();
+        entries.sort_unstable_by_key(|&(entry, _)| entry);
+        entries
+    }
+
+    /// Warn about characters in the mod's localization that are not in any of the fonts the game
+    /// uses for the language.
+    #[cfg(feature = "jomini")]
+    fn check_glyphs(
+        &self,
+        lang: Language,
+        data: &Everything,
+        cmaps: &TigerHashMap<&'static str, Cmap>,
+    ) {
+        let Some(cmap) = glyph_coverage(data, lang, cmaps) else {
+            return;
+        };
+        let mut entries: Vec<&LocaEntry> = self.locas[lang.to_idx()]
+            .values()
+            .filter(|entry| !entry.key.loc.kind.counts_as_vanilla())
+            .collect();
+        entries.sort_unstable();
+        for entry in entries {
+            let mut texts = Vec::new();
+            collect_text(&entry.value, &mut texts);
+            let mut missing = Vec::new();
+            let mut first_loc = None;
+            for token in texts {
+                for (col, c) in token.as_str().chars().enumerate() {
+                    if c.is_ascii_control() || c == ' ' || cmap.contains(c) {
+                        continue;
+                    }
+                    if first_loc.is_none() {
+                        let mut loc = token.loc;
+                        loc.column += u32::try_from(col).expect("internal error: 2^32 columns");
+                        first_loc = Some(loc);
+                    }
+                    if !missing.contains(&c) {
+                        missing.push(c);
+                    }
+                }
+            }
+            if let Some(loc) = first_loc {
+                let chars: Vec<String> =
+                    missing.iter().map(|&c| format!("`{c}` (U+{:04X})", u32::from(c))).collect();
+                let msg = format!("the {lang} fonts have no glyphs for {}", chars.join(", "));
+                let info = "these characters will be shown as boxes in the game";
+                warn(ErrorKey::MissingGlyph).msg(msg).info(info).loc(loc).push();
+            }
+        }
+    }
+
+    /// Report the words in the mod's localization that are not in the dictionary for the
+    /// language, and not among the known words either.
+    fn check_spelling(
+        &self,
+        lang: Language,
+        spelling: &SpellingConfig,
+        known_words: &TigerHashSet<String>,
+    ) {
+        let Some(dict) = spelling.dictionary(lang) else {
+            return;
+        };
+        let mut entries: Vec<&LocaEntry> = self.locas[lang.to_idx()]
+            .values()
+            .filter(|entry| !entry.key.loc.kind.counts_as_vanilla())
+            .collect();
+        entries.sort_unstable();
+        let mut suggestions: TigerHashMap<String, Vec<String>> = TigerHashMap::default();
+        for entry in entries {
+            let mut texts = Vec::new();
+            collect_text(&entry.value, &mut texts);
+            for token in texts {
+                for (col, word) in split_words(token.as_str()) {
+                    if !should_check(word) {
+                        continue;
+                    }
+                    // Dictionaries use the plain apostrophe.
+                    let word = word.replace('’', "'");
+                    if known_words.contains(&word.to_lowercase()) || dict.check(&word) {
+                        continue;
+                    }
+                    let mut loc = token.loc;
+                    loc.column += u32::try_from(col).expect("internal error: 2^32 columns");
+                    let suggestions =
+                        suggestions.entry(word.clone()).or_insert_with(|| dict.suggest(&word));
+                    let info = (!suggestions.is_empty()).then(|| {
+                        let suggestions: Vec<&str> =
+                            suggestions.iter().map(String::as_str).collect();
+                        format!("did you mean {}?", stringify_choices(&suggestions))
+                    });
+                    let msg = format!("possible misspelling `{word}`");
+                    warn(ErrorKey::Spelling).weak().msg(msg).opt_info(info).loc(loc).push();
+                }
+            }
+        }
+    }
+
+    /// Warn about translations that have a lower version number than the same key in the
+    /// reference language, or that don't have the same macros, code scopes, icons, markup, and
+    /// line breaks as it.
+    fn check_translations(&self, lang: Language) {
+        let reference_lang = self.reference_lang;
+        for (entry, reference_entry) in self.translations(lang) {
+            if entry.version < reference_entry.version {
+                let msg = format!("{lang} localization is older than the {reference_lang} one");
+                let info = format!(
+                    "its version is {}, while the {reference_lang} text is version {}",
+                    entry.version, reference_entry.version
+                );
+                warn(ErrorKey::StaleTranslation)
+                    .msg(msg)
+                    .info(info)
+                    .loc(&entry.key)
+                    .loc_msg(&reference_entry.key, reference_lang.to_string())
+                    .push();
+            }
+
+            let structure = LocaStructure::new(&entry.value);
+            let reference_structure = LocaStructure::new(&reference_entry.value);
+            if structure.has_error || reference_structure.has_error {
+                continue;
+            }
+            let differences = structure.differences(&reference_structure);
+            if !differences.is_empty() {
+                let msg = format!("{lang} localization does not match the {reference_lang} one");
+                let info = differences.join("; ");
+                warn(ErrorKey::LocalizationStructure)
+                    .msg(msg)
+                    .info(info)
+                    .loc(&entry.key)
+                    .loc_msg(&reference_entry.key, reference_lang.to_string())
+                    .push();
+            }
+        }
+    }
+
+    /// Warn about translations whose reference language text changed since they were last
+    /// edited, according to the hashes recorded in the file at `path`. Then update the file.
+    ///
+    /// The file remembers, for each translation, a hash of its own text and of the reference text
+    /// at the time. A translation whose text changed is assumed to be up to date again.
+    pub fn check_translation_hashes(&self, path: &Path) -> Result<()> {
+        let reference_lang = self.reference_lang;
+        if !self.mod_langs[reference_lang.to_idx()] {
+            // Nothing to compare against, so leave the recorded hashes as they are.
+            return Ok(());
+        }
+        let mut old = read_translation_hashes(path)?;
+        let mut new = TranslationHashes::default();
+        for lang in self.iter_lang_idx().map(Language::from_idx) {
+            if lang == reference_lang {
+                continue;
+            }
+            let mut old_lang = old.remove(&lang.to_string()).unwrap_or_default();
+            let mut new_lang = BTreeMap::new();
+            for (entry, reference_entry) in self.translations(lang) {
+                let current = TranslationHash {
+                    source: text_hash(reference_entry),
+                    translation: text_hash(entry),
+                };
+                let recorded = old_lang
+                    .remove(entry.key.as_str())
+                    .filter(|recorded| recorded.translation == current.translation)
+                    .unwrap_or(current);
+                if recorded.source != text_hash(reference_entry) {
+                    let msg = format!("{lang} localization may be out of date");
+                    let info =
+                        format!("the {reference_lang} text changed since this was last translated");
+                    warn(ErrorKey::StaleTranslation)
+                        .msg(msg)
+                        .info(info)
+                        .loc(&entry.key)
+                        .loc_msg(&reference_entry.key, reference_lang.to_string())
+                        .push();
+                }
+                new_lang.insert(entry.key.to_string(), recorded);
+            }
+            new.insert(lang.to_string(), new_lang);
+        }
+        // Keep what was recorded for the languages that weren't loaded this time.
+        new.extend(old);
+        let mut content = serde_json::to_string_pretty(&new)?;
+        content.push('\n');
+        write(path, content).with_context(|| format!("could not write {}", path.display()))
+    }
+
+    /// The language that translations are compared against.
+    pub fn reference_lang(&self) -> Language {
+        self.reference_lang
+    }
+
+    /// Return the mod's keys in the reference language that are missing in `lang`, or whose
+    /// translation is out of date according to the version numbers or to the translation hashes
+    /// recorded in the file at `hashes_path`.
+    pub fn untranslated(
+        &self,
+        lang: Language,
+        hashes_path: Option<&Path>,
+    ) -> Result<Vec<Untranslated<'_>>> {
+        let hashes = match hashes_path {
+            Some(path) => read_translation_hashes(path)?.remove(&lang.to_string()),
+            None => None,
+        };
+        let mut reference_entries: Vec<&LocaEntry> = self.locas[self.reference_lang.to_idx()]
+            .values()
+            .filter(|entry| entry.key.loc.kind == FileKind::Mod)
+            .collect();
+        reference_entries.sort_unstable();
+        let used_at = self.used_at.lock().unwrap();
+        let mut result = Vec::new();
+        for reference_entry in reference_entries {
+            let key = reference_entry.key.as_str();
+            let current = self.locas[lang.to_idx()].get(key);
+            if let Some(entry) = current {
+                let stale_by_hash =
+                    hashes.as_ref().and_then(|hashes| hashes.get(key)).is_some_and(|recorded| {
+                        recorded.translation == text_hash(entry)
+                            && recorded.source != text_hash(reference_entry)
+                    });
+                if entry.version >= reference_entry.version && !stale_by_hash {
+                    continue;
+                }
+            }
+            result.push(Untranslated {
+                key: &reference_entry.key,
+                source: reference_entry.orig.as_ref().map_or("", Token::as_str),
+                current: current.map(|entry| entry.orig.as_ref().map_or("", Token::as_str)),
+                used_at: used_at.get(key).copied(),
+            });
+        }
+        Ok(result)
+    }
+
+    /// Return where the translation of the mod's reference language `key` into `lang` belongs:
+    /// the path of the mod's file that already has it, or else the path corresponding to the
+    /// reference language file. Also return the version number it should have, and its current
+    /// text if any.
+    pub fn translation_destination(
+        &self,
+        lang: Language,
+        key: &str,
+    ) -> Option<(PathBuf, u32, Option<&str>)> {
+        let reference_entry = self.locas[self.reference_lang.to_idx()].get(key)?;
+        if reference_entry.key.loc.kind != FileKind::Mod {
+            return None;
+        }
+        let current = self.locas[lang.to_idx()].get(key);
+        let text = current.map(|entry| entry.orig.as_ref().map_or("", Token::as_str));
+        let path = match current {
+            Some(entry) if entry.key.loc.kind == FileKind::Mod => {
+                entry.key.loc.pathname().to_path_buf()
+            }
+            _ => translated_path(reference_entry.key.loc.pathname(), self.reference_lang, lang),
+        };
+        Some((path, reference_entry.version, text))
+    }
+
     #[cfg(any(feature = "ck3", feature = "vic3"))]
     fn check_collisions(&self, lang: Language) {
         for (k, v) in self.all_collision_keys(lang) {
@@ -606,6 +1309,12 @@ impl Localization {
 
     // This is in pass2 to make sure all `validated` entries have been marked.
     pub fn validate_pass2(&self, data: &Everything) {
+        let known_words = self.spelling.as_ref().map(|spelling| spelling.known_words(data));
+        // Read the fonts up front, because several languages often share them.
+        #[cfg(feature = "jomini")]
+        let langs: Vec<Language> = self.iter_lang_idx().map(Language::from_idx).collect();
+        #[cfg(feature = "jomini")]
+        let cmaps = &read_cmaps(data, &langs);
         scope(|s| {
             for lang in self.iter_lang_idx() {
                 let loca = &self.locas[lang];
@@ -614,6 +1323,19 @@ impl Localization {
                 #[cfg(any(feature = "ck3", feature = "vic3"))]
                 s.spawn(move |_| self.check_collisions(lang));
 
+                if lang != self.reference_lang && self.mod_langs[self.reference_lang.to_idx()] {
+                    s.spawn(move |_| self.check_translations(lang));
+                }
+
+                #[cfg(feature = "jomini")]
+                s.spawn(move |_| self.check_glyphs(lang, data, cmaps));
+
+                if let (Some(spelling), Some(known_words)) = (&self.spelling, &known_words) {
+                    if separates_words(lang) {
+                        s.spawn(move |_| self.check_spelling(lang, spelling, known_words));
+                    }
+                }
+
                 // Collect and sort the entries before looping, to create more stable output
                 let mut unvalidated_entries: Vec<&LocaEntry> =
                     loca.values().filter(|e| !e.validated.load(Relaxed)).collect();
@@ -714,6 +1436,20 @@ impl FileHandler<(Language, Vec<LocaEntry>)> for Localization {
                 }
             }
             self.check_langs = langs;
+
+            if let Some(token) = block.get_field_value("reference") {
+                if let Ok(lang) = Language::try_from(token.as_str()) {
+                    self.reference_lang = lang;
+                } else {
+                    let msg = "unknown language";
+                    let info = format!("expected one of {}", *LANG_LIST);
+                    err(ErrorKey::Config).msg(msg).info(info).loc(token).push();
+                }
+            }
+        }
+
+        if let Some(block) = config.get_field_block("spelling") {
+            self.spelling = SpellingConfig::from_config(block);
         }
     }
 
@@ -798,7 +1534,12 @@ impl FileHandler<(Language, Vec<LocaEntry>)> for Localization {
                     }
                 }
             }
-            hash.insert(loca.key.to_string(), loca);
+            let keep = self.keep_overridden && !entry.kind().counts_as_vanilla();
+            if let Some(other) = hash.insert(loca.key.to_string(), loca) {
+                if keep && other.key.loc.kind.counts_as_vanilla() {
+                    self.overridden[filelang.to_idx()].insert(other.key.to_string(), other);
+                }
+            }
         }
     }
 }
@@ -809,6 +1550,12 @@ impl Default for Localization {
             check_langs: bitarr![u16, Lsb0; 1; Language::COUNT],
             mod_langs: bitarr![u16, Lsb0; 0; Language::COUNT],
             locas: Box::new(std::array::from_fn(|_| TigerHashMap::default())),
+            overridden: Box::new(std::array::from_fn(|_| TigerHashMap::default())),
+            keep_overridden: false,
+            reference_lang: Language::English,
+            used_at: Mutex::default(),
+            collect_used_at: false,
+            spelling: None,
         }
     }
 }
@@ -848,10 +1595,12 @@ fn normal_capitalization_for_name(name: &str) -> bool {
     true
 }
 
-#[cfg(all(test, feature = "ck3"))]
+#[cfg(test)]
 mod tests {
     use super::*;
+    use crate::report::{take_reports_for, LogReport};
 
+    #[cfg(feature = "ck3")]
     #[test]
     fn test_only_latin_script() {
         let mut langs = vec!["english", "french", "german"];
@@ -862,6 +1611,7 @@ mod tests {
         assert!(only_latin_script(&langs));
     }
 
+    #[cfg(feature = "ck3")]
     #[test]
     fn test_normal_capitalization_for_name() {
         assert!(normal_capitalization_for_name("George"));
@@ -872,4 +1622,78 @@ mod tests {
         assert!(normal_capitalization_for_name("Abu Abdallah Muhammad"));
         assert!(!normal_capitalization_for_name("AbuAbdallahMuhammad"));
     }
+
+    /// Check `format` as the formatting of code that returns `dtype`, and return the reports.
+    /// `desc` must be different for each call, to keep the reports apart.
+    fn check_format(format: &str, dtype: Datatype, desc: &str) -> Vec<LogReport> {
+        let loc = Loc::for_file(PathBuf::from(desc), FileKind::Mod, PathBuf::from(desc));
+        let kind = |c| format_codes().iter().find(|(code, _)| *code == c).map(|(_, kind)| *kind);
+        check_loca_format(&Token::new(format, loc), dtype, kind);
+        take_reports_for(desc)
+    }
+
+    /// Return a code of this kind from the game's table, if it has one.
+    fn code_of_kind(kind: FormatKind) -> Option<String> {
+        format_codes().iter().find(|(_, k)| *k == kind).map(|(c, _)| c.to_string())
+    }
+
+    #[test]
+    fn test_format_number() {
+        let code = code_of_kind(FormatKind::Number).unwrap();
+        assert!(check_format(&code, Datatype::int32, "test_number_int.yml").is_empty());
+        assert!(check_format(&code, Datatype::Unknown, "test_number_unknown.yml").is_empty());
+        let reports = check_format(&code, Datatype::CString, "test_number_string.yml");
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].msg.contains("is for numbers"));
+    }
+
+    #[test]
+    fn test_format_text() {
+        for kind in [FormatKind::Text, FormatKind::Concept] {
+            let Some(code) = code_of_kind(kind) else {
+                continue;
+            };
+            let desc = format!("test_{kind:?}_string.yml");
+            assert!(check_format(&code, Datatype::CString, &desc).is_empty());
+            let desc = format!("test_{kind:?}_int.yml");
+            let reports = check_format(&code, Datatype::int32, &desc);
+            assert_eq!(reports.len(), 1);
+            assert!(reports[0].msg.contains("is for text"));
+        }
+    }
+
+    #[test]
+    fn test_format_color() {
+        let Some(code) = code_of_kind(FormatKind::Color) else {
+            return;
+        };
+        assert!(check_format(&code, Datatype::int32, "test_color_int.yml").is_empty());
+        assert!(check_format(&code, Datatype::CString, "test_color_string.yml").is_empty());
+    }
+
+    #[test]
+    fn test_format_conflict() {
+        let number = code_of_kind(FormatKind::Number).unwrap();
+        let Some(text) =
+            code_of_kind(FormatKind::Text).or_else(|| code_of_kind(FormatKind::Concept))
+        else {
+            return;
+        };
+        let reports =
+            check_format(&format!("{number}{text}"), Datatype::Unknown, "test_conflict.yml");
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].pointers.len(), 2);
+        assert_eq!(reports[0].pointers[1].loc.column, reports[0].pointers[0].loc.column + 1);
+    }
+
+    #[test]
+    fn test_format_unknown() {
+        assert!(!format_codes().iter().any(|(c, _)| *c == '?'));
+        let number = code_of_kind(FormatKind::Number).unwrap();
+        let reports = check_format(&format!("{number}|?"), Datatype::int32, "test_unknown.yml");
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].msg.contains("unknown formatting code `?`"));
+        // The token starts at column 0, and the `?` is its third character.
+        assert_eq!(reports[0].primary().loc.column, 2);
+    }
 }
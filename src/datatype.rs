@@ -250,6 +250,32 @@ impl CodeChain {
     }
 }
 
+impl Display for CodeChain {
+    /// Convert a `CodeChain` back to the form it has in script, without the surrounding `[` `]`.
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        for (i, code) in self.codes.iter().enumerate() {
+            if i > 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", code.name)?;
+            if !code.arguments.is_empty() {
+                write!(f, "(")?;
+                for (j, arg) in code.arguments.iter().enumerate() {
+                    if j > 0 {
+                        write!(f, ", ")?;
+                    }
+                    match arg {
+                        CodeArg::Chain(chain) => write!(f, "{chain}")?,
+                        CodeArg::Literal(token) => write!(f, "'{token}'")?,
+                    }
+                }
+                write!(f, ")")?;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// [`Arg`] is the counterpart to [`CodeArg`]. Where `CodeArg` represents an actual argument given
 /// in a codechain string, the `Arg` represents what kind of argument is expected by a promote or
 /// function.
@@ -373,6 +399,8 @@ fn validate_argument(
 /// * `expect_promote` is true iff the chain is expected to end on a promote rather than on a function.
 ///   Promotes and functions are very similar but they are defined separately in the datafunction tables
 ///   and usually only a function can end a chain.
+///
+/// Returns the datatype that the chain evaluates to, or `Datatype::Unknown` if that can't be determined.
 #[allow(unused_variables)] // TODO HOI4: use `format`
 pub fn validate_datatypes(
     chain: &CodeChain,
@@ -382,7 +410,7 @@ pub fn validate_datatypes(
     lang: Option<Language>,
     format: Option<&Token>,
     expect_promote: bool,
-) {
+) -> Datatype {
     let mut curtype = Datatype::Unknown;
     #[allow(unused_mut)] // imperator does not need the mut
     let mut codes = Cow::from(&chain.codes[..]);
@@ -401,11 +429,11 @@ pub fn validate_datatypes(
                         let msg =
                             format!("substituted data bindings {macro_count} times, giving up");
                         err(ErrorKey::Macro).msg(msg).loc(&codes[i].name).push();
-                        return;
+                        return Datatype::Unknown;
                     }
                     codes.to_mut().splice(i..=i, replacement.codes);
                 } else {
-                    return;
+                    return Datatype::Unknown;
                 }
             }
         }
@@ -419,7 +447,7 @@ pub fn validate_datatypes(
         if code.name.is("") {
             // TODO: verify if the game engine is okay with this
             warn(ErrorKey::Datafunctions).msg("empty fragment").loc(&code.name).push();
-            return;
+            return Datatype::Unknown;
         }
 
         let lookup_gf = lookup_global_function(code.name.as_str());
@@ -456,7 +484,7 @@ pub fn validate_datatypes(
                 LookupResult::WrongType => {
                     let msg = format!("{} cannot follow a {curtype} promote", code.name);
                     warn(ErrorKey::Datafunctions).msg(msg).loc(&code.name).push();
-                    return;
+                    return Datatype::Unknown;
                 }
                 LookupResult::NotFound => (),
             }
@@ -470,7 +498,7 @@ pub fn validate_datatypes(
                 LookupResult::WrongType => {
                     let msg = format!("{} cannot follow a {curtype} promote", code.name);
                     warn(ErrorKey::Datafunctions).msg(msg).loc(&code.name).push();
-                    return;
+                    return Datatype::Unknown;
                 }
                 LookupResult::NotFound => (),
             }
@@ -494,33 +522,33 @@ pub fn validate_datatypes(
             if is_first && (p_found || f_found) && !gp_found && !gf_found {
                 let msg = format!("{} cannot be the first in a chain", code.name);
                 warn(ErrorKey::Datafunctions).msg(msg).loc(&code.name).push();
-                return;
+                return Datatype::Unknown;
             }
             if is_last && (gp_found || p_found) && !gf_found && !f_found && !expect_promote {
                 let msg = format!("{} cannot be last in a chain", code.name);
                 warn(ErrorKey::Datafunctions).msg(msg).loc(&code.name).push();
-                return;
+                return Datatype::Unknown;
             }
             if expect_promote && (gf_found || f_found) {
                 let msg = format!("{} cannot be used in this field", code.name);
                 warn(ErrorKey::Datafunctions).msg(msg).loc(&code.name).push();
-                return;
+                return Datatype::Unknown;
             }
             if !is_first && (gp_found || gf_found) && !p_found && !f_found {
                 let msg = format!("{} must be the first in a chain", code.name);
                 warn(ErrorKey::Datafunctions).msg(msg).loc(&code.name).push();
-                return;
+                return Datatype::Unknown;
             }
             if !is_last && (gf_found || f_found) && !gp_found && !p_found {
                 let msg = format!("{} must be last in the chain", code.name);
                 warn(ErrorKey::Datafunctions).msg(msg).loc(&code.name).push();
-                return;
+                return Datatype::Unknown;
             }
             // A catch-all condition if none of the above match
             if gp_found || gf_found || p_found || f_found {
                 let msg = format!("{} is improperly used here", code.name);
                 warn(ErrorKey::Datafunctions).msg(msg).loc(&code.name).push();
-                return;
+                return Datatype::Unknown;
             }
         }
 
@@ -689,7 +717,7 @@ pub fn validate_datatypes(
             } else {
                 warn(ErrorKey::Datafunctions).msg(msg).loc(&code.name).push();
             }
-            return;
+            return Datatype::Unknown;
         }
 
         // This `if let` skips this check if args is `Args::Unknown`
@@ -702,7 +730,7 @@ pub fn validate_datatypes(
                     code.arguments.len()
                 );
                 warn(ErrorKey::Datafunctions).msg(msg).loc(&code.name).push();
-                return;
+                return Datatype::Unknown;
             }
         }
 
@@ -800,18 +828,19 @@ pub fn validate_datatypes(
                     let msg =
                         format!("{} returns {curtype} but a scope type is needed here", code.name);
                     warn(ErrorKey::Datafunctions).msg(msg).loc(&code.name).push();
-                    return;
+                    return Datatype::Unknown;
                 }
             } else {
                 let msg =
                     format!("{} returns {curtype} but a {expect_type} is needed here", code.name);
                 warn(ErrorKey::Datafunctions).msg(msg).loc(&code.name).push();
-                return;
+                return Datatype::Unknown;
             }
         }
 
         i += 1;
     }
+    curtype
 }
 
 fn lookup_global_promote(lookup_name: &str) -> Option<(Args, Datatype)> {
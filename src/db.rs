@@ -35,6 +35,11 @@ pub struct Db {
     anonymous: Vec<DbEntry>,
     /// Lowercased registry of database items and flags, for case insensitive lookups
     items_lc: Vec<TigerHashMap<Lowercase<'static>, &'static str>>,
+    /// Vanilla items that were replaced by mod items with the same key.
+    /// They are only kept if `keep_overridden` is set.
+    /// The `Vec` is indexed with an `Item` discriminant.
+    overridden: Vec<TigerHashMap<&'static str, (Token, Block)>>,
+    keep_overridden: bool,
 }
 
 impl Default for Db {
@@ -44,6 +49,8 @@ impl Default for Db {
             flags: (0..Item::COUNT).map(|_| (TigerHashSet::default(), None)).collect(),
             anonymous: Vec::new(),
             items_lc: (0..Item::COUNT).map(|_| TigerHashMap::default()).collect(),
+            overridden: (0..Item::COUNT).map(|_| TigerHashMap::default()).collect(),
+            keep_overridden: false,
         }
     }
 }
@@ -60,7 +67,7 @@ impl Db {
             }
         }
         self.items_lc[item as usize].insert(Lowercase::new(key.as_str()), key.as_str());
-        self.database[item as usize].insert(key.as_str(), DbEntry { key, block, kind });
+        self.insert(item, DbEntry { key, block, kind });
     }
 
     #[allow(dead_code)]
@@ -81,7 +88,25 @@ impl Db {
             }
         }
         self.items_lc[item as usize].insert(Lowercase::new(key.as_str()), key.as_str());
-        self.database[item as usize].insert(key.as_str(), DbEntry { key, block, kind });
+        self.insert(item, DbEntry { key, block, kind });
+    }
+
+    /// Remember the vanilla items that mod items replace, instead of dropping them.
+    /// This has to be set before loading.
+    pub fn keep_overridden(&mut self) {
+        self.keep_overridden = true;
+    }
+
+    /// Insert the entry, remembering the vanilla entry it replaces if it's from a mod and
+    /// `keep_overridden` is set.
+    fn insert(&mut self, item: Item, entry: DbEntry) {
+        let key = entry.key.as_str();
+        let keep = self.keep_overridden && !entry.key.loc.kind.counts_as_vanilla();
+        if let Some(other) = self.database[item as usize].insert(key, entry) {
+            if keep && other.key.loc.kind.counts_as_vanilla() {
+                self.overridden[item as usize].insert(key, (other.key, other.block));
+            }
+        }
     }
 
     #[cfg(feature = "hoi4")]
@@ -241,6 +266,21 @@ impl Db {
             .map(|entry| &entry.key)
             .chain(self.flags[itype as usize].0.iter())
     }
+
+    /// Iterate over the vanilla versions of the items, including the ones that were replaced by
+    /// the mod if they were kept. Flags are not included.
+    pub fn iter_vanilla_key_block(&self, itype: Item) -> impl Iterator<Item = (&Token, &Block)> {
+        self.database[itype as usize]
+            .values()
+            .filter(|entry| entry.key.loc.kind.counts_as_vanilla())
+            .map(|entry| (&entry.key, &entry.block))
+            .chain(self.overridden[itype as usize].values().map(|(key, block)| (key, block)))
+    }
+
+    /// Return the vanilla version of an item that was replaced by the mod, if it was kept.
+    pub fn get_overridden(&self, itype: Item, key: &str) -> Option<(&Token, &Block)> {
+        self.overridden[itype as usize].get(key).map(|(key, block)| (key, block))
+    }
 }
 
 #[derive(Debug)]
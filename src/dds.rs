@@ -11,10 +11,38 @@ use crate::report::{err, tips, warn, ErrorKey};
 #[cfg(feature = "ck3")]
 use crate::token::Token;
 
-const DDS_HEADER_SIZE: usize = 124;
+/// The size of the `DDS ` magic number plus the header that follows it.
+const DDS_HEADER_SIZE: usize = 128;
+/// The size of the extra header that follows if the four-character code is `DX10`.
+const DDS_DX10_HEADER_SIZE: usize = 20;
+
+const DDS_SIZE_OFFSET: usize = 4;
+const DDS_FLAGS_OFFSET: usize = 8;
 const DDS_HEIGHT_OFFSET: usize = 12;
 const DDS_WIDTH_OFFSET: usize = 16;
+const DDS_DEPTH_OFFSET: usize = 24;
+const DDS_MIPMAPCOUNT_OFFSET: usize = 28;
+const DDS_PIXELFORMAT_SIZE_OFFSET: usize = 76;
 const DDS_PIXELFORMAT_FLAGS_OFFSET: usize = 80;
+const DDS_FOURCC_OFFSET: usize = 84;
+const DDS_RGBBITCOUNT_OFFSET: usize = 88;
+const DDS_CAPS2_OFFSET: usize = 112;
+const DX10_FORMAT_OFFSET: usize = DDS_HEADER_SIZE;
+const DX10_DIMENSION_OFFSET: usize = DDS_HEADER_SIZE + 4;
+const DX10_MISC_FLAG_OFFSET: usize = DDS_HEADER_SIZE + 8;
+const DX10_ARRAY_SIZE_OFFSET: usize = DDS_HEADER_SIZE + 12;
+
+const DDSD_MIPMAPCOUNT: u32 = 0x2_0000;
+const DDSD_DEPTH: u32 = 0x80_0000;
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_ALPHA: u32 = 0x2;
+const DDPF_FOURCC: u32 = 0x4;
+const DDPF_RGB: u32 = 0x40;
+const DDPF_LUMINANCE: u32 = 0x2_0000;
+const DDSCAPS2_CUBEMAP: u32 = 0x200;
+const DDSCAPS2_VOLUME: u32 = 0x20_0000;
+const DX10_DIMENSION_TEXTURE3D: u32 = 4;
+const DX10_MISC_TEXTURECUBE: u32 = 0x4;
 
 fn from_le32(buffer: &[u8], offset: usize) -> u32 {
     u32::from(buffer[offset])
@@ -30,13 +58,14 @@ pub struct DdsFiles {
 
 impl DdsFiles {
     fn load_dds(entry: &FileEntry) -> Result<Option<DdsInfo>> {
-        if metadata(entry.fullpath())?.len() == 0 {
+        let file_size = metadata(entry.fullpath())?.len();
+        if file_size == 0 {
             warn(ErrorKey::ImageFormat).msg("empty file").loc(entry).push();
             return Ok(None);
         }
-        let mut f = File::open(entry.fullpath())?;
-        let mut buffer = [0; DDS_HEADER_SIZE];
-        f.read_exact(&mut buffer)?;
+        let f = File::open(entry.fullpath())?;
+        let mut buffer = Vec::with_capacity(DDS_HEADER_SIZE + DDS_DX10_HEADER_SIZE);
+        f.take((DDS_HEADER_SIZE + DDS_DX10_HEADER_SIZE) as u64).read_to_end(&mut buffer)?;
         if buffer.starts_with(b"\x89PNG") {
             let msg = "actually a PNG";
             let info =
@@ -48,7 +77,22 @@ impl DdsFiles {
             err(ErrorKey::ImageFormat).msg("not a DDS file").loc(entry).push();
             return Ok(None);
         }
-        Ok(Some(DdsInfo::new(entry.clone(), &buffer)))
+        let dx10 = buffer.get(DDS_FOURCC_OFFSET..DDS_FOURCC_OFFSET + 4) == Some(b"DX10");
+        let header_size =
+            if dx10 { DDS_HEADER_SIZE + DDS_DX10_HEADER_SIZE } else { DDS_HEADER_SIZE };
+        if buffer.len() < header_size {
+            err(ErrorKey::ImageFormat).msg("file is truncated in its header").loc(entry).push();
+            return Ok(None);
+        }
+        if from_le32(&buffer, DDS_SIZE_OFFSET) != 124
+            || from_le32(&buffer, DDS_PIXELFORMAT_SIZE_OFFSET) != 32
+        {
+            let msg = "DDS header is corrupt";
+            let info = "the header and pixel format sizes do not have their fixed values";
+            err(ErrorKey::ImageFormat).msg(msg).info(info).loc(entry).push();
+            return Ok(None);
+        }
+        Ok(Some(DdsInfo::new(entry.clone(), &buffer, file_size)))
     }
 
     fn handle_dds(&mut self, entry: &FileEntry, info: DdsInfo) {
@@ -107,26 +151,244 @@ impl FileHandler<DdsInfo> for DdsFiles {
     }
 }
 
+/// How the pixel data of a format is laid out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Layout {
+    /// Block compression, with this many bytes per block of 4x4 pixels.
+    Block(u64),
+    /// Uncompressed, with this many bits per pixel.
+    Pixel(u64),
+}
+
+/// Which channels a format stores.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Channels {
+    One,
+    Two,
+    Color,
+    ColorAlpha,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct DdsFormat {
+    name: &'static str,
+    layout: Layout,
+    channels: Channels,
+}
+
+impl DdsFormat {
+    const fn new(name: &'static str, layout: Layout, channels: Channels) -> Self {
+        Self { name, layout, channels }
+    }
+
+    /// The formats that are identified by the four-character code of the pixel format, either
+    /// as four letters or as an old Direct3D format number.
+    fn from_fourcc(fourcc: &[u8]) -> Option<Self> {
+        use Channels::*;
+        use Layout::*;
+        Some(match fourcc {
+            b"DXT1" => Self::new("BC1 (DXT1)", Block(8), Color),
+            b"DXT2" | b"DXT3" => Self::new("BC2 (DXT3)", Block(16), ColorAlpha),
+            b"DXT4" | b"DXT5" => Self::new("BC3 (DXT5)", Block(16), ColorAlpha),
+            b"ATI1" | b"BC4U" | b"BC4S" => Self::new("BC4", Block(8), One),
+            b"ATI2" | b"BC5U" | b"BC5S" => Self::new("BC5", Block(16), Two),
+            [36, 0, 0, 0] => Self::new("A16B16G16R16", Pixel(64), ColorAlpha),
+            [111, 0, 0, 0] => Self::new("R16F", Pixel(16), One),
+            [112, 0, 0, 0] => Self::new("G16R16F", Pixel(32), Two),
+            [113, 0, 0, 0] => Self::new("A16B16G16R16F", Pixel(64), ColorAlpha),
+            [114, 0, 0, 0] => Self::new("R32F", Pixel(32), One),
+            [115, 0, 0, 0] => Self::new("G32R32F", Pixel(64), Two),
+            [116, 0, 0, 0] => Self::new("A32B32G32R32F", Pixel(128), ColorAlpha),
+            _ => return None,
+        })
+    }
+
+    /// The formats that are identified by their DXGI number in the DX10 header.
+    fn from_dxgi(dxgi: u32) -> Option<Self> {
+        use Channels::*;
+        use Layout::*;
+        Some(match dxgi {
+            1..=4 => Self::new("R32G32B32A32", Pixel(128), ColorAlpha),
+            5..=8 => Self::new("R32G32B32", Pixel(96), Color),
+            9..=14 => Self::new("R16G16B16A16", Pixel(64), ColorAlpha),
+            15..=18 => Self::new("R32G32", Pixel(64), Two),
+            23..=25 => Self::new("R10G10B10A2", Pixel(32), ColorAlpha),
+            26 => Self::new("R11G11B10", Pixel(32), Color),
+            27..=32 => Self::new("R8G8B8A8", Pixel(32), ColorAlpha),
+            33..=38 => Self::new("R16G16", Pixel(32), Two),
+            39..=43 => Self::new("R32", Pixel(32), One),
+            48..=52 => Self::new("R8G8", Pixel(16), Two),
+            53..=59 => Self::new("R16", Pixel(16), One),
+            60..=64 => Self::new("R8", Pixel(8), One),
+            65 => Self::new("A8", Pixel(8), One),
+            67 => Self::new("R9G9B9E5", Pixel(32), Color),
+            70..=72 => Self::new("BC1 (DXT1)", Block(8), Color),
+            73..=75 => Self::new("BC2 (DXT3)", Block(16), ColorAlpha),
+            76..=78 => Self::new("BC3 (DXT5)", Block(16), ColorAlpha),
+            79..=81 => Self::new("BC4", Block(8), One),
+            82..=84 => Self::new("BC5", Block(16), Two),
+            85 => Self::new("B5G6R5", Pixel(16), Color),
+            86 => Self::new("B5G5R5A1", Pixel(16), ColorAlpha),
+            87 | 90 | 91 => Self::new("B8G8R8A8", Pixel(32), ColorAlpha),
+            88 | 92 | 93 => Self::new("B8G8R8X8", Pixel(32), Color),
+            94..=96 => Self::new("BC6H", Block(16), Color),
+            97..=99 => Self::new("BC7", Block(16), ColorAlpha),
+            115 => Self::new("B4G4R4A4", Pixel(16), ColorAlpha),
+            _ => return None,
+        })
+    }
+
+    /// The uncompressed formats that are described by the flags and bit count of the pixel
+    /// format instead of by a four-character code.
+    fn from_flags(flags: u32, bits: u32) -> Option<Self> {
+        use Channels::*;
+        use Layout::*;
+        let alpha = flags & DDPF_ALPHAPIXELS != 0;
+        let bits = u64::from(bits);
+        if bits == 0 {
+            None
+        } else if flags & DDPF_RGB != 0 {
+            Some(if alpha {
+                Self::new("uncompressed RGBA", Pixel(bits), ColorAlpha)
+            } else {
+                Self::new("uncompressed RGB", Pixel(bits), Color)
+            })
+        } else if flags & DDPF_LUMINANCE != 0 {
+            Some(if alpha {
+                Self::new("luminance-alpha", Pixel(bits), Two)
+            } else {
+                Self::new("luminance", Pixel(bits), One)
+            })
+        } else if flags & DDPF_ALPHA != 0 {
+            Some(Self::new("alpha-only", Pixel(bits), One))
+        } else {
+            None
+        }
+    }
+
+    /// The number of bytes of one image of the given size in this format.
+    /// Return `None` if the size doesn't fit in 64 bits, which can only happen if the header
+    /// is corrupt.
+    fn image_size(self, width: u32, height: u32) -> Option<u64> {
+        let (width, height) = (u64::from(width), u64::from(height));
+        match self.layout {
+            Layout::Block(bytes) => {
+                width.div_ceil(4).checked_mul(height.div_ceil(4))?.checked_mul(bytes)
+            }
+            Layout::Pixel(bits) => width.checked_mul(bits)?.div_ceil(8).checked_mul(height),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct DdsInfo {
     entry: FileEntry,
-    compressed: bool,
     width: u32,
     height: u32,
+    /// The depth of a volume texture, or 1.
+    depth: u32,
+    /// The number of images of each mipmap level, such as 6 for a cube map.
+    layers: u32,
+    /// The number of mipmap levels, counting the full-size image as one.
+    mipmaps: u32,
+    /// The format, or a description of it if it's not one we know.
+    format: std::result::Result<DdsFormat, String>,
+    header_size: u64,
+    file_size: u64,
 }
 
 impl DdsInfo {
-    pub fn new(entry: FileEntry, header: &[u8]) -> Self {
+    /// Read the info from the header, which must have been checked to be complete.
+    fn new(entry: FileEntry, header: &[u8], file_size: u64) -> Self {
+        let flags = from_le32(header, DDS_FLAGS_OFFSET);
+        let pf_flags = from_le32(header, DDS_PIXELFORMAT_FLAGS_OFFSET);
+        let caps2 = from_le32(header, DDS_CAPS2_OFFSET);
+        let fourcc = &header[DDS_FOURCC_OFFSET..DDS_FOURCC_OFFSET + 4];
+        let mut header_size = DDS_HEADER_SIZE as u64;
+        let mut volume = flags & DDSD_DEPTH != 0 && caps2 & DDSCAPS2_VOLUME != 0;
+        let mut layers = if caps2 & DDSCAPS2_CUBEMAP != 0 { 6 } else { 1 };
+
+        let format = if pf_flags & DDPF_FOURCC == 0 {
+            let bits = from_le32(header, DDS_RGBBITCOUNT_OFFSET);
+            DdsFormat::from_flags(pf_flags, bits)
+                .ok_or_else(|| format!("pixel format flags {pf_flags:#x}"))
+        } else if fourcc == b"DX10" {
+            header_size += DDS_DX10_HEADER_SIZE as u64;
+            volume = from_le32(header, DX10_DIMENSION_OFFSET) == DX10_DIMENSION_TEXTURE3D;
+            let cube = from_le32(header, DX10_MISC_FLAG_OFFSET) & DX10_MISC_TEXTURECUBE != 0;
+            let array_size = from_le32(header, DX10_ARRAY_SIZE_OFFSET).max(1);
+            layers = if cube { 6 * array_size } else { array_size };
+            let dxgi = from_le32(header, DX10_FORMAT_OFFSET);
+            DdsFormat::from_dxgi(dxgi).ok_or_else(|| format!("DXGI format {dxgi}"))
+        } else {
+            DdsFormat::from_fourcc(fourcc).ok_or_else(|| {
+                if fourcc.iter().all(u8::is_ascii_alphanumeric) {
+                    format!("FourCC `{}`", String::from_utf8_lossy(fourcc))
+                } else {
+                    format!("Direct3D format {}", from_le32(header, DDS_FOURCC_OFFSET))
+                }
+            })
+        };
+
+        let mipmaps = if flags & DDSD_MIPMAPCOUNT != 0 {
+            from_le32(header, DDS_MIPMAPCOUNT_OFFSET).max(1)
+        } else {
+            1
+        };
         Self {
             entry,
-            compressed: (from_le32(header, DDS_PIXELFORMAT_FLAGS_OFFSET) & 0x04) != 0,
             width: from_le32(header, DDS_WIDTH_OFFSET),
             height: from_le32(header, DDS_HEIGHT_OFFSET),
+            depth: if volume { from_le32(header, DDS_DEPTH_OFFSET).max(1) } else { 1 },
+            layers,
+            mipmaps,
+            format,
+            header_size,
+            file_size,
+        }
+    }
+
+    /// The most mipmap levels that a texture of this size can have.
+    fn max_mipmaps(&self) -> u32 {
+        u32::BITS - self.width.max(self.height).max(self.depth).leading_zeros()
+    }
+
+    /// The size that the file should have according to its header, or `None` if that doesn't
+    /// fit in 64 bits, which means the header is corrupt.
+    fn expected_size(&self, format: DdsFormat) -> Option<u64> {
+        let mut size = self.header_size;
+        for level in 0..self.mipmaps.min(self.max_mipmaps()) {
+            let width = (self.width >> level).max(1);
+            let height = (self.height >> level).max(1);
+            let depth = (self.depth >> level).max(1);
+            let level_size = format
+                .image_size(width, height)?
+                .checked_mul(u64::from(depth))?
+                .checked_mul(u64::from(self.layers))?;
+            size = size.checked_add(level_size)?;
         }
+        Some(size)
     }
 
     fn validate(&self) {
-        if self.compressed && !(self.width % 4 == 0 || self.height % 4 == 0) {
+        let format = match &self.format {
+            Ok(format) => *format,
+            Err(description) => {
+                let msg = format!("unknown texture format: {description}");
+                let info = "the game may show this texture in pink or crash on some graphics cards";
+                err(ErrorKey::ImageFormat).msg(msg).info(info).loc(&self.entry).push();
+                return;
+            }
+        };
+        if self.width == 0 || self.height == 0 {
+            let msg = format!("texture size {}x{} is empty", self.width, self.height);
+            err(ErrorKey::ImageSize).msg(msg).loc(&self.entry).push();
+            return;
+        }
+
+        if matches!(format.layout, Layout::Block(_))
+            && (self.width % 4 != 0 || self.height % 4 != 0)
+        {
             let msg = "compressed DDS must have width and height divisible by 4";
             let info = format!(
                 "DDS file is {}x{}, which can cause scaling problems and graphical artifacts",
@@ -134,5 +396,138 @@ impl DdsInfo {
             );
             err(ErrorKey::ImageSize).msg(msg).info(info).loc(&self.entry).push();
         }
+
+        let max_mipmaps = self.max_mipmaps();
+        if self.mipmaps > max_mipmaps {
+            let msg = format!("DDS header says there are {} mipmap levels", self.mipmaps);
+            let info = format!(
+                "textures of size {}x{} can have at most {max_mipmaps}",
+                self.width, self.height
+            );
+            err(ErrorKey::ImageFormat).msg(msg).info(info).loc(&self.entry).push();
+        }
+
+        let Some(expected_size) = self.expected_size(format) else {
+            let msg = "DDS header is corrupt";
+            let info = format!(
+                "the texture it describes, {}x{}x{} with {} layers, is too large to exist",
+                self.width, self.height, self.depth, self.layers
+            );
+            err(ErrorKey::ImageFormat).msg(msg).info(info).loc(&self.entry).push();
+            return;
+        };
+        if self.file_size < expected_size {
+            let msg = "file is truncated";
+            let info = format!(
+                "the header describes {expected_size} bytes of {} texture, but the file has {}",
+                format.name, self.file_size
+            );
+            err(ErrorKey::ImageFormat).msg(msg).info(info).loc(&self.entry).push();
+        }
+
+        self.validate_usage(format);
+    }
+
+    /// Check the constraints that depend on what the texture is used for, which is known from
+    /// its path.
+    fn validate_usage(&self, format: DdsFormat) {
+        let path = self.entry.path().to_string_lossy().to_lowercase();
+        let filename = path.rsplit('/').next().unwrap_or(&path);
+
+        if (path.starts_with("gfx/map/terrain/") || path.starts_with("gfx/coat_of_arms/"))
+            && !(self.width.is_power_of_two() && self.height.is_power_of_two())
+        {
+            let msg = "this texture must have a width and height that are powers of 2";
+            let info = format!("it is {}x{}", self.width, self.height);
+            warn(ErrorKey::ImageSize).msg(msg).info(info).loc(&self.entry).push();
+        }
+
+        let model_texture = filename.ends_with("_diffuse.dds")
+            || filename.ends_with("_normal.dds")
+            || filename.ends_with("_properties.dds");
+        if filename.ends_with("_normal.dds") {
+            if format.channels == Channels::One {
+                let msg = format!("normal map is in {}, which has only one channel", format.name);
+                let info =
+                    "normal maps need at least two channels, such as in BC3 (DXT5), BC5 or BC7";
+                err(ErrorKey::ImageFormat).msg(msg).info(info).loc(&self.entry).push();
+            } else if format.name.starts_with("BC1") {
+                let msg = "normal map in BC1 (DXT1) loses a lot of detail";
+                let info = "BC3 (DXT5), BC5 or BC7 give smoother lighting";
+                tips(ErrorKey::ImageFormat).msg(msg).info(info).loc(&self.entry).push();
+            }
+        } else if filename.ends_with("_properties.dds") {
+            if format.channels != Channels::ColorAlpha {
+                let msg =
+                    format!("properties texture is in {}, which has no alpha channel", format.name);
+                let info = "properties textures use all four channels";
+                warn(ErrorKey::ImageFormat).msg(msg).info(info).loc(&self.entry).push();
+            }
+        } else if (filename.ends_with("_diffuse.dds") || path.starts_with("gfx/interface/icons/"))
+            && matches!(format.channels, Channels::One | Channels::Two)
+        {
+            let msg = format!("texture is in {}, which does not store full color", format.name);
+            let info = "the picture will show in shades of red or green";
+            warn(ErrorKey::ImageFormat).msg(msg).info(info).loc(&self.entry).push();
+        }
+
+        if model_texture && self.mipmaps == 1 && self.width.max(self.height) > 4 {
+            let msg = "texture has no mipmaps";
+            let info = "without mipmaps, models will look grainy and flicker when zoomed out";
+            tips(ErrorKey::ImageFormat).msg(msg).info(info).loc(&self.entry).push();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fileset::FileKind;
+
+    fn header(width: u32, height: u32, mipmaps: u32, fourcc: &[u8; 4]) -> Vec<u8> {
+        let mut header = vec![0; DDS_HEADER_SIZE];
+        header[..4].copy_from_slice(b"DDS ");
+        let mut set = |offset: usize, value: u32| {
+            header[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        };
+        set(DDS_SIZE_OFFSET, 124);
+        set(DDS_FLAGS_OFFSET, DDSD_MIPMAPCOUNT);
+        set(DDS_HEIGHT_OFFSET, height);
+        set(DDS_WIDTH_OFFSET, width);
+        set(DDS_MIPMAPCOUNT_OFFSET, mipmaps);
+        set(DDS_PIXELFORMAT_SIZE_OFFSET, 32);
+        set(DDS_PIXELFORMAT_FLAGS_OFFSET, DDPF_FOURCC);
+        header[DDS_FOURCC_OFFSET..DDS_FOURCC_OFFSET + 4].copy_from_slice(fourcc);
+        header
+    }
+
+    #[test]
+    fn test_expected_size() {
+        let entry = FileEntry::new(
+            PathBuf::from("gfx/test.dds"),
+            FileKind::Mod,
+            PathBuf::from("/mod/gfx/test.dds"),
+        );
+        // 8x8 DXT1 with all mipmaps: 4 blocks, then 1 block for each of 4x4, 2x2 and 1x1.
+        let info = DdsInfo::new(entry.clone(), &header(8, 8, 4, b"DXT1"), 0);
+        assert_eq!(info.max_mipmaps(), 4);
+        assert_eq!(info.expected_size(info.format.clone().unwrap()), Some(128 + 8 * 7));
+
+        let mut dx10 = header(16, 4, 1, b"DX10");
+        dx10.extend_from_slice(&98u32.to_le_bytes()); // BC7_UNORM
+        dx10.extend_from_slice(&3u32.to_le_bytes()); // 2d texture
+        dx10.extend_from_slice(&DX10_MISC_TEXTURECUBE.to_le_bytes());
+        dx10.extend_from_slice(&1u32.to_le_bytes()); // array size
+        dx10.extend_from_slice(&0u32.to_le_bytes());
+        let info = DdsInfo::new(entry.clone(), &dx10, 0);
+        assert_eq!(info.format.clone().unwrap().name, "BC7");
+        assert_eq!(info.expected_size(info.format.clone().unwrap()), Some(148 + 6 * 4 * 16));
+
+        // A corrupt header can describe more bytes than fit in 64 bits.
+        let info = DdsInfo::new(entry.clone(), &header(u32::MAX, u32::MAX, 1, b"DXT5"), 0);
+        assert_eq!(info.expected_size(info.format.clone().unwrap()), None);
+
+        let info = DdsInfo::new(entry, &header(8, 8, 1, b"XYZW"), 0);
+        assert_eq!(info.format, Err("FourCC `XYZW`".to_owned()));
     }
 }
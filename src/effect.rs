@@ -6,6 +6,7 @@ use crate::context::{Reason, ScopeContext};
 use crate::data::effect_localization::EffectLocalization;
 use crate::desc::validate_desc;
 use crate::everything::Everything;
+use crate::explain::probe_scope;
 use crate::game::Game;
 #[cfg(feature = "hoi4")]
 use crate::hoi4::variables::validate_variable;
@@ -14,7 +15,7 @@ use crate::lowercase::Lowercase;
 use crate::report::{err, fatal, tips, warn, ErrorKey, Severity};
 use crate::scopes::{scope_iterator, Scopes};
 #[cfg(feature = "jomini")]
-use crate::script_value::validate_script_value;
+use crate::script_value::{validate_script_value, validate_script_value_range};
 use crate::token::Token;
 use crate::tooltipped::Tooltipped;
 #[cfg(any(feature = "ck3", feature = "imperator"))]
@@ -125,6 +126,9 @@ pub fn validate_effect_field(
     sc: &mut ScopeContext,
     tooltipped: Tooltipped,
 ) {
+    probe_scope(key, sc);
+    data.variable_flow.record_field(key, bv);
+
     if let Some(effect) = data.get_effect(key) {
         match bv {
             BV::Value(token) => {
@@ -510,7 +514,9 @@ pub fn validate_effect_control(
         vd.req_field("chance");
         if Game::is_jomini() {
             #[cfg(feature = "jomini")]
-            vd.field_script_value("chance", sc);
+            vd.field_validated_sc("chance", sc, |bv, data, sc| {
+                validate_script_value_range(bv, data, sc, 0.0, 100.0, Severity::Warning);
+            });
         } else {
             // TODO HOI4
             vd.field_numeric("chance");
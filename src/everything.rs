@@ -43,7 +43,7 @@ use crate::data::{
     assets::Assets,
     defines::Defines,
     gui::Gui,
-    localization::Localization,
+    localization::{Language, Localization},
     on_actions::OnActions,
     scripted_effects::{Effect, Effects},
     scripted_triggers::{Trigger, Triggers},
@@ -55,13 +55,16 @@ use crate::data::{
 };
 use crate::db::{Db, DbKind};
 use crate::dds::DdsFiles;
+use crate::explain::{set_probe, take_probe, ScopeExplanation};
 use crate::fileset::{FileEntry, FileKind, Fileset};
+use crate::flow::VariableFlow;
 use crate::game::Game;
 #[cfg(any(feature = "ck3", feature = "vic3"))]
 use crate::helpers::TigerHashSet;
 #[cfg(feature = "hoi4")]
 use crate::hoi4::data::{
-    events::Hoi4Events, gfx::Gfx, music::Hoi4Musics, provinces::Hoi4Provinces,
+    equipment::Equipment, events::Hoi4Events, focus::NationalFocusTree, gfx::Gfx,
+    music::Hoi4Musics, provinces::Hoi4Provinces, technology::Technology,
 };
 #[cfg(feature = "hoi4")]
 use crate::hoi4::tables::misc::*;
@@ -76,16 +79,21 @@ use crate::macros::MACRO_MAP;
 use crate::parse::json::parse_json_file;
 use crate::parse::ParserMemory;
 use crate::pdxfile::PdxFile;
-#[cfg(any(feature = "ck3", feature = "vic3"))]
-use crate::report::err;
-use crate::report::{report, set_output_style, ErrorKey, OutputStyle, Severity};
+use crate::render_loca::{render_loca, RenderOptions};
+use crate::report::{
+    err, report, set_changed_lines, set_output_style, ChangedLines, ErrorKey, OutputStyle, Severity,
+};
 use crate::rivers::Rivers;
+#[cfg(feature = "jomini")]
+use crate::sound_banks::SoundBanks;
 use crate::token::{Loc, Token};
+use crate::translations::{export_translations, import_translations, TranslationFormat};
+use crate::upgrade::check_upgrade;
 use crate::variables::Variables;
 #[cfg(feature = "vic3")]
 use crate::vic3::data::{
-    buy_packages::BuyPackage, history::History, provinces::Vic3Provinces,
-    strategic_regions::StrategicRegion, terrain::TerrainMask,
+    buy_packages::BuyPackage, goods::Goods, history::History, provinces::Vic3Provinces,
+    state_regions::StateRegion, strategic_regions::StrategicRegion, terrain::TerrainMask,
 };
 #[cfg(feature = "vic3")]
 use crate::vic3::tables::misc::*;
@@ -131,6 +139,10 @@ pub struct Everything {
     /// Tracks specifically the .dds files, and their formats and sizes.
     pub(crate) dds: DdsFiles,
 
+    /// The sound events defined in FMOD banks.
+    #[cfg(feature = "jomini")]
+    pub(crate) sound_banks: SoundBanks,
+
     /// A general database of item types. Most items go here. The ones that need special handling
     /// go in the separate databases listed below.
     pub(crate) database: Db,
@@ -221,6 +233,9 @@ pub struct Everything {
     pub(crate) wars: Wars,
 
     pub(crate) variables: Variables,
+
+    /// Where script variables and flags are set and used, recorded during validation.
+    pub(crate) variable_flow: VariableFlow,
 }
 
 impl Everything {
@@ -273,6 +288,8 @@ impl Everything {
             parser: ParserMemory::default(),
             fileset,
             dds: DdsFiles::default(),
+            #[cfg(feature = "jomini")]
+            sound_banks: SoundBanks::default(),
             config,
             #[cfg(any(feature = "ck3", feature = "vic3"))]
             warned_defines: RwLock::new(TigerHashSet::default()),
@@ -341,6 +358,7 @@ impl Everything {
             #[cfg(feature = "ck3")]
             wars: Wars::default(),
             variables: Variables::new(),
+            variable_flow: VariableFlow::new(),
         })
     }
 
@@ -383,6 +401,12 @@ impl Everything {
         set_output_style(self.load_output_styles(default_colors));
     }
 
+    /// Only show reports about lines of the mod that were changed since git revision `rev`.
+    pub fn load_changed_lines(&self, rev: &str) -> Result<()> {
+        set_changed_lines(ChangedLines::from_git(self.fileset.mod_root(), rev)?);
+        Ok(())
+    }
+
     #[cfg(feature = "vic3")]
     fn load_json<F>(&mut self, itype: Item, add_json: F)
     where
@@ -448,6 +472,8 @@ impl Everything {
     fn load_all_generic(&mut self) {
         scope(|s| {
             s.spawn(|_| self.fileset.handle(&mut self.dds, &self.parser));
+            #[cfg(feature = "jomini")]
+            s.spawn(|_| self.fileset.handle(&mut self.sound_banks, &self.parser));
             s.spawn(|_| self.fileset.handle(&mut self.localization, &self.parser));
             s.spawn(|_| self.fileset.handle(&mut self.defines, &self.parser));
             s.spawn(|_| self.fileset.handle(&mut self.triggers, &self.parser));
@@ -605,6 +631,7 @@ impl Everything {
 
     fn validate_all_generic<'a>(&'a self, s: &Scope<'a>) {
         s.spawn(|_| self.fileset.validate(self));
+        s.spawn(|_| self.fileset.check_overrides(self));
         s.spawn(|_| self.defines.validate(self));
         s.spawn(|_| self.triggers.validate(self));
         s.spawn(|_| self.effects.validate(self));
@@ -626,6 +653,7 @@ impl Everything {
         s.spawn(|_| self.characters.validate(self));
         s.spawn(|_| self.traits.validate(self));
         s.spawn(|_| self.title_history.validate(self));
+        s.spawn(|_| self.title_history.check_timeline(self));
         s.spawn(|_| self.doctrines.validate(self));
         s.spawn(|_| self.menatarmstypes.validate(self));
         s.spawn(|_| self.data_bindings.validate(self));
@@ -651,7 +679,10 @@ impl Everything {
         s.spawn(|_| self.script_values.validate(self));
         s.spawn(|_| self.music.validate(self));
         s.spawn(|_| StrategicRegion::crosscheck(self));
+        s.spawn(|_| StateRegion::crosscheck(self));
+        s.spawn(|_| self.history.crosscheck(self));
         s.spawn(|_| BuyPackage::crosscheck(self));
+        s.spawn(|_| Goods::crosscheck(self));
     }
 
     #[cfg(feature = "imperator")]
@@ -672,6 +703,9 @@ impl Everything {
         s.spawn(|_| self.provinces_hoi4.validate(self));
         s.spawn(|_| self.gfx.validate(self));
         s.spawn(|_| self.music_hoi4.validate(self));
+        s.spawn(|_| NationalFocusTree::crosscheck(self));
+        s.spawn(|_| Technology::crosscheck(self));
+        s.spawn(|_| Equipment::crosscheck(self));
     }
 
     pub fn validate_all(&self) {
@@ -691,6 +725,79 @@ impl Everything {
         self.database.validate(self);
 
         self.localization.validate_pass2(self);
+        self.variable_flow.check(self);
+    }
+
+    /// Validate everything again, and report what is known about the scopes at the given position
+    /// in the script. There may be several explanations if the position is validated in more than
+    /// one context, for example in a scripted effect that is called from several places.
+    ///
+    /// `file` may be the path relative to the game or mod root, or a path on disk.
+    /// The reports generated by the validation are stored as usual; callers who only want the
+    /// explanation can discard them with [`take_reports`](crate::take_reports).
+    pub fn explain_scope(&self, file: &Path, line: u32, column: u32) -> Vec<ScopeExplanation> {
+        set_probe(file, line, column);
+        self.validate_all();
+        take_probe()
+    }
+
+    /// Remember the vanilla items and localizations that the mod overrides, instead of dropping
+    /// them. Both versions given to [`Everything::check_upgrade`] need this before loading.
+    pub fn keep_overridden(&mut self) {
+        self.database.keep_overridden();
+        self.localization.keep_overridden();
+    }
+
+    /// Remember where the mod's localization keys are first used, so that
+    /// [`Everything::export_translations`] can tell the translators. This has to be called before
+    /// validating.
+    pub fn collect_loca_usage(&mut self) {
+        self.localization.collect_used_at();
+    }
+
+    /// Report what in the mod is affected by the game update from the vanilla files loaded in
+    /// `old` to the ones loaded here. Both must have been loaded with the same mod, after
+    /// calling [`Everything::keep_overridden`].
+    pub fn check_upgrade(&self, old: &Everything) {
+        check_upgrade(old, self);
+    }
+
+    /// Return the text of localization `key` with its macros expanded, as far as it can be known
+    /// without running the game.
+    pub fn render_loca(&self, key: &str, options: &RenderOptions) -> Result<String> {
+        render_loca(&self.localization, key, options)
+    }
+
+    /// Return the text of a file of the mod's localizations that are missing or out of date in
+    /// `lang`, in the given format. `hashes_path` is as for [`Self::check_translation_hashes`].
+    pub fn export_translations(
+        &self,
+        lang: Language,
+        format: TranslationFormat,
+        hashes_path: Option<&Path>,
+    ) -> Result<String> {
+        export_translations(self, lang, format, hashes_path)
+    }
+
+    /// Write the translations into `lang` from the file at `path` into the mod's localization
+    /// files, and return the paths of the files that were written.
+    pub fn import_translations(
+        &self,
+        lang: Language,
+        format: TranslationFormat,
+        path: &Path,
+    ) -> Result<Vec<PathBuf>> {
+        import_translations(self, lang, format, path)
+    }
+
+    /// Report translations whose reference language text changed since they were last edited,
+    /// using and then updating the hashes recorded in the file at `path`.
+    /// If the file can't be read or written, that is reported too.
+    pub fn check_translation_hashes(&self, path: &Path) {
+        if let Err(e) = self.localization.check_translation_hashes(path) {
+            let loc = Loc::for_file(path.to_path_buf(), FileKind::Mod, path.to_path_buf());
+            err(ErrorKey::Config).msg(format!("{e:#}")).loc(loc).push();
+        }
     }
 
     pub fn check_rivers(&mut self) {
@@ -709,6 +816,8 @@ impl Everything {
     pub fn check_unused(&mut self) {
         self.localization.check_unused(self);
         self.fileset.check_unused_dds(self);
+        #[cfg(feature = "jomini")]
+        self.sound_banks.check_unused();
     }
 
     #[allow(dead_code)]
@@ -1260,6 +1369,8 @@ impl Everything {
             Item::Pdxmesh => Box::new(self.assets.iter_mesh_keys()),
             Item::ScriptedEffect => Box::new(self.effects.iter_keys()),
             Item::ScriptedTrigger => Box::new(self.triggers.iter_keys()),
+            #[cfg(feature = "jomini")]
+            Item::Sound => Box::new(self.sound_banks.iter_keys()),
             Item::TextFormat => Box::new(self.gui.iter_textformat_keys()),
             Item::TextIcon => Box::new(self.gui.iter_texticon_keys()),
             Item::TextureFile => Box::new(self.assets.iter_texture_keys()),
@@ -1291,7 +1402,9 @@ impl Everything {
                 #[cfg(feature = "imperator")]
                 Game::Imperator => &crate::imperator::tables::sounds::SOUNDS_SET,
             };
-            sounds_set.contains(&Lowercase::new(name))
+            // Ask the sound banks first, so that the mod's events are marked as used even if they
+            // have the same name as a vanilla sound.
+            self.sound_banks.exists(name) || sounds_set.contains(&Lowercase::new(name))
         }
     }
 
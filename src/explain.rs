@@ -0,0 +1,162 @@
+//! Support for explaining what tiger knows about the scopes at a given position in the script.
+//!
+//! The position is registered as a probe before validation. Whenever the trigger or effect
+//! validators pass a field key on that line, they hand their [`ScopeContext`] to [`probe_scope`],
+//! which takes a snapshot of it in the form of a [`ScopeExplanation`].
+
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex};
+
+use crate::context::ScopeContext;
+use crate::scopes::Scopes;
+use crate::token::{Loc, Token};
+
+/// Quick check so that validation doesn't have to take the lock when no probe is set.
+static PROBE_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+static PROBE: LazyLock<Mutex<Option<Probe>>> = LazyLock::new(|| Mutex::new(None));
+
+/// A position in a script file that we want to know the scopes for, and the snapshots taken there.
+#[derive(Debug)]
+struct Probe {
+    file: PathBuf,
+    line: u32,
+    column: u32,
+    /// The snapshots taken so far, together with the column of the field key they were taken at.
+    found: Vec<(u32, ScopeExplanation)>,
+}
+
+impl Probe {
+    fn matches(&self, loc: Loc) -> bool {
+        loc.line == self.line
+            && loc.column <= self.column
+            && (loc.pathname() == self.file || loc.fullpath().ends_with(&self.file))
+    }
+}
+
+/// One entry in a [`ScopeExplanation`]: what we know about the scope type of a scope reference,
+/// and why we think so.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScopeFact {
+    pub scopes: Scopes,
+    /// A description of the reason, such as "deduced from `liege` here".
+    pub reason: String,
+    /// Where the reason can be found in the script.
+    pub loc: Loc,
+}
+
+/// A snapshot of what a [`ScopeContext`] knows at a specific field in the script.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScopeExplanation {
+    /// The field key where the snapshot was taken.
+    pub key: Token,
+    pub root: ScopeFact,
+    pub this: ScopeFact,
+    /// The chain of previous scopes, starting with `prev` and then `prev.prev` etc.
+    pub prev: Vec<ScopeFact>,
+    /// Named scopes, sorted by name. The `bool` is true if the scope is expected to be supplied
+    /// by the caller.
+    pub named: Vec<(&'static str, ScopeFact, bool)>,
+    /// Named lists, sorted by name. The `bool` is true if the list is expected to be supplied by
+    /// the caller.
+    pub lists: Vec<(&'static str, ScopeFact, bool)>,
+    /// Whether all named scopes are expected to be known in this context.
+    pub strict: bool,
+    /// Where the scope context was created.
+    pub source: Token,
+    /// The events and on-actions that were triggered on the way from `source` to here, oldest
+    /// first.
+    pub traceback: Vec<Token>,
+}
+
+/// Start recording scope snapshots for the given position.
+///
+/// `file` may be either the path relative to the game or mod root, or a path on disk.
+/// Any field key on `line` at or before `column` counts as a match; the closest one wins.
+pub(crate) fn set_probe(file: &Path, line: u32, column: u32) {
+    *PROBE.lock().unwrap() =
+        Some(Probe { file: file.to_path_buf(), line, column, found: Vec::new() });
+    PROBE_ACTIVE.store(true, Ordering::Relaxed);
+}
+
+/// Stop recording and return the distinct snapshots taken at the field key closest to the probe.
+pub(crate) fn take_probe() -> Vec<ScopeExplanation> {
+    PROBE_ACTIVE.store(false, Ordering::Relaxed);
+    let Some(probe) = PROBE.lock().unwrap().take() else {
+        return Vec::new();
+    };
+    let Some(best) = probe.found.iter().map(|(column, _)| *column).max() else {
+        return Vec::new();
+    };
+    let mut result: Vec<ScopeExplanation> = Vec::new();
+    for (column, explanation) in probe.found {
+        if column == best && !result.contains(&explanation) {
+            result.push(explanation);
+        }
+    }
+    result
+}
+
+/// Called by the validators for each field they examine. Records a snapshot of `sc` if `key`
+/// matches the probe.
+pub(crate) fn probe_scope(key: &Token, sc: &ScopeContext) {
+    if !PROBE_ACTIVE.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut guard = PROBE.lock().unwrap();
+    if let Some(probe) = guard.as_mut() {
+        if probe.matches(key.loc) {
+            probe.found.push((key.loc.column, sc.explain(key)));
+        }
+    }
+}
+
+fn display_loc(loc: Loc) -> String {
+    if loc.line == 0 {
+        format!("{}", loc.pathname().display())
+    } else {
+        format!("{}:{}:{}", loc.pathname().display(), loc.line, loc.column)
+    }
+}
+
+impl Display for ScopeFact {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "{} ({}, at {})", self.scopes, self.reason, display_loc(self.loc))
+    }
+}
+
+impl Display for ScopeExplanation {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        writeln!(f, "scopes at `{}` in {}", self.key, display_loc(self.key.loc))?;
+        writeln!(f, "  root: {}", self.root)?;
+        writeln!(f, "  this: {}", self.this)?;
+        let mut name = "prev".to_owned();
+        for fact in &self.prev {
+            writeln!(f, "  {name}: {fact}")?;
+            name.push_str(".prev");
+        }
+        for (name, fact, is_input) in &self.named {
+            let input = if *is_input { " (expected from caller)" } else { "" };
+            writeln!(f, "  scope:{name}: {fact}{input}")?;
+        }
+        for (name, fact, is_input) in &self.lists {
+            let input = if *is_input { " (expected from caller)" } else { "" };
+            writeln!(f, "  list {name}: {fact}{input}")?;
+        }
+        if !self.strict {
+            writeln!(f, "  other named scopes may be supplied by the caller")?;
+        }
+        writeln!(
+            f,
+            "  scopes initialized at {} (`{}`)",
+            display_loc(self.source.loc),
+            self.source
+        )?;
+        for token in self.traceback.iter().rev() {
+            writeln!(f, "  triggered from {} (`{token}`)", display_loc(token.loc))?;
+        }
+        Ok(())
+    }
+}
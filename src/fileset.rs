@@ -12,10 +12,10 @@ use anyhow::{bail, Result};
 use rayon::prelude::*;
 use walkdir::WalkDir;
 
-use crate::block::Block;
+use crate::block::{Block, Field};
 use crate::everything::{Everything, FilesError};
 use crate::game::Game;
-use crate::helpers::TigerHashSet;
+use crate::helpers::{TigerHashMap, TigerHashSet};
 use crate::item::Item;
 #[cfg(feature = "vic3")]
 use crate::mod_metadata::ModMetadata;
@@ -23,9 +23,10 @@ use crate::mod_metadata::ModMetadata;
 use crate::modfile::ModFile;
 use crate::parse::ParserMemory;
 use crate::pathtable::{PathTable, PathTableIndex};
+use crate::pdxfile::PdxFile;
 use crate::report::{
-    add_loaded_dlc_root, add_loaded_mod_root, err, fatal, report, warn_abbreviated, warn_header,
-    will_maybe_log, ErrorKey, Severity,
+    add_loaded_dlc_root, add_loaded_mod_root, err, fatal, report, tips, untidy, warn_abbreviated,
+    warn_header, will_maybe_log, ErrorKey, Severity,
 };
 use crate::token::Token;
 
@@ -233,6 +234,9 @@ pub struct Fileset {
     /// The CK3 and mod files in the order the game would load them.
     ordered_files: Vec<FileEntry>,
 
+    /// Vanilla files that were replaced by mod files with the same path, by path.
+    overridden: TigerHashMap<PathBuf, FileEntry>,
+
     /// Filename Tokens for the files in `ordered_files`.
     /// Used for [`Fileset::iter_keys()`].
     filename_tokens: Vec<Token>,
@@ -271,6 +275,7 @@ impl Fileset {
             config: None,
             files: Vec::new(),
             ordered_files: Vec::new(),
+            overridden: TigerHashMap::default(),
             filename_tokens: Vec::new(),
             filenames: TigerHashSet::default(),
             directories: RwLock::new(TigerHashSet::default()),
@@ -457,7 +462,11 @@ impl Fileset {
         for entry in self.files.drain(..) {
             if let Some(prev) = self.ordered_files.last_mut() {
                 if entry.path == prev.path {
-                    *prev = entry;
+                    let from_mod = !entry.kind.counts_as_vanilla();
+                    let prev = std::mem::replace(prev, entry);
+                    if from_mod && prev.kind.counts_as_vanilla() {
+                        self.overridden.insert(prev.path.clone(), prev);
+                    }
                 } else {
                     self.ordered_files.push(entry);
                 }
@@ -481,6 +490,12 @@ impl Fileset {
         &self.ordered_files[start..end]
     }
 
+    /// Return the file at this path, after mod files have replaced vanilla ones.
+    pub fn get_entry(&self, path: &Path) -> Option<&FileEntry> {
+        let idx = self.ordered_files.partition_point(|entry| entry.path.as_path() < path);
+        self.ordered_files.get(idx).filter(|entry| entry.path == path)
+    }
+
     pub fn filter_map_under<F, T>(&self, subpath: &Path, f: F) -> Vec<T>
     where
         F: Fn(&FileEntry) -> Option<T> + Sync + Send,
@@ -503,6 +518,21 @@ impl Fileset {
         handler.finalize();
     }
 
+    /// Return the directory of the mod itself.
+    pub fn mod_root(&self) -> &Path {
+        self.the_mod.root()
+    }
+
+    /// Iterate over the files of the mod itself.
+    pub fn iter_mod_files(&self) -> impl Iterator<Item = &FileEntry> {
+        self.ordered_files.iter().filter(|entry| entry.kind == FileKind::Mod)
+    }
+
+    /// Return the vanilla file that was replaced by the mod file with this path, if any.
+    pub fn get_overridden(&self, path: &Path) -> Option<&FileEntry> {
+        self.overridden.get(path)
+    }
+
     pub fn mark_used(&self, file: &str) {
         let file = file.strip_prefix('/').unwrap_or(file);
         self.used.write().unwrap().insert(file.to_string());
@@ -685,6 +715,80 @@ impl Fileset {
         }
     }
 
+    /// Advise about mod files that replace whole vanilla files while copying some of their items
+    /// unchanged. Such files make the mod incompatible with other mods that change the same file,
+    /// even where the mod itself didn't need to change anything.
+    pub fn check_overrides(&self, data: &Everything) {
+        for entry in self.iter_mod_files() {
+            if entry.path.extension().is_none_or(|ext| ext != "txt")
+                || !will_maybe_log(entry, ErrorKey::FileOverride)
+            {
+                continue;
+            }
+            let Some(vanilla) = self.overridden.get(&entry.path) else {
+                continue;
+            };
+            let Some(block) = PdxFile::read_optional_bom(entry, &data.parser) else {
+                continue;
+            };
+            let Some(vanilla_block) = PdxFile::read_optional_bom(vanilla, &data.parser) else {
+                continue;
+            };
+
+            let mut changed = Vec::new();
+            let mut unchanged = Vec::new();
+            let mut dropped = Vec::new();
+            let mut seen = Vec::new();
+            // Only the block-valued fields are items. Settings such as an events file's
+            // `namespace = x` have to be repeated in any file, so they don't count as copies.
+            for Field(key, _, bv) in vanilla_block.iter_fields() {
+                if bv.get_block().is_none() || seen.contains(&key.as_str()) {
+                    continue;
+                }
+                seen.push(key.as_str());
+                let mod_items = fields_named(&block, key.as_str());
+                let vanilla_items = fields_named(&vanilla_block, key.as_str());
+                if mod_items.is_empty() {
+                    dropped.push(key.as_str());
+                } else if mod_items.len() == vanilla_items.len()
+                    && mod_items.iter().zip(&vanilla_items).all(|(f1, f2)| f1.equivalent(f2))
+                {
+                    unchanged.push(key.as_str());
+                } else {
+                    changed.push(key.as_str());
+                }
+            }
+
+            if unchanged.is_empty() {
+                continue;
+            }
+            if changed.is_empty() && dropped.is_empty() && block.equivalent(&vanilla_block) {
+                let msg = "this file is an unchanged copy of the vanilla file it replaces";
+                let info = "it can be removed from the mod";
+                untidy(ErrorKey::FileOverride).msg(msg).info(info).loc(entry).push();
+                continue;
+            }
+            let msg = format!(
+                "this file replaces the vanilla file but copies {} of its {} items unchanged",
+                unchanged.len(),
+                seen.len()
+            );
+            let mut info = format!(
+                "changed: {}; unchanged: {}; dropped: {}. ",
+                list_names(&changed),
+                list_names(&unchanged),
+                list_names(&dropped)
+            );
+            info.push_str("Putting only the changed items in a separately named file keeps the mod compatible with other mods that change this file");
+            if !dropped.is_empty() {
+                info.push_str(
+                    ", but vanilla items can only be dropped by replacing the whole file",
+                );
+            }
+            tips(ErrorKey::FileOverride).msg(msg).info(info).loc(entry).push();
+        }
+    }
+
     pub fn check_unused_dds(&self, _data: &Everything) {
         let mut vec = Vec::new();
         for entry in &self.ordered_files {
@@ -709,3 +813,23 @@ impl Fileset {
         }
     }
 }
+
+fn fields_named<'a>(block: &'a Block, name: &str) -> Vec<&'a Field> {
+    block
+        .iter_fields()
+        .filter(|Field(key, _, bv)| key.is(name) && bv.get_block().is_some())
+        .collect()
+}
+
+/// Format a list of item names for a report, shortening long lists.
+fn list_names(names: &[&str]) -> String {
+    const MAX_NAMES: usize = 5;
+    if names.is_empty() {
+        "none".to_string()
+    } else if names.len() > MAX_NAMES {
+        let shown = names[..MAX_NAMES].iter().map(|name| format!("`{name}`")).collect::<Vec<_>>();
+        format!("{} and {} more", shown.join(", "), names.len() - MAX_NAMES)
+    } else {
+        names.iter().map(|name| format!("`{name}`")).collect::<Vec<_>>().join(", ")
+    }
+}
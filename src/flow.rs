@@ -0,0 +1,339 @@
+//! Tracking of where script variables and flags are set, used, and removed.
+//!
+//! The validators report every effect and trigger that touches a variable or flag, while they
+//! validate it. After validation, names that were used but never set, removed but never set, or
+//! set but never used are reported. Those are usually typos in the name.
+//!
+//! Names that still contain macro parameters, such as `flag_$NAME$`, are kept as patterns. Setting
+//! or using a pattern counts as setting or using every name that matches it.
+
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+
+use crate::block::BV;
+use crate::everything::Everything;
+use crate::fileset::FileKind;
+use crate::game::{Game, GameFlags};
+use crate::helpers::TigerHashMap;
+use crate::report::{untidy, warn, ErrorKey};
+use crate::token::Token;
+use crate::variables::Extract::{self, AssignOrField, Field};
+
+use Access::*;
+use FlowKind::*;
+
+/// The kinds of names that are tracked. Each kind has its own namespace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum FlowKind {
+    Variable,
+    GlobalVariable,
+    LocalVariable,
+    VariableList,
+    GlobalVariableList,
+    LocalVariableList,
+    CharacterFlag,
+    CountryFlag,
+    StateFlag,
+    UnitLeaderFlag,
+    GlobalFlag,
+}
+
+impl FlowKind {
+    fn is_flag(self) -> bool {
+        matches!(self, CharacterFlag | CountryFlag | StateFlag | UnitLeaderFlag | GlobalFlag)
+    }
+}
+
+impl Display for FlowKind {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        let s = match self {
+            Variable => "variable",
+            Self::GlobalVariable => "global variable",
+            Self::LocalVariable => "local variable",
+            Self::VariableList => "variable list",
+            Self::GlobalVariableList => "global variable list",
+            Self::LocalVariableList => "local variable list",
+            Self::CharacterFlag => "character flag",
+            Self::CountryFlag => "country flag",
+            Self::StateFlag => "state flag",
+            Self::UnitLeaderFlag => "unit leader flag",
+            Self::GlobalFlag => "global flag",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// What an effect or trigger does with the name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Access {
+    Set,
+    Use,
+    /// Changing a value both uses it and sets it.
+    Change,
+    Remove,
+}
+
+/// The most relevant place where a name was set, used, or removed.
+/// Places in the mod under scrutiny are preferred, so that the report can point there.
+#[derive(Debug, Default)]
+struct Accesses {
+    set: Option<Token>,
+    used: Option<Token>,
+    removed: Option<Token>,
+}
+
+fn keep_best(slot: &mut Option<Token>, token: &Token) {
+    let better = match slot {
+        None => true,
+        Some(old) => {
+            token.loc.kind > old.loc.kind
+                || (token.loc.kind == old.loc.kind
+                    && (token.loc.pathname(), token.loc.line, token.loc.column)
+                        < (old.loc.pathname(), old.loc.line, old.loc.column))
+        }
+    };
+    if better {
+        *slot = Some(token.clone());
+    }
+}
+
+#[derive(Debug)]
+pub struct VariableFlow {
+    /// Effect and trigger names to look for, mapped to what they do and how to find the name.
+    fields: TigerHashMap<&'static str, (FlowKind, Access, Extract)>,
+    names: Mutex<TigerHashMap<(FlowKind, &'static str), Accesses>>,
+}
+
+impl VariableFlow {
+    pub fn new() -> Self {
+        let game = GameFlags::game();
+        let fields = FLOW_FIELDS
+            .iter()
+            .filter(|(_, _, _, _, gameflags)| gameflags.contains(game))
+            .map(|(key, kind, access, extract, _)| (*key, (*kind, *access, *extract)))
+            .collect();
+        Self { fields, names: Mutex::new(TigerHashMap::default()) }
+    }
+
+    fn record(&self, kind: FlowKind, access: Access, name: &Token) {
+        // Skip names that are only known at runtime.
+        if name.as_str().is_empty() || name.as_str().contains(['@', '[', ':', '.']) {
+            return;
+        }
+        let mut names = self.names.lock().unwrap();
+        let entry = names.entry((kind, name.as_str())).or_default();
+        match access {
+            Set => keep_best(&mut entry.set, name),
+            Use => keep_best(&mut entry.used, name),
+            Change => {
+                keep_best(&mut entry.set, name);
+                keep_best(&mut entry.used, name);
+            }
+            Remove => keep_best(&mut entry.removed, name),
+        }
+    }
+
+    /// Called by the effect and trigger validators for each field they examine.
+    pub fn record_field(&self, key: &Token, bv: &BV) {
+        if let Some((kind, access, extract)) = self.fields.get(key.as_str()) {
+            if let Some(name) = extract.extract_token(bv) {
+                self.record(*kind, *access, name);
+            }
+        } else if let Some((_, list)) = key.as_str().split_once('_') {
+            // Iterators over variable lists, such as `every_in_global_list`
+            let kind = match list {
+                "in_list" => VariableList,
+                "in_global_list" => GlobalVariableList,
+                "in_local_list" => LocalVariableList,
+                _ => return,
+            };
+            if let Some(name) = bv.get_block().and_then(|block| block.get_field_value("variable")) {
+                self.record(kind, Use, name);
+            }
+        }
+    }
+
+    /// Called when validating a `var:`, `global_var:`, or `local_var:` target.
+    pub fn record_target(&self, prefix: &Token, name: &Token) {
+        // Hoi4 variables are checked in their own way, see `hoi4::variables`.
+        if !Game::is_jomini() {
+            return;
+        }
+        let kind = if prefix.lowercase_is("var") {
+            Variable
+        } else if prefix.lowercase_is("global_var") {
+            GlobalVariable
+        } else if prefix.lowercase_is("local_var") {
+            LocalVariable
+        } else {
+            return;
+        };
+        self.record(kind, Use, name);
+    }
+
+    /// Report the names that were used or removed but never set, and the ones that were set but
+    /// never used.
+    pub fn check(&self, data: &Everything) {
+        let names = self.names.lock().unwrap();
+        let mut entries: Vec<_> = names.iter().collect();
+        entries.sort_unstable_by_key(|(key, _)| *key);
+        let patterns: Vec<_> =
+            entries.iter().filter(|((_, name), _)| name.contains('$')).copied().collect();
+        let pattern_matches = |kind: &FlowKind, name: &str, slot: fn(&Accesses) -> bool| {
+            patterns.iter().any(|((pkind, pattern), accesses)| {
+                pkind == kind && slot(accesses) && matches_pattern(pattern, name)
+            })
+        };
+        for ((kind, name), accesses) in entries {
+            if name.contains('$') {
+                continue;
+            }
+            let set_elsewhere = match kind {
+                Variable | GlobalVariable | LocalVariable => data.variables.variable_exists(name),
+                VariableList | GlobalVariableList | LocalVariableList => {
+                    data.variables.list_exists(name)
+                }
+                _ => false,
+            } || pattern_matches(kind, name, |a| a.set.is_some());
+            let used_elsewhere = pattern_matches(kind, name, |a| a.used.is_some());
+            let what = if kind.is_flag() { "checked" } else { "used" };
+            if accesses.set.is_none() && !set_elsewhere {
+                if let Some(token) = &accesses.used {
+                    let msg = format!("{kind} `{name}` is {what} but never set");
+                    warn(ErrorKey::Variables).msg(msg).loc(token).push();
+                } else if let Some(token) = &accesses.removed {
+                    let msg = format!("{kind} `{name}` is removed but never set");
+                    warn(ErrorKey::Variables).msg(msg).loc(token).push();
+                }
+            } else if accesses.used.is_none() && !used_elsewhere {
+                if let Some(token) = &accesses.set {
+                    // Only report this about the mod under scrutiny, because the base game may
+                    // set things for the benefit of its own gui and localization.
+                    if token.loc.kind >= FileKind::LoadedMod(0) {
+                        let msg = format!("{kind} `{name}` is set but never {what}");
+                        let info = "it may still be used in gui or localization files, which are not checked for this";
+                        untidy(ErrorKey::Variables).weak().msg(msg).info(info).loc(token).push();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Check if `name` could be the result of expanding the macro parameters in `pattern`.
+/// Each `$PARAM$` in the pattern can stand for any text, including none.
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    // The literal parts of the pattern are the ones outside the `$` pairs.
+    let literals: Vec<_> = pattern.split('$').step_by(2).collect();
+    let (first, rest) = literals.split_first().unwrap();
+    let Some((last, middle)) = rest.split_last() else {
+        return pattern == name;
+    };
+    let Some(mut remaining) = name.strip_prefix(first) else {
+        return false;
+    };
+    let Some(stripped) = remaining.strip_suffix(last) else {
+        return false;
+    };
+    remaining = stripped;
+    for literal in middle {
+        match remaining.find(literal) {
+            Some(i) => remaining = &remaining[i + literal.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+const CK3: GameFlags = GameFlags::Ck3;
+const VIC3: GameFlags = GameFlags::Vic3;
+const HOI4: GameFlags = GameFlags::Hoi4;
+const CK3_HOI4: GameFlags = GameFlags::Ck3.union(GameFlags::Hoi4);
+const JOMINI: GameFlags = GameFlags::jomini();
+
+const FLOW_FIELDS: &[(&str, FlowKind, Access, Extract, GameFlags)] = &[
+    ("add_character_flag", CharacterFlag, Set, AssignOrField("flag"), CK3),
+    ("add_dead_character_flag", CharacterFlag, Set, Field("flag"), CK3),
+    ("add_to_global_variable_list", GlobalVariableList, Set, Field("name"), JOMINI),
+    ("add_to_local_variable_list", LocalVariableList, Set, Field("name"), JOMINI),
+    ("add_to_variable_list", VariableList, Set, Field("name"), JOMINI),
+    ("change_global_variable", GlobalVariable, Change, Field("name"), JOMINI),
+    ("change_local_variable", LocalVariable, Change, Field("name"), JOMINI),
+    ("change_variable", Variable, Change, Field("name"), JOMINI),
+    ("clamp_global_variable", GlobalVariable, Change, Field("name"), JOMINI),
+    ("clamp_local_variable", LocalVariable, Change, Field("name"), JOMINI),
+    ("clamp_variable", Variable, Change, Field("name"), JOMINI),
+    ("clear_global_variable_list", GlobalVariableList, Remove, AssignOrField("name"), JOMINI),
+    ("clear_local_variable_list", LocalVariableList, Remove, AssignOrField("name"), JOMINI),
+    ("clear_variable_list", VariableList, Remove, AssignOrField("name"), JOMINI),
+    ("clr_character_flag", CharacterFlag, Remove, AssignOrField("flag"), HOI4),
+    ("clr_country_flag", CountryFlag, Remove, AssignOrField("flag"), HOI4),
+    ("clr_global_flag", GlobalFlag, Remove, AssignOrField("flag"), HOI4),
+    ("clr_state_flag", StateFlag, Remove, AssignOrField("flag"), HOI4),
+    ("clr_unit_leader_flag", UnitLeaderFlag, Remove, AssignOrField("flag"), HOI4),
+    ("global_variable_list_size", GlobalVariableList, Use, Field("name"), JOMINI),
+    ("has_character_flag", CharacterFlag, Use, AssignOrField("flag"), CK3_HOI4),
+    ("has_country_flag", CountryFlag, Use, AssignOrField("flag"), HOI4),
+    ("has_dead_character_flag", CharacterFlag, Use, AssignOrField("flag"), CK3),
+    ("has_dead_character_variable", Variable, Use, AssignOrField("name"), CK3),
+    ("has_global_flag", GlobalFlag, Use, AssignOrField("flag"), HOI4),
+    ("has_global_variable", GlobalVariable, Use, AssignOrField("name"), JOMINI),
+    ("has_global_variable_list", GlobalVariableList, Use, AssignOrField("name"), JOMINI),
+    ("has_local_variable", LocalVariable, Use, AssignOrField("name"), JOMINI),
+    ("has_local_variable_list", LocalVariableList, Use, AssignOrField("name"), JOMINI),
+    ("has_state_flag", StateFlag, Use, AssignOrField("flag"), HOI4),
+    ("has_unit_leader_flag", UnitLeaderFlag, Use, AssignOrField("flag"), HOI4),
+    ("has_variable", Variable, Use, AssignOrField("name"), JOMINI),
+    ("has_variable_list", VariableList, Use, AssignOrField("name"), JOMINI),
+    ("is_target_in_global_variable_list", GlobalVariableList, Use, Field("name"), JOMINI),
+    ("is_target_in_local_variable_list", LocalVariableList, Use, Field("name"), JOMINI),
+    ("is_target_in_variable_list", VariableList, Use, Field("name"), JOMINI),
+    ("local_variable_list_size", LocalVariableList, Use, Field("name"), JOMINI),
+    ("modify_character_flag", CharacterFlag, Change, Field("flag"), HOI4),
+    ("modify_country_flag", CountryFlag, Change, Field("flag"), HOI4),
+    ("modify_global_flag", GlobalFlag, Change, Field("flag"), HOI4),
+    ("modify_state_flag", StateFlag, Change, Field("flag"), HOI4),
+    ("modify_unit_leader_flag", UnitLeaderFlag, Change, Field("flag"), HOI4),
+    ("remove_character_flag", CharacterFlag, Remove, AssignOrField("flag"), CK3),
+    ("remove_dead_character_variable", Variable, Remove, AssignOrField("name"), CK3),
+    ("remove_global_variable", GlobalVariable, Remove, AssignOrField("name"), JOMINI),
+    ("remove_list_global_variable", GlobalVariableList, Remove, Field("name"), JOMINI),
+    ("remove_list_local_variable", LocalVariableList, Remove, Field("name"), JOMINI),
+    ("remove_list_variable", VariableList, Remove, Field("name"), JOMINI),
+    ("remove_local_variable", LocalVariable, Remove, AssignOrField("name"), JOMINI),
+    ("remove_variable", Variable, Remove, AssignOrField("name"), JOMINI),
+    ("round_global_variable", GlobalVariable, Change, Field("name"), JOMINI),
+    ("round_local_variable", LocalVariable, Change, Field("name"), JOMINI),
+    ("round_variable", Variable, Change, Field("name"), JOMINI),
+    ("set_character_flag", CharacterFlag, Set, AssignOrField("flag"), HOI4),
+    ("set_country_flag", CountryFlag, Set, AssignOrField("flag"), HOI4),
+    ("set_dead_character_variable", Variable, Set, Field("name"), CK3),
+    ("set_global_flag", GlobalFlag, Set, AssignOrField("flag"), HOI4),
+    ("set_global_variable", GlobalVariable, Set, AssignOrField("name"), JOMINI),
+    ("set_local_variable", LocalVariable, Set, AssignOrField("name"), JOMINI),
+    ("set_state_flag", StateFlag, Set, AssignOrField("flag"), HOI4),
+    ("set_unit_leader_flag", UnitLeaderFlag, Set, AssignOrField("flag"), HOI4),
+    ("set_variable", Variable, Set, AssignOrField("name"), JOMINI),
+    ("sort_global_variable_list", GlobalVariableList, Change, Field("name"), VIC3),
+    ("sort_local_variable_list", LocalVariableList, Change, Field("name"), VIC3),
+    ("sort_variable_list", VariableList, Change, Field("name"), VIC3),
+    ("variable_list_size", VariableList, Use, Field("name"), JOMINI),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("flag_$NAME$", "flag_test"));
+        assert!(matches_pattern("flag_$NAME$", "flag_"));
+        assert!(matches_pattern("$A$_and_$B$", "this_and_that"));
+        assert!(matches_pattern("pre_$A$_mid_$B$_post", "pre_x_mid_y_post"));
+        assert!(!matches_pattern("pre_$A$_mid_$B$_post", "pre_x_post"));
+        assert!(!matches_pattern("flag_$NAME$", "other_test"));
+        assert!(!matches_pattern("$NAME$_flag", "flag"));
+        assert!(matches_pattern("plain", "plain"));
+        assert!(!matches_pattern("plain", "plainer"));
+    }
+}
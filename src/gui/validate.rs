@@ -1,6 +1,6 @@
 use crate::block::{Block, BV};
 use crate::context::ScopeContext;
-use crate::data::localization::LocaValue;
+use crate::data::localization::{validate_loca_format, LocaValue};
 use crate::datatype::{validate_datatypes, Datatype};
 use crate::everything::Everything;
 #[cfg(feature = "ck3")]
@@ -369,9 +369,8 @@ pub fn validate_datatype_field(
             let loca_value = ValueParser::new(vec![value]).parse();
             let mut sc = ScopeContext::new(Scopes::None, key);
             match loca_value {
-                // TODO: validate format
                 LocaValue::Code(chain, format) => {
-                    validate_datatypes(
+                    let dtype = validate_datatypes(
                         &chain,
                         data,
                         &mut sc,
@@ -380,6 +379,9 @@ pub fn validate_datatype_field(
                         format.as_ref(),
                         allow_promote,
                     );
+                    if let Some(format) = format {
+                        validate_loca_format(&format, dtype, data);
+                    }
                 }
                 LocaValue::Error => (),
                 _ => {
@@ -410,6 +412,7 @@ fn validate_gui_loca(key: &Token, loca_value: LocaValue, data: &Everything) {
                     if format.as_str().contains('E') || format.as_str().contains('e') {
                         if let Some(concept) = chain.as_gameconcept() {
                             data.verify_exists(Item::GameConcept, concept);
+                            validate_loca_format(format, Datatype::Unknown, data);
                             return;
                         }
                     }
@@ -417,7 +420,7 @@ fn validate_gui_loca(key: &Token, loca_value: LocaValue, data: &Everything) {
             }
 
             let mut sc = ScopeContext::new(Scopes::None, key);
-            validate_datatypes(
+            let dtype = validate_datatypes(
                 &chain,
                 data,
                 &mut sc,
@@ -426,6 +429,9 @@ fn validate_gui_loca(key: &Token, loca_value: LocaValue, data: &Everything) {
                 format.as_ref(),
                 false,
             );
+            if let Some(format) = format {
+                validate_loca_format(&format, dtype, data);
+            }
         }
         LocaValue::Icon(token) => {
             data.verify_exists(Item::TextIcon, &token);
@@ -3,6 +3,7 @@ use crate::context::ScopeContext;
 use crate::db::{Db, DbKind};
 use crate::everything::Everything;
 use crate::game::GameFlags;
+use crate::helpers::TigerHashSet;
 use crate::hoi4::data::subunits::validate_equipment_stat;
 use crate::item::{Item, ItemLoader};
 use crate::report::{err, ErrorKey};
@@ -59,6 +60,122 @@ impl Equipment {
             err(ErrorKey::UnknownField).msg(msg).loc(key).push();
         }
     }
+
+    /// Check the `parent` and `archetype` relations between equipment, and that the stats
+    /// every producible equipment needs are set after inheritance.
+    pub fn crosscheck(data: &Everything) {
+        let mut equipment: Vec<(&Token, &Block)> =
+            data.database.iter_key_block(Item::Equipment).collect();
+        equipment.sort_unstable_by_key(|(key, _)| &key.loc);
+
+        // Equipment in loops that were already reported
+        let mut in_loop = TigerHashSet::default();
+        for &(key, block) in &equipment {
+            // Walk up the parents, checking for loops. Each loop is reported only once, from
+            // the member that is defined first.
+            let mut seen = vec![key.as_str()];
+            let mut links = Vec::new();
+            let mut current = block;
+            while let Some(parent) = current.get_field_value("parent") {
+                links.push(parent);
+                if seen.contains(&parent.as_str()) {
+                    if parent == key && !in_loop.contains(key.as_str()) {
+                        let msg = format!("equipment {key} is its own ancestor");
+                        let mut report = err(ErrorKey::Loop).msg(msg).loc(links[0]);
+                        for link in &links[1..] {
+                            report = report.loc_msg(*link, "through here");
+                        }
+                        report.push();
+                        in_loop.extend(seen);
+                    }
+                    break;
+                }
+                seen.push(parent.as_str());
+                let Some((_, block)) = data.get_key_block(Item::Equipment, parent.as_str()) else {
+                    break;
+                };
+                current = block;
+            }
+
+            if let Some(archetype) = block.get_field_value("archetype") {
+                if let Some((_, arch_block)) =
+                    data.get_key_block(Item::Equipment, archetype.as_str())
+                {
+                    if !arch_block.get_field_bool("is_archetype").unwrap_or(false) {
+                        let msg = format!("{archetype} is not an archetype");
+                        let info = "it needs `is_archetype = yes`";
+                        err(ErrorKey::Validation).msg(msg).info(info).loc(archetype).push();
+                    }
+                }
+                if let Some(parent) = block.get_field_value("parent") {
+                    if let Some(parent_archetype) = inherited_value(parent, "archetype", data) {
+                        if parent_archetype != archetype {
+                            let msg = format!(
+                                "{key} has archetype {archetype} but its parent {parent} has archetype {parent_archetype}"
+                            );
+                            err(ErrorKey::Validation)
+                                .msg(msg)
+                                .loc(archetype)
+                                .loc_msg(parent_archetype, "parent's archetype")
+                                .push();
+                        }
+                    }
+                }
+            }
+
+            if block.get_field_bool("is_archetype").unwrap_or(false)
+                || !block.get_field_bool("is_buildable").unwrap_or(true)
+            {
+                continue;
+            }
+            for stat in REQUIRED_STATS {
+                if !has_inherited_field(key, stat, data) {
+                    let msg = format!("equipment {key} has no `{stat}`, and inherits none");
+                    let info = "set it here, in a parent, or in the archetype";
+                    err(ErrorKey::FieldMissing).msg(msg).info(info).loc(key).push();
+                }
+            }
+        }
+    }
+}
+
+/// Stats that every equipment that can be produced needs, either directly or inherited.
+const REQUIRED_STATS: &[&str] = &["type", "build_cost_ic"];
+
+/// Return the equipment itself, then its parents, then the archetype, in the order that
+/// fields are inherited from them. Stops at loops.
+fn inheritance_chain<'a>(key: &str, data: &'a Everything) -> Vec<&'a Block> {
+    let mut chain = Vec::new();
+    let mut seen = TigerHashSet::default();
+    let mut next = Some(key);
+    let mut archetype = None;
+    while let Some(key) = next {
+        if !seen.insert(key) {
+            break;
+        }
+        let Some((_, block)) = data.get_key_block(Item::Equipment, key) else {
+            break;
+        };
+        chain.push(block);
+        archetype = archetype.or_else(|| block.get_field_value("archetype"));
+        next = block.get_field_value("parent").map(Token::as_str);
+    }
+    if let Some(archetype) = archetype {
+        if seen.insert(archetype.as_str()) {
+            if let Some((_, block)) = data.get_key_block(Item::Equipment, archetype.as_str()) {
+                chain.push(block);
+            }
+        }
+    }
+    chain
+}
+
+fn inherited_value<'a>(key: &Token, field: &str, data: &'a Everything) -> Option<&'a Token> {
+    inheritance_chain(key.as_str(), data).into_iter().find_map(|b| b.get_field_value(field))
+}
+
+fn has_inherited_field(key: &Token, field: &str, data: &Everything) -> bool {
+    inheritance_chain(key.as_str(), data).iter().any(|b| b.has_key(field))
 }
 
 impl EquipmentGroup {
@@ -435,3 +552,26 @@ const MISSION_TYPES: &[&str] = &[
     "air_supply",
     "sam_mission",
 ];
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::report::take_reports_for;
+
+    #[test]
+    fn test_parent_loop() {
+        let mod_root = PathBuf::from("tests/files/hoi4");
+        let mut data = Everything::new(None, None, &mod_root, Vec::new()).unwrap();
+        data.load_all();
+        let pathname = "common/units/equipment/test_equipment.txt";
+        take_reports_for(pathname);
+
+        Equipment::crosscheck(&data);
+        let reports = take_reports_for(pathname);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].msg, "equipment test_loop_a is its own ancestor");
+        assert_eq!(reports[0].pointers.len(), 3);
+    }
+}
@@ -3,8 +3,9 @@ use crate::context::ScopeContext;
 use crate::db::{Db, DbKind};
 use crate::everything::Everything;
 use crate::game::GameFlags;
+use crate::helpers::{TigerHashMap, TigerHashSet};
 use crate::item::{Item, ItemLoader};
-use crate::report::{err, ErrorKey};
+use crate::report::{err, warn, ErrorKey};
 use crate::scopes::Scopes;
 use crate::token::Token;
 use crate::tooltipped::Tooltipped;
@@ -55,6 +56,306 @@ impl NationalFocusTree {
             err(ErrorKey::UnknownField).msg(msg).loc(key).push();
         }
     }
+
+    /// Check each focus tree as a whole: where its foci end up on the grid, and how they
+    /// depend on each other.
+    pub fn crosscheck(data: &Everything) {
+        // The tree each focus is defined in. Shared foci are not in this map.
+        let mut tree_of: TigerHashMap<&str, &Token> = TigerHashMap::default();
+        for (tree, block) in data.database.iter_key_block(Item::NationalFocusTree) {
+            for block in block.get_field_blocks("focus") {
+                if let Some(id) = block.get_field_value("id") {
+                    tree_of.insert(id.as_str(), tree);
+                }
+            }
+        }
+        let shared: Vec<(&Token, &Block)> = data
+            .database
+            .iter_key_block(Item::NationalFocus)
+            .filter(|(key, _)| !tree_of.contains_key(key.as_str()))
+            .collect();
+
+        for (tree, block) in data.database.iter_key_block(Item::NationalFocusTree) {
+            let mut foci = TigerHashMap::default();
+            for block in block.get_field_blocks("focus") {
+                if let Some(id) = block.get_field_value("id") {
+                    foci.insert(id.as_str(), (id, block));
+                }
+            }
+            // A shared focus brings along the shared foci that follow from it.
+            let mut included: TigerHashSet<&str> =
+                block.get_field_values("shared_focus").into_iter().map(Token::as_str).collect();
+            loop {
+                let before = included.len();
+                for (key, block) in &shared {
+                    if !included.contains(key.as_str())
+                        && prerequisites(block).flatten().any(|p| included.contains(p.as_str()))
+                    {
+                        included.insert(key.as_str());
+                    }
+                }
+                if included.len() == before {
+                    break;
+                }
+            }
+            for (key, block) in &shared {
+                if included.contains(key.as_str()) {
+                    foci.insert(key.as_str(), (key, block));
+                }
+            }
+
+            let check = FocusTreeCheck { tree, foci, tree_of: &tree_of };
+            check.check_positions();
+            check.check_prerequisites();
+            check.check_mutually_exclusive();
+        }
+    }
+}
+
+/// Return the `prerequisite` blocks of a focus. All of them must be met, and each is met by
+/// completing any one of the foci in it.
+fn prerequisites(block: &Block) -> impl Iterator<Item = Vec<&Token>> {
+    block.get_field_blocks("prerequisite").into_iter().map(|block| block.get_field_values("focus"))
+}
+
+/// Return the foci that can be taken, assuming the ones in `reachable` already can.
+/// A focus can be taken if each of its prerequisite blocks has a focus in it that can be
+/// taken.
+fn reachable_foci<'a>(
+    foci: &[(&'a Token, &'a Block)],
+    mut reachable: TigerHashSet<&'a str>,
+) -> TigerHashSet<&'a str> {
+    loop {
+        let before = reachable.len();
+        for &(id, block) in foci {
+            if !reachable.contains(id.as_str())
+                && prerequisites(block)
+                    .all(|options| options.iter().any(|p| reachable.contains(p.as_str())))
+            {
+                reachable.insert(id.as_str());
+            }
+        }
+        if reachable.len() == before {
+            break;
+        }
+    }
+    reachable
+}
+
+fn mutually_exclusive(block: &Block) -> Vec<&Token> {
+    block.get_field_block("mutually_exclusive").map_or(Vec::new(), |b| b.get_field_values("focus"))
+}
+
+struct FocusTreeCheck<'a> {
+    tree: &'a Token,
+    /// The foci in this tree, including the shared ones, by id.
+    foci: TigerHashMap<&'a str, (&'a Token, &'a Block)>,
+    tree_of: &'a TigerHashMap<&'a str, &'a Token>,
+}
+
+impl<'a> FocusTreeCheck<'a> {
+    /// Resolve the grid position of each focus and report foci that end up on the same spot.
+    /// Offsets that depend on a trigger can't be resolved, so foci that have them are skipped.
+    fn check_positions(&self) {
+        let mut positions = TigerHashMap::default();
+        let mut by_position: TigerHashMap<(i64, i64), &Token> = TigerHashMap::default();
+        let mut ids: Vec<&(&Token, &Block)> = self.foci.values().collect();
+        ids.sort_unstable_by_key(|(id, _)| &id.loc);
+        for &(id, block) in ids {
+            if block.get_field_blocks("offset").iter().any(|b| b.has_key("trigger")) {
+                continue;
+            }
+            if let Some(pos) = self.position(id, &mut positions, &mut Vec::new()) {
+                if let Some(other) = by_position.get(&pos) {
+                    let msg =
+                        format!("focus {id} is in the same place as {other} in {}", self.tree);
+                    let info = format!("both are at x = {}, y = {}", pos.0, pos.1);
+                    warn(ErrorKey::Validation)
+                        .msg(msg)
+                        .info(info)
+                        .loc(id)
+                        .loc_msg(*other, "other focus")
+                        .push();
+                } else {
+                    by_position.insert(pos, id);
+                }
+            }
+        }
+    }
+
+    fn position(
+        &self,
+        id: &'a Token,
+        positions: &mut TigerHashMap<&'a str, Option<(i64, i64)>>,
+        chain: &mut Vec<&'a Token>,
+    ) -> Option<(i64, i64)> {
+        if let Some(&pos) = positions.get(id.as_str()) {
+            return pos;
+        }
+        let &(_, block) = self.foci.get(id.as_str())?;
+        if let Some(start) = chain.iter().position(|t| t.as_str() == id.as_str()) {
+            let msg = format!("focus {id} is positioned relative to itself");
+            let mut report = err(ErrorKey::Loop).msg(msg).loc(id);
+            for token in &chain[start + 1..] {
+                report = report.loc_msg(*token, "through here");
+            }
+            report.push();
+            positions.insert(id.as_str(), None);
+            return None;
+        }
+        let mut x = block.get_field_integer("x").unwrap_or(0);
+        let mut y = block.get_field_integer("y").unwrap_or(0);
+        // Offsets with a trigger only apply sometimes, so use the position without them.
+        for block in block.get_field_blocks("offset") {
+            if block.has_key("trigger") {
+                continue;
+            }
+            x += block.get_field_integer("x").unwrap_or(0);
+            y += block.get_field_integer("y").unwrap_or(0);
+        }
+        let mut pos = Some((x, y));
+        if let Some(relative) = block.get_field_value("relative_position_id") {
+            chain.push(id);
+            pos = self
+                .position(relative, positions, chain)
+                .map(|(rel_x, rel_y)| (rel_x + x, rel_y + y));
+            chain.pop();
+        }
+        positions.insert(id.as_str(), pos);
+        pos
+    }
+
+    /// Return the foci in the order they are defined, so that reports come out the same every run.
+    fn sorted_foci(&self) -> Vec<(&'a Token, &'a Block)> {
+        let mut foci: Vec<(&Token, &Block)> = self.foci.values().copied().collect();
+        foci.sort_unstable_by_key(|(id, _)| &id.loc);
+        foci
+    }
+
+    /// Report prerequisites in other trees, prerequisite cycles, and foci that can never be taken.
+    fn check_prerequisites(&self) {
+        let foci = self.sorted_foci();
+        // Foci that were already reported for a problem with their prerequisites.
+        let mut reported: TigerHashSet<&str> = TigerHashSet::default();
+        for &(id, block) in &foci {
+            for options in prerequisites(block) {
+                for prereq in options {
+                    if let Some(&tree) = self.tree_of.get(prereq.as_str()) {
+                        if tree != self.tree {
+                            let msg = format!("prerequisite {prereq} is in another focus tree");
+                            let info = format!("it is in {tree}, and {id} is in {}", self.tree);
+                            err(ErrorKey::Validation).msg(msg).info(info).loc(prereq).push();
+                            reported.insert(id.as_str());
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut state = TigerHashMap::default();
+        for &(id, _) in &foci {
+            self.find_cycle(id, &mut state, &mut Vec::new(), &mut reported);
+        }
+
+        let reachable = reachable_foci(&foci, TigerHashSet::default());
+        // The foci that would be reachable if the reported ones were fixed. The others have a
+        // problem of their own.
+        let excused = reachable_foci(&foci, reported);
+        for &(id, block) in &foci {
+            if !reachable.contains(id.as_str()) {
+                if excused.contains(id.as_str()) {
+                    // Its blocker was already reported
+                    continue;
+                }
+                let msg = format!("focus {id} can never be taken");
+                let info = "one of its prerequisite blocks has no focus that can be completed in this tree";
+                warn(ErrorKey::Validation).msg(msg).info(info).loc(id).push();
+                continue;
+            }
+            let required: Vec<&Token> =
+                prerequisites(block).filter(|o| o.len() == 1).map(|o| o[0]).collect();
+            for (i, first) in required.iter().enumerate() {
+                for second in &required[i + 1..] {
+                    if self.excludes(first, second) {
+                        let msg = format!(
+                            "focus {id} needs both {first} and {second}, which are mutually exclusive"
+                        );
+                        warn(ErrorKey::Validation).msg(msg).loc(id).push();
+                    }
+                }
+            }
+        }
+    }
+
+    fn excludes(&self, a: &Token, b: &Token) -> bool {
+        let excluded_by = |x: &Token, y: &Token| {
+            self.foci
+                .get(x.as_str())
+                .is_some_and(|(_, block)| mutually_exclusive(block).contains(&y))
+        };
+        excluded_by(a, b) || excluded_by(b, a)
+    }
+
+    /// Depth-first search for cycles among the prerequisites. `state` is `false` while a focus
+    /// is being searched and `true` once it's done. The foci in reported cycles are added to
+    /// `in_cycle`.
+    fn find_cycle(
+        &self,
+        id: &'a Token,
+        state: &mut TigerHashMap<&'a str, bool>,
+        chain: &mut Vec<&'a Token>,
+        in_cycle: &mut TigerHashSet<&'a str>,
+    ) {
+        match state.get(id.as_str()) {
+            Some(true) => return,
+            Some(false) => {
+                let msg = format!("focus {id} is its own prerequisite");
+                let mut report = err(ErrorKey::Loop).msg(msg).loc(id);
+                let start = chain.iter().position(|t| t.as_str() == id.as_str()).unwrap_or(0);
+                for token in &chain[start + 1..] {
+                    report = report.loc_msg(*token, "through here");
+                }
+                report.push();
+                in_cycle.extend(chain[start..].iter().map(|t| t.as_str()));
+                return;
+            }
+            None => (),
+        }
+        let Some(&(_, block)) = self.foci.get(id.as_str()) else {
+            return;
+        };
+        state.insert(id.as_str(), false);
+        chain.push(id);
+        for options in prerequisites(block) {
+            for prereq in options {
+                self.find_cycle(prereq, state, chain, in_cycle);
+            }
+        }
+        chain.pop();
+        state.insert(id.as_str(), true);
+    }
+
+    /// `mutually_exclusive` has to be declared on both foci to work both ways.
+    fn check_mutually_exclusive(&self) {
+        for (id, block) in self.sorted_foci() {
+            for other in mutually_exclusive(block) {
+                if let Some(&(other_id, other_block)) = self.foci.get(other.as_str()) {
+                    if !mutually_exclusive(other_block).contains(&id) {
+                        let msg = format!(
+                            "{id} is mutually exclusive with {other}, but not the other way around"
+                        );
+                        let info = format!("add {id} to the `mutually_exclusive` of {other}");
+                        warn(ErrorKey::Validation)
+                            .msg(msg)
+                            .info(info)
+                            .loc(other)
+                            .loc_msg(other_id, "other focus")
+                            .push();
+                    }
+                }
+            }
+        }
+    }
 }
 
 impl DbKind for NationalFocusTree {
@@ -165,3 +466,28 @@ impl DbKind for NationalFocusStyle {
         vd.field_item("current", Item::Sprite);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::report::take_reports_for;
+
+    #[test]
+    fn test_relative_to_triggered_offset() {
+        let mod_root = PathBuf::from("tests/files/hoi4");
+        let mut data = Everything::new(None, None, &mod_root, Vec::new()).unwrap();
+        data.load_all();
+        let pathname = "common/national_focus/test_focus.txt";
+        take_reports_for(pathname);
+
+        NationalFocusTree::crosscheck(&data);
+        let msgs: Vec<_> =
+            take_reports_for(pathname).into_iter().map(|report| report.msg).collect();
+        assert_eq!(
+            msgs,
+            ["focus test_focus_below is in the same place as test_focus_child in test_focus_tree"]
+        );
+    }
+}
@@ -3,9 +3,10 @@ use crate::context::ScopeContext;
 use crate::db::{Db, DbKind};
 use crate::everything::Everything;
 use crate::game::GameFlags;
+use crate::helpers::TigerHashMap;
 use crate::item::{Item, ItemLoader};
 use crate::modif::{validate_modifs, ModifKinds};
-use crate::report::{err, ErrorKey};
+use crate::report::{err, warn, ErrorKey};
 use crate::scopes::Scopes;
 use crate::token::Token;
 use crate::tooltipped::Tooltipped;
@@ -43,6 +44,95 @@ impl Technology {
             err(ErrorKey::UnknownField).msg(msg).info(info).loc(key).push();
         }
     }
+
+    /// Check the technology tree as a whole: the paths between technologies and their places
+    /// in the folders.
+    pub fn crosscheck(data: &Everything) {
+        let mut techs: Vec<(&Token, &Block)> =
+            data.database.iter_key_block(Item::Technology).collect();
+        techs.sort_unstable_by_key(|(key, _)| &key.loc);
+
+        let mut positions: TigerHashMap<(&str, i64, i64), &Token> = TigerHashMap::default();
+        for &(key, block) in &techs {
+            // A technology can be shown in several folders.
+            let folders = block.get_field_blocks("folder");
+            if folders.is_empty() {
+                if !block.get_field_bool("is_special_project_tech").unwrap_or(false) {
+                    let msg = format!("technology {key} is not in any folder");
+                    let info = "it will not be shown in the technology screen";
+                    warn(ErrorKey::Validation).msg(msg).info(info).loc(key).push();
+                }
+                continue;
+            }
+            // Technologies that depend on `allow_branch` may share a spot with alternatives.
+            if block.has_key("allow_branch") {
+                continue;
+            }
+            for folder in folders {
+                let (Some(name), Some(position)) =
+                    (folder.get_field_value("name"), folder.get_field_block("position"))
+                else {
+                    continue;
+                };
+                let x = position.get_field_integer("x").unwrap_or(0);
+                let y = position.get_field_integer("y").unwrap_or(0);
+                if let Some(other) = positions.get(&(name.as_str(), x, y)) {
+                    let msg = format!("technology {key} is in the same place as {other} in {name}");
+                    let info = format!("both are at x = {x}, y = {y}");
+                    warn(ErrorKey::Validation)
+                        .msg(msg)
+                        .info(info)
+                        .loc(key)
+                        .loc_msg(*other, "other technology")
+                        .push();
+                } else {
+                    positions.insert((name.as_str(), x, y), key);
+                }
+            }
+        }
+
+        // Find cycles in the paths with a depth-first search.
+        // `state` is false while a technology is being searched and true once it's done.
+        let mut state = TigerHashMap::default();
+        for &(key, _) in &techs {
+            find_path_cycle(key, data, &mut state, &mut Vec::new());
+        }
+    }
+}
+
+fn find_path_cycle<'a>(
+    key: &'a Token,
+    data: &'a Everything,
+    state: &mut TigerHashMap<&'a str, bool>,
+    chain: &mut Vec<&'a Token>,
+) {
+    match state.get(key.as_str()) {
+        Some(true) => return,
+        Some(false) => {
+            let msg = format!("technology {key} leads back to itself");
+            let mut report = err(ErrorKey::Loop).msg(msg).loc(key);
+            let start = chain.iter().position(|t| t.as_str() == key.as_str()).unwrap_or(0);
+            for token in &chain[start + 1..] {
+                report = report.loc_msg(*token, "through here");
+            }
+            report.push();
+            return;
+        }
+        None => (),
+    }
+    let Some((_, block)) = data.get_key_block(Item::Technology, key.as_str()) else {
+        // Missing technologies are reported by the validation
+        return;
+    };
+    state.insert(key.as_str(), false);
+    chain.push(key);
+    for path in block.get_field_blocks("path") {
+        if let Some(next) = path.get_field_value("leads_to_tech") {
+            find_path_cycle(next, data, state, chain);
+        }
+    }
+    chain.pop();
+    state.insert(key.as_str(), true);
 }
 
 /// Loads both technology categories and technology folders
@@ -160,3 +250,33 @@ impl DbKind for TechnologySharing {
         vd.field_trigger_rooted("available", Tooltipped::No, Scopes::Country);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::report::take_reports_for;
+
+    #[test]
+    fn test_crosscheck() {
+        let mod_root = PathBuf::from("tests/files/hoi4");
+        let mut data = Everything::new(None, None, &mod_root, Vec::new()).unwrap();
+        data.load_all();
+        let pathname = "common/technologies/test_tech.txt";
+        take_reports_for(pathname);
+
+        Technology::crosscheck(&data);
+        let mut msgs: Vec<_> =
+            take_reports_for(pathname).into_iter().map(|report| report.msg).collect();
+        msgs.sort();
+        assert_eq!(
+            msgs,
+            [
+                "technology test_tech_a leads back to itself",
+                "technology test_tech_c is in the same place as test_tech_a in test_other_folder",
+                "technology test_tech_d is not in any folder",
+            ]
+        );
+    }
+}
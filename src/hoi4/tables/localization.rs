@@ -1,5 +1,6 @@
 use std::sync::LazyLock;
 
+use crate::data::localization::FormatKind;
 use crate::helpers::TigerHashSet;
 
 pub(crate) static BUILTIN_MACROS_HOI4: LazyLock<TigerHashSet<&'static str>> =
@@ -988,3 +989,38 @@ const BUILTIN_MACROS: &[&str] = &[
     "YESNO",
     "YES_NO",
 ];
+
+/// The built-in formatting codes that can follow the `|` in `[code|format]`.
+/// Single-letter textformats defined in the gui files can be used as color codes too, so they
+/// don't have to be listed here.
+pub(crate) const FORMAT_CODES_HOI4: &[(char, FormatKind)] = &[
+    ('0', FormatKind::Number),
+    ('1', FormatKind::Number),
+    ('2', FormatKind::Number),
+    ('3', FormatKind::Number),
+    ('4', FormatKind::Number),
+    ('5', FormatKind::Number),
+    ('6', FormatKind::Number),
+    ('7', FormatKind::Number),
+    ('8', FormatKind::Number),
+    ('9', FormatKind::Number),
+    ('%', FormatKind::Number),
+    ('+', FormatKind::Number),
+    ('-', FormatKind::Number),
+    ('=', FormatKind::Number),
+    ('*', FormatKind::Number),
+    ('^', FormatKind::Number),
+    ('R', FormatKind::Color),
+    ('G', FormatKind::Color),
+    ('B', FormatKind::Color),
+    ('Y', FormatKind::Color),
+    ('W', FormatKind::Color),
+    ('H', FormatKind::Color),
+    ('T', FormatKind::Color),
+    ('O', FormatKind::Color),
+    ('L', FormatKind::Color),
+    ('C', FormatKind::Color),
+    ('g', FormatKind::Color),
+    ('b', FormatKind::Color),
+    ('t', FormatKind::Color),
+];
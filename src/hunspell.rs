@@ -0,0 +1,519 @@
+//! Reader for Hunspell `.aff` and `.dic` dictionaries, with enough of the affix and compounding
+//! rules to check the spelling of words and to suggest corrections.
+//!
+//! This is an approximation of what Hunspell does. Features that only affect rare words, such as
+//! the morphological fields or the compounding restrictions beyond the basic flags, are ignored.
+
+use std::fs::read;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use encoding_rs::{Encoding, UTF_8};
+
+use crate::helpers::TigerHashMap;
+
+type Flag = u32;
+
+/// Don't look at more parts than this when trying to split a compound word.
+const MAX_COMPOUND_PARTS: usize = 3;
+
+/// The number of corrections to suggest for a misspelled word.
+const MAX_SUGGESTIONS: usize = 4;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum FlagType {
+    /// Each character is a flag.
+    #[default]
+    Char,
+    /// Each pair of characters is a flag.
+    Long,
+    /// Flags are comma-separated numbers.
+    Num,
+}
+
+impl FlagType {
+    fn parse(self, s: &str) -> Vec<Flag> {
+        match self {
+            FlagType::Char => s.chars().map(u32::from).collect(),
+            FlagType::Long => {
+                let chars: Vec<char> = s.chars().collect();
+                chars
+                    .chunks(2)
+                    .map(|pair| pair.iter().fold(0, |flag, c| (flag << 16) | u32::from(*c)))
+                    .collect()
+            }
+            FlagType::Num => s.split(',').filter_map(|n| n.trim().parse().ok()).collect(),
+        }
+    }
+}
+
+/// One part of an affix condition, which matches one character.
+#[derive(Debug, Clone)]
+enum CondPart {
+    Any,
+    Char(char),
+    Set(Vec<char>),
+    NotSet(Vec<char>),
+}
+
+impl CondPart {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            CondPart::Any => true,
+            CondPart::Char(ch) => c == *ch,
+            CondPart::Set(set) => set.contains(&c),
+            CondPart::NotSet(set) => !set.contains(&c),
+        }
+    }
+}
+
+/// Parse a condition like `[^aeiou]y`, which is a very limited regular expression.
+fn parse_condition(s: &str) -> Vec<CondPart> {
+    let mut parts = Vec::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => parts.push(CondPart::Any),
+            '[' => {
+                let mut set = Vec::new();
+                let mut negated = false;
+                for c in chars.by_ref() {
+                    match c {
+                        ']' => break,
+                        '^' if set.is_empty() && !negated => negated = true,
+                        c => set.push(c),
+                    }
+                }
+                parts.push(if negated { CondPart::NotSet(set) } else { CondPart::Set(set) });
+            }
+            c => parts.push(CondPart::Char(c)),
+        }
+    }
+    parts
+}
+
+#[derive(Debug, Clone)]
+struct Affix {
+    flag: Flag,
+    /// Whether the affix can be combined with an affix of the other kind.
+    cross_product: bool,
+    /// The characters removed from the root before adding the affix.
+    strip: String,
+    add: String,
+    /// The flags of the affixes that can be added after this one.
+    cont_flags: Vec<Flag>,
+    condition: Vec<CondPart>,
+}
+
+impl Affix {
+    /// If `word` can be formed by adding this suffix to a root, return that root.
+    fn unapply_suffix(&self, word: &str) -> Option<String> {
+        let stem = word.strip_suffix(self.add.as_str())?;
+        if stem.is_empty() {
+            return None;
+        }
+        let root = format!("{stem}{}", self.strip);
+        let count = root.chars().count();
+        if count < self.condition.len() {
+            return None;
+        }
+        let tail = root.chars().skip(count - self.condition.len());
+        tail.zip(&self.condition).all(|(c, part)| part.matches(c)).then_some(root)
+    }
+
+    /// If `word` can be formed by adding this prefix to a root, return that root.
+    fn unapply_prefix(&self, word: &str) -> Option<String> {
+        let stem = word.strip_prefix(self.add.as_str())?;
+        if stem.is_empty() {
+            return None;
+        }
+        let root = format!("{}{stem}", self.strip);
+        if root.chars().count() < self.condition.len() {
+            return None;
+        }
+        root.chars().zip(&self.condition).all(|(c, part)| part.matches(c)).then_some(root)
+    }
+}
+
+/// The position of a part in a compound word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompoundPos {
+    Begin,
+    Middle,
+    End,
+}
+
+/// A loaded Hunspell dictionary.
+#[derive(Debug, Default)]
+pub struct Dictionary {
+    flag_type: FlagType,
+    /// The flag aliases defined with `AF`, which can be referred to by number in the `.dic` file.
+    flag_aliases: Vec<Vec<Flag>>,
+    /// The root words and their flags.
+    words: TigerHashMap<String, Vec<Flag>>,
+    prefixes: Vec<Affix>,
+    suffixes: Vec<Affix>,
+    /// The characters to try when looking for corrections, most common first.
+    try_chars: Vec<char>,
+    /// Common mistakes and their corrections.
+    replacements: Vec<(String, String)>,
+    forbidden: Option<Flag>,
+    need_affix: Option<Flag>,
+    only_in_compound: Option<Flag>,
+    compound: Option<Flag>,
+    compound_begin: Option<Flag>,
+    compound_middle: Option<Flag>,
+    compound_end: Option<Flag>,
+    compound_min: usize,
+}
+
+impl Dictionary {
+    /// Load the dictionary from a pair of `.aff` and `.dic` files.
+    pub fn read(aff_path: &Path, dic_path: &Path) -> Result<Self> {
+        let aff =
+            read(aff_path).with_context(|| format!("could not read {}", aff_path.display()))?;
+        let dic =
+            read(dic_path).with_context(|| format!("could not read {}", dic_path.display()))?;
+        // The `SET` line that gives the encoding is plain ASCII, so it can be found before decoding.
+        let encoding = aff
+            .split(|&b| b == b'\n')
+            .find_map(|line| line.strip_prefix(b"SET "))
+            .map_or(Some(UTF_8), |label| Encoding::for_label(label.trim_ascii()))
+            .context("unknown encoding in SET")?;
+        let (aff, _, _) = encoding.decode(&aff);
+        let (dic, _, _) = encoding.decode(&dic);
+        Self::parse(&aff, &dic)
+    }
+
+    pub fn parse(aff: &str, dic: &str) -> Result<Self> {
+        let mut dict = Self { compound_min: 3, ..Default::default() };
+        dict.parse_aff(aff);
+        dict.parse_dic(dic);
+        if dict.words.is_empty() {
+            bail!("dictionary has no words");
+        }
+        Ok(dict)
+    }
+
+    fn parse_aff(&mut self, aff: &str) {
+        // How many rules are still expected for each affix flag, after its header line.
+        let mut expected: TigerHashMap<Flag, usize> = TigerHashMap::default();
+        let mut cross_products: TigerHashMap<Flag, bool> = TigerHashMap::default();
+        for line in aff.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let flag_type = self.flag_type;
+            let flag = |i: usize| fields.get(i).and_then(|f| flag_type.parse(f).first().copied());
+            match fields[..] {
+                ["FLAG", "long", ..] => self.flag_type = FlagType::Long,
+                ["FLAG", "num", ..] => self.flag_type = FlagType::Num,
+                ["TRY", chars, ..] => self.try_chars = chars.chars().collect(),
+                ["REP", from, to, ..] => {
+                    self.replacements.push((from.replace('_', " "), to.replace('_', " ")));
+                }
+                ["AF", flags, ..] if flags.parse::<usize>().is_err() => {
+                    self.flag_aliases.push(self.flag_type.parse(flags));
+                }
+                ["FORBIDDENWORD", ..] => self.forbidden = flag(1),
+                ["NEEDAFFIX" | "PSEUDOROOT", ..] => self.need_affix = flag(1),
+                ["ONLYINCOMPOUND", ..] => self.only_in_compound = flag(1),
+                ["COMPOUNDFLAG", ..] => self.compound = flag(1),
+                ["COMPOUNDBEGIN", ..] => self.compound_begin = flag(1),
+                ["COMPOUNDMIDDLE", ..] => self.compound_middle = flag(1),
+                ["COMPOUNDEND", ..] => self.compound_end = flag(1),
+                ["COMPOUNDMIN", min, ..] => self.compound_min = min.parse().unwrap_or(3).max(1),
+                [kind @ ("PFX" | "SFX"), _, ..] => {
+                    let Some(affix_flag) = flag(1) else {
+                        continue;
+                    };
+                    let remaining = expected.entry(affix_flag).or_default();
+                    if *remaining == 0 {
+                        // This is the header line, with the cross product setting and the count.
+                        *remaining = fields.get(3).and_then(|n| n.parse().ok()).unwrap_or(0);
+                        cross_products.insert(affix_flag, fields.get(2) == Some(&"Y"));
+                        continue;
+                    }
+                    *remaining -= 1;
+                    let (Some(strip), Some(add)) = (fields.get(2), fields.get(3)) else {
+                        continue;
+                    };
+                    let (add, cont_flags) = add.split_once('/').unwrap_or((add, ""));
+                    let cont_flags = self.parse_flags(cont_flags);
+                    let affix = Affix {
+                        flag: affix_flag,
+                        cross_product: cross_products.get(&affix_flag) == Some(&true),
+                        strip: if *strip == "0" { String::new() } else { (*strip).to_owned() },
+                        add: if add == "0" { String::new() } else { add.to_owned() },
+                        cont_flags,
+                        condition: parse_condition(fields.get(4).unwrap_or(&".")),
+                    };
+                    if kind == "PFX" {
+                        self.prefixes.push(affix);
+                    } else {
+                        self.suffixes.push(affix);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn parse_dic(&mut self, dic: &str) {
+        // The first line is the approximate number of words.
+        for line in dic.lines().skip(1) {
+            // Morphological fields come after whitespace.
+            let Some(entry) = line.split(['\t', ' ']).next() else {
+                continue;
+            };
+            if entry.is_empty() || entry.starts_with('#') {
+                continue;
+            }
+            let (word, flags) = entry.split_once('/').unwrap_or((entry, ""));
+            let flags = self.parse_flags(flags);
+            self.words.entry(word.to_owned()).or_default().extend(flags);
+        }
+    }
+
+    fn parse_flags(&self, flags: &str) -> Vec<Flag> {
+        if !self.flag_aliases.is_empty() {
+            if let Ok(idx) = flags.parse::<usize>() {
+                // Aliases are numbered from 1.
+                return self.flag_aliases.get(idx.wrapping_sub(1)).cloned().unwrap_or_default();
+            }
+        }
+        self.flag_type.parse(flags)
+    }
+
+    /// Return true iff the word is spelled correctly, allowing for it being capitalized at the
+    /// start of a sentence or written in all capitals.
+    pub fn check(&self, word: &str) -> bool {
+        if self.check_exact(word) {
+            return true;
+        }
+        let mut chars = word.chars();
+        let Some(first) = chars.next() else {
+            return true;
+        };
+        if !first.is_uppercase() {
+            return false;
+        }
+        if chars.clone().all(|c| !c.is_lowercase()) {
+            let lower = word.to_lowercase();
+            if self.check_exact(&lower) || self.check_exact(&capitalize(&lower)) {
+                return true;
+            }
+        }
+        let uncapitalized: String = first.to_lowercase().chain(chars).collect();
+        self.check_exact(&uncapitalized)
+    }
+
+    fn check_exact(&self, word: &str) -> bool {
+        self.is_root(word)
+            || self.check_suffixed(word, None, None)
+            || self.check_prefixed(word, None)
+            || self.check_compound(word, CompoundPos::Begin, 1)
+    }
+
+    fn is_root(&self, word: &str) -> bool {
+        self.words.get(word).is_some_and(|flags| {
+            [self.forbidden, self.need_affix, self.only_in_compound]
+                .iter()
+                .flatten()
+                .all(|flag| !flags.contains(flag))
+        })
+    }
+
+    /// Return true iff `root` is a word that has all of the given flags.
+    fn root_has(&self, root: &str, flags: &[Option<Flag>]) -> bool {
+        self.words.get(root).is_some_and(|root_flags| {
+            self.forbidden.is_none_or(|forbidden| !root_flags.contains(&forbidden))
+                && flags.iter().flatten().all(|flag| root_flags.contains(flag))
+        })
+    }
+
+    /// Return true iff `word` is a root with one or two suffixes. If a prefix was already
+    /// removed, the root must have its flag too. If `extra` is given, the root must have that flag
+    /// too.
+    fn check_suffixed(&self, word: &str, prefix: Option<&Affix>, extra: Option<Flag>) -> bool {
+        let prefix_flag = prefix.map(|prefix| prefix.flag);
+        for sfx in &self.suffixes {
+            if prefix.is_some() && !sfx.cross_product {
+                continue;
+            }
+            let Some(stem) = sfx.unapply_suffix(word) else {
+                continue;
+            };
+            if self.root_has(&stem, &[Some(sfx.flag), prefix_flag, extra]) {
+                return true;
+            }
+            // Suffixes can allow other suffixes after them with their continuation flags.
+            for inner in &self.suffixes {
+                if inner.cont_flags.contains(&sfx.flag) {
+                    if let Some(root) = inner.unapply_suffix(&stem) {
+                        if self.root_has(&root, &[Some(inner.flag), prefix_flag, extra]) {
+                            return true;
+                        }
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    fn check_prefixed(&self, word: &str, extra: Option<Flag>) -> bool {
+        for pfx in &self.prefixes {
+            let Some(stem) = pfx.unapply_prefix(word) else {
+                continue;
+            };
+            if self.root_has(&stem, &[Some(pfx.flag), extra])
+                || (pfx.cross_product && self.check_suffixed(&stem, Some(pfx), extra))
+            {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Return true iff `word` can be split into words that may be compounded.
+    fn check_compound(&self, word: &str, pos: CompoundPos, parts: usize) -> bool {
+        if self.compound.is_none() && self.compound_begin.is_none() {
+            return false;
+        }
+        let boundaries: Vec<usize> = word.char_indices().map(|(i, _)| i).collect();
+        if boundaries.len() < self.compound_min * 2 {
+            return false;
+        }
+        for &split in &boundaries[self.compound_min..=boundaries.len() - self.compound_min] {
+            let (first, rest) = word.split_at(split);
+            if !self.compound_part(first, pos) {
+                continue;
+            }
+            if self.compound_part(rest, CompoundPos::End)
+                || self.compound_part(&capitalize(rest), CompoundPos::End)
+                || (parts + 1 < MAX_COMPOUND_PARTS
+                    && self.check_compound(rest, CompoundPos::Middle, parts + 1))
+            {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn compound_part(&self, part: &str, pos: CompoundPos) -> bool {
+        let pos_flag = match pos {
+            CompoundPos::Begin => self.compound_begin,
+            CompoundPos::Middle => self.compound_middle,
+            CompoundPos::End => self.compound_end,
+        };
+        [self.compound, pos_flag].into_iter().flatten().any(|flag| {
+            self.root_has(part, &[Some(flag)])
+                || self.check_suffixed(part, None, Some(flag))
+                || self.check_prefixed(part, Some(flag))
+        })
+    }
+
+    /// Return a few correctly spelled words that `word` may have been meant to be.
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        let mut suggestions = Vec::new();
+        let mut consider = |candidate: String| {
+            if suggestions.len() < MAX_SUGGESTIONS
+                && candidate != word
+                && !suggestions.contains(&candidate)
+                && candidate.split(' ').all(|part| self.check(part))
+            {
+                suggestions.push(candidate);
+            }
+        };
+
+        for (from, to) in &self.replacements {
+            for (i, _) in word.match_indices(from.as_str()) {
+                consider(format!("{}{to}{}", &word[..i], &word[i + from.len()..]));
+            }
+        }
+
+        let chars: Vec<char> = word.chars().collect();
+        let with = |f: &dyn Fn(&mut Vec<char>)| {
+            let mut chars = chars.clone();
+            f(&mut chars);
+            chars.into_iter().collect::<String>()
+        };
+        for i in 0..chars.len() {
+            if i + 1 < chars.len() {
+                consider(with(&|chars| chars.swap(i, i + 1)));
+            }
+            consider(with(&|chars| {
+                chars.remove(i);
+            }));
+        }
+        for &c in &self.try_chars {
+            for i in 0..=chars.len() {
+                if i < chars.len() && chars[i] != c {
+                    consider(with(&|chars| chars[i] = c));
+                }
+                consider(with(&|chars| chars.insert(i, c)));
+            }
+        }
+        for i in 1..chars.len() {
+            consider(with(&|chars| chars.insert(i, ' ')));
+        }
+        suggestions
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map_or_else(String::new, |first| first.to_uppercase().chain(chars).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AFF: &str = "\
+SET UTF-8
+TRY esianrtolcdugmphbyfvkwz
+REP 1
+REP f ph
+PFX U Y 1
+PFX U 0 un .
+SFX S Y 2
+SFX S y ies [^aeiou]y
+SFX S 0 s [^y]
+SFX D Y 1
+SFX D 0 ed/S .
+COMPOUNDFLAG X
+";
+
+    const DIC: &str = "\
+5
+dragon/SX
+fly/S
+kind/U
+lock/UD
+fire/X
+";
+
+    #[test]
+    fn test_check() {
+        let dict = Dictionary::parse(AFF, DIC).unwrap();
+        assert!(dict.check("dragon"));
+        assert!(dict.check("dragons"));
+        assert!(dict.check("Dragons"));
+        assert!(dict.check("DRAGONS"));
+        assert!(dict.check("flies"));
+        assert!(!dict.check("flys"));
+        assert!(dict.check("unkind"));
+        assert!(!dict.check("undragon"));
+        assert!(dict.check("unlocked"));
+        assert!(dict.check("firedragon"));
+        assert!(!dict.check("dragonfly"));
+        assert!(!dict.check("kinds"));
+    }
+
+    #[test]
+    fn test_suggest() {
+        let dict = Dictionary::parse(AFF, DIC).unwrap();
+        assert_eq!(dict.suggest("dargon"), vec!["dragon"]);
+        assert!(dict.suggest("unlockd").contains(&"unlocked".to_owned()));
+        assert!(dict.suggest("qqqqq").is_empty());
+    }
+}
@@ -3,7 +3,9 @@ use crate::context::ScopeContext;
 use crate::db::{Db, DbKind};
 use crate::everything::Everything;
 use crate::game::GameFlags;
+use crate::helpers::{TigerHashMap, TigerHashSet};
 use crate::item::{Item, ItemLoader};
+use crate::report::{err, warn, ErrorKey};
 use crate::scopes::Scopes;
 use crate::token::Token;
 use crate::tooltipped::Tooltipped;
@@ -25,25 +27,8 @@ impl Mission {
 
 impl DbKind for Mission {
     fn add_subitems(&self, _key: &Token, block: &Block, db: &mut Db) {
-        for (key, block) in block.iter_definitions() {
-            if !&[
-                "icon",
-                "header",
-                "repeatable",
-                "chance",
-                "ai_chance",
-                "on_potential",
-                "potential",
-                "on_start",
-                "abort",
-                "on_abort",
-                "on_completion",
-            ]
-            .iter()
-            .any(|&v| key.is(v))
-            {
-                db.add(Item::MissionTask, key.clone(), block.clone(), Box::new(MissionTask {}));
-            }
+        for (key, block) in tasks(block) {
+            db.add(Item::MissionTask, key.clone(), block.clone(), Box::new(MissionTask {}));
         }
     }
 
@@ -81,7 +66,126 @@ impl DbKind for Mission {
         vd.unknown_block_fields(|key, block| {
             validate_task(key, block, &mut sc, data);
         });
+
+        check_task_graph(block);
+    }
+}
+
+/// The fields of a mission that are not tasks.
+const MISSION_FIELDS: &[&str] = &[
+    "icon",
+    "header",
+    "repeatable",
+    "chance",
+    "ai_chance",
+    "on_potential",
+    "potential",
+    "on_start",
+    "abort",
+    "on_abort",
+    "on_completion",
+];
+
+fn tasks(block: &Block) -> impl Iterator<Item = (&Token, &Block)> {
+    block.iter_definitions().filter(|(key, _)| !MISSION_FIELDS.iter().any(|&v| key.is(v)))
+}
+
+/// Check the `requires` and `prevented_by` relations between the tasks of one mission.
+/// The tasks are visited in file order, so that the reports come out the same every run.
+fn check_task_graph(block: &Block) {
+    let ordered: Vec<(&Token, &Block)> = tasks(block).collect();
+    let tasks: TigerHashMap<&str, (&Token, &Block)> =
+        ordered.iter().map(|&(key, block)| (key.as_str(), (key, block))).collect();
+    let requires = |block: &Block| block.get_field_list("requires").unwrap_or_default();
+    let prevented_by = |block: &Block| block.get_field_list("prevented_by").unwrap_or_default();
+
+    let mut state = TigerHashMap::default();
+    let mut in_cycle = TigerHashSet::default();
+    for &(key, _) in &ordered {
+        find_cycle(key, &tasks, &mut state, &mut vec![key.clone()], &mut in_cycle);
+    }
+
+    // A task can become available once all the tasks it requires can be completed,
+    // unless it's prevented by one of those.
+    let mut available: TigerHashSet<&str> = TigerHashSet::default();
+    loop {
+        let before = available.len();
+        for &(key, block) in &ordered {
+            let key = key.as_str();
+            if available.contains(key) {
+                continue;
+            }
+            let required = requires(block);
+            let prevented = prevented_by(block);
+            if required.iter().all(|r| available.contains(r.as_str()))
+                && !required.iter().any(|r| prevented.contains(r))
+                && !required.iter().any(|r| {
+                    tasks.get(r.as_str()).is_some_and(|(_, rb)| {
+                        prevented_by(rb).iter().any(|p| required.contains(p))
+                    })
+                })
+            {
+                available.insert(key);
+            }
+        }
+        if available.len() == before {
+            break;
+        }
+    }
+
+    for &(key, block) in &ordered {
+        if available.contains(key.as_str()) || in_cycle.contains(key.as_str()) {
+            continue;
+        }
+        let msg = if block.get_field_bool("final").unwrap_or(false) {
+            format!("final task {key} can't be reached from the starting tasks")
+        } else {
+            format!("mission task {key} can never become available")
+        };
+        let info = "it requires tasks that are missing from this mission, that can't be completed, or that prevent each other";
+        warn(ErrorKey::Validation).msg(msg).info(info).loc(key).push();
+    }
+}
+
+/// Depth-first search for cycles in the `requires` relations. `state` is false while a task is
+/// being searched and true once it's done. The tasks in reported cycles are added to `in_cycle`.
+fn find_cycle<'a>(
+    key: &'a Token,
+    tasks: &TigerHashMap<&str, (&'a Token, &'a Block)>,
+    state: &mut TigerHashMap<&'a str, bool>,
+    chain: &mut Vec<Token>,
+    in_cycle: &mut TigerHashSet<&'a str>,
+) {
+    match state.get(key.as_str()) {
+        Some(true) => return,
+        Some(false) => {
+            let msg = format!("mission task {key} requires itself");
+            let start = chain.iter().position(|t| t.as_str() == key.as_str()).unwrap_or(0);
+            let mut report = err(ErrorKey::Loop).msg(msg).loc(&chain[chain.len() - 1]);
+            for token in &chain[start + 1..chain.len() - 1] {
+                report = report.loc_msg(token, "through here");
+            }
+            report.push();
+            for token in &chain[start..] {
+                if let Some((key, _)) = tasks.get(token.as_str()) {
+                    in_cycle.insert(key.as_str());
+                }
+            }
+            return;
+        }
+        None => (),
+    }
+    state.insert(key.as_str(), false);
+    if let Some(&(_, block)) = tasks.get(key.as_str()) {
+        for required in block.get_field_list("requires").unwrap_or_default() {
+            if let Some(&(required_key, _)) = tasks.get(required.as_str()) {
+                chain.push(required);
+                find_cycle(required_key, tasks, state, chain, in_cycle);
+                chain.pop();
+            }
+        }
     }
+    state.insert(key.as_str(), true);
 }
 
 fn validate_task(key: &Token, block: &Block, sc: &mut ScopeContext, data: &Everything) {
@@ -124,3 +228,66 @@ impl DbKind for MissionTask {
         // This is so the scopes saves in Mission can get passed down into the MissionTask properly while also saving MissionTask Items like that need to be saved.
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::pdxfile::parse_pdx_internal;
+    use crate::report::{take_reports_for, LogReport};
+
+    /// Run the task graph check on `input` as the contents of a mission and return the reports.
+    fn check(input: &'static str, desc: &str) -> Vec<LogReport> {
+        let block = parse_pdx_internal(input, desc);
+        check_task_graph(&block);
+        let mut reports = take_reports_for(desc);
+        reports.sort_by_key(|report| report.primary().loc);
+        reports
+    }
+
+    #[test]
+    fn test_cycles() {
+        let input = "
+            icon = test
+            task_start = { }
+            task_a = { requires = { task_start task_c } }
+            task_b = { requires = { task_a } }
+            task_c = { requires = { task_b } }
+            task_after = { requires = { task_c } }
+        ";
+        for _ in 0..5 {
+            let reports = check(input, "test_cycles.txt");
+            let msgs: Vec<_> = reports.iter().map(|report| report.msg.as_str()).collect();
+            assert_eq!(
+                msgs,
+                [
+                    "mission task task_a requires itself",
+                    "mission task task_after can never become available",
+                ]
+            );
+            assert_eq!(reports[0].pointers.len(), 3);
+        }
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let input = "
+            task_start = { }
+            task_left = { requires = { task_start } }
+            task_right = { requires = { task_start } prevented_by = { task_left } }
+            task_both = { requires = { task_left task_right } }
+            task_missing = { requires = { task_nowhere } }
+            task_end = { final = yes requires = { task_both } }
+            task_ok = { final = yes requires = { task_left } }
+        ";
+        let reports = check(input, "test_unreachable.txt");
+        let msgs: Vec<_> = reports.iter().map(|report| report.msg.as_str()).collect();
+        assert_eq!(
+            msgs,
+            [
+                "mission task task_both can never become available",
+                "mission task task_missing can never become available",
+                "final task task_end can't be reached from the starting tasks",
+            ]
+        );
+    }
+}
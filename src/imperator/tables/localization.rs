@@ -1,5 +1,6 @@
 use std::sync::LazyLock;
 
+use crate::data::localization::FormatKind;
 use crate::helpers::TigerHashSet;
 
 pub(crate) static BUILTIN_MACROS_IMPERATOR: LazyLock<TigerHashSet<&'static str>> =
@@ -482,3 +483,32 @@ const BUILTIN_MACROS: &[&str] = &[
     "YOU_HAVE",
     "ZEAL",
 ];
+
+/// The built-in formatting codes that can follow the `|` in `[code|format]`.
+/// Single-letter textformats defined in the gui files can be used as color codes too, so they
+/// don't have to be listed here.
+pub(crate) const FORMAT_CODES_IMPERATOR: &[(char, FormatKind)] = &[
+    ('0', FormatKind::Number),
+    ('1', FormatKind::Number),
+    ('2', FormatKind::Number),
+    ('3', FormatKind::Number),
+    ('4', FormatKind::Number),
+    ('5', FormatKind::Number),
+    ('6', FormatKind::Number),
+    ('7', FormatKind::Number),
+    ('8', FormatKind::Number),
+    ('9', FormatKind::Number),
+    ('%', FormatKind::Number),
+    ('+', FormatKind::Number),
+    ('-', FormatKind::Number),
+    ('=', FormatKind::Number),
+    ('*', FormatKind::Number),
+    ('U', FormatKind::Text),
+    ('L', FormatKind::Text),
+    ('E', FormatKind::Concept),
+    ('e', FormatKind::Concept),
+    ('P', FormatKind::Color),
+    ('N', FormatKind::Color),
+    ('V', FormatKind::Color),
+    ('H', FormatKind::Color),
+];
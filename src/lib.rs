@@ -18,7 +18,9 @@ compile_error!(
 );
 
 pub use crate::config_load::validate_config_file;
+pub use crate::data::localization::Language;
 pub use crate::everything::Everything;
+pub use crate::explain::{ScopeExplanation, ScopeFact};
 pub use crate::fileset::FileKind;
 pub use crate::game::Game;
 pub use crate::item::Item;
@@ -26,12 +28,14 @@ pub use crate::item::Item;
 pub use crate::mod_metadata::ModMetadata;
 #[cfg(any(feature = "ck3", feature = "imperator", feature = "hoi4"))]
 pub use crate::modfile::ModFile;
+pub use crate::render_loca::{MarkupStyle, RenderOptions};
 pub use crate::report::{
-    add_loaded_mod_root, disable_ansi_colors, emit_reports, log, set_output_file, set_output_style,
-    set_show_loaded_mods, set_show_vanilla, suppress_from_json, take_reports, Confidence,
-    LogReport, PointedMessage, Severity,
+    add_loaded_mod_root, check_ignore_directives, disable_ansi_colors, emit_reports, log,
+    set_output_file, set_output_style, set_show_loaded_mods, set_show_vanilla, suppress_from_json,
+    take_reports, Confidence, LogReport, OutputFormat, PointedMessage, Severity,
 };
 pub use crate::token::{Loc, Token};
+pub use crate::translations::TranslationFormat;
 
 #[cfg(feature = "ck3")]
 mod ck3;
@@ -43,6 +47,8 @@ mod imperator;
 mod vic3;
 
 mod block;
+#[cfg(feature = "jomini")]
+mod cmap;
 mod config_load;
 mod context;
 mod data;
@@ -55,10 +61,13 @@ mod effect;
 #[cfg(feature = "jomini")]
 mod effect_validation;
 mod everything;
+mod explain;
 mod fileset;
+mod flow;
 mod game;
 mod gui;
 mod helpers;
+mod hunspell;
 mod item;
 mod lowercase;
 mod macros;
@@ -71,15 +80,25 @@ mod on_action;
 mod parse;
 mod pathtable;
 mod pdxfile;
+mod pdxmodel;
+mod render_loca;
 mod report;
 mod rivers;
 mod scopes;
 #[cfg(feature = "jomini")]
 mod script_value;
+#[cfg(feature = "jomini")]
+mod sound_banks;
+mod spelling;
 mod token;
 mod tooltipped;
+mod translations;
 mod trigger;
+mod trigger_logic;
+mod upgrade;
 mod util;
 mod validate;
 mod validator;
+#[cfg(feature = "jomini")]
+mod value_range;
 mod variables;
@@ -1,6 +1,7 @@
 use lalrpop_util::lalrpop_mod;
 
-use crate::report::ErrorKey;
+use crate::report::{warn, ErrorKey};
+use crate::token::Loc;
 
 lalrpop_mod! {
     #[allow(clippy::pedantic)]
@@ -18,10 +19,12 @@ pub enum IgnoreSize {
     End,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct IgnoreFilter {
     key: Option<ErrorKey>,
     text: Option<String>,
+    /// Where the directive is, for reporting directives that didn't suppress anything.
+    loc: Option<Loc>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -34,7 +37,26 @@ pub fn parse_comment(comment: &str) -> Option<IgnoreSpec> {
     parser::CommentParser::new().parse(comment).ok()
 }
 
+/// Report a `#tiger-ignore(begin)` directive that was never closed.
+pub fn begin_without_end(loc: Loc) {
+    let msg = "`#tiger-ignore(begin)` without a matching `#tiger-ignore(end)`";
+    let info = "it does not suppress anything until it is closed";
+    warn(ErrorKey::IgnoreDirective).msg(msg).info(info).loc(loc).push();
+}
+
+/// Report a `#tiger-ignore(end)` directive that has no begin directive to close.
+pub fn end_without_begin(loc: Loc) {
+    let msg = "`#tiger-ignore(end)` without a matching `#tiger-ignore(begin)`";
+    warn(ErrorKey::IgnoreDirective).msg(msg).loc(loc).push();
+}
+
 impl IgnoreSpec {
+    /// Record where the directive was found.
+    pub fn at(mut self, loc: Loc) -> Self {
+        self.filter.loc = Some(loc);
+        self
+    }
+
     fn set_key(mut self, key: ErrorKey) -> Self {
         self.filter.key = Some(key);
         self
@@ -60,6 +82,20 @@ impl IgnoreSpec {
 }
 
 impl IgnoreFilter {
+    pub fn loc(&self) -> Option<Loc> {
+        self.loc
+    }
+
+    /// Describe which reports this filter is narrowed down to, if any.
+    pub fn describe(&self) -> Option<String> {
+        match (self.key, &self.text) {
+            (None, None) => None,
+            (Some(key), None) => Some(format!("key={key}")),
+            (None, Some(text)) => Some(format!("text=\"{text}\"")),
+            (Some(key), Some(text)) => Some(format!("key={key}, text=\"{text}\"")),
+        }
+    }
+
     pub fn matches(&self, other_key: ErrorKey, other_text: &str) -> bool {
         if let Some(key) = self.key {
             if key != other_key {
@@ -172,4 +208,15 @@ mod tests {
             assert_eq!(spec.filter.text, Some("missing english".to_owned()));
         }
     }
+
+    #[test]
+    fn test_describe() {
+        let spec = parse_comment("tiger-ignore(key=missing-item, text=\"script value\")").unwrap();
+        assert_eq!(
+            spec.filter.describe(),
+            Some("key=missing-item, text=\"script value\"".to_owned())
+        );
+        let spec = parse_comment("tiger-ignore(block)").unwrap();
+        assert!(spec.filter.describe().is_none());
+    }
 }
@@ -7,7 +7,9 @@ use crate::datatype::{Code, CodeArg, CodeChain};
 use crate::fileset::FileEntry;
 use crate::game::Game;
 use crate::parse::cob::Cob;
-use crate::parse::ignore::{parse_comment, IgnoreFilter, IgnoreSize};
+use crate::parse::ignore::{
+    begin_without_end, end_without_begin, parse_comment, IgnoreFilter, IgnoreSize,
+};
 use crate::report::register_ignore_filter;
 use crate::report::{untidy, warn, ErrorKey};
 use crate::token::{leak, Loc, Token};
@@ -33,7 +35,7 @@ struct LocaParser {
     loca_end: usize,
     value: Vec<LocaValue>,
     pending_line_ignores: Vec<IgnoreFilter>,
-    active_range_ignores: Vec<(u32, IgnoreFilter)>,
+    active_range_ignores: Vec<(Loc, IgnoreFilter)>,
 }
 
 impl LocaParser {
@@ -117,7 +119,7 @@ impl LocaParser {
     #[allow(clippy::unnecessary_wraps)]
     fn error_line(&mut self, key: Token) -> Option<LocaEntry> {
         self.skip_line();
-        Some(LocaEntry::new(key, LocaValue::Error, None))
+        Some(LocaEntry::new(key, LocaValue::Error, None, 0))
     }
 
     fn get_key(&mut self) -> Token {
@@ -258,8 +260,10 @@ impl LocaParser {
             // Skip comments and blank lines
             self.skip_whitespace();
             if self.chars.peek() == Some(&'#') {
+                let comment_loc = self.loc;
                 self.next_char();
-                if let Some(spec) = parse_comment(self.get_rest_of_line()) {
+                let spec = parse_comment(self.get_rest_of_line()).map(|spec| spec.at(comment_loc));
+                if let Some(spec) = spec {
                     match spec.size {
                         IgnoreSize::Line => self.pending_line_ignores.push(spec.filter),
                         IgnoreSize::Block => (),
@@ -269,12 +273,15 @@ impl LocaParser {
                             spec.filter,
                         ),
                         IgnoreSize::Begin => {
-                            self.active_range_ignores.push((self.loc.line + 1, spec.filter));
+                            self.active_range_ignores.push((comment_loc, spec.filter));
                         }
                         IgnoreSize::End => {
-                            if let Some((start_line, filter)) = self.active_range_ignores.pop() {
+                            if let Some((begin_loc, filter)) = self.active_range_ignores.pop() {
                                 let path = self.loc.pathname().to_path_buf();
-                                register_ignore_filter(path, start_line..self.loc.line, filter);
+                                let range = begin_loc.line + 1..comment_loc.line;
+                                register_ignore_filter(path, range, filter);
+                            } else {
+                                end_without_begin(comment_loc);
                             }
                         }
                     }
@@ -298,7 +305,12 @@ impl LocaParser {
         // Loop until we have a key. Once we have a key, we'll definitely
         // return a LocaEntry for the current line, though it might be an Error entry.
         self.skip_until_key();
-        self.chars.peek()?;
+        if self.chars.peek().is_none() {
+            for (begin_loc, _) in self.active_range_ignores.drain(..) {
+                begin_without_end(begin_loc);
+            }
+            return None;
+        }
         for filter in self.pending_line_ignores.drain(..) {
             let path = self.loc.pathname().to_path_buf();
             let line = self.loc.line;
@@ -314,9 +326,11 @@ impl LocaParser {
             return self.error_line(key);
         }
 
-        // Skip optional number after :
+        // Optional version number after :
+        let mut version = 0u32;
         while let Some(c) = self.chars.peek() {
-            if c.is_ascii_digit() {
+            if let Some(digit) = c.to_digit(10) {
+                version = version.saturating_mul(10).saturating_add(digit);
                 self.next_char();
             } else {
                 break;
@@ -398,7 +412,7 @@ impl LocaParser {
         } else {
             LocaValue::Concat(take(&mut self.value))
         };
-        Some(LocaEntry::new(key, value, Some(token)))
+        Some(LocaEntry::new(key, value, Some(token), version))
     }
 }
 
@@ -741,7 +755,7 @@ impl<'a> ValueParser<'a> {
             // Clean up leftover state at end
             match state {
                 State::InKey(_) => {
-                    self.value.push(LocaValue::Markup);
+                    self.value.push(LocaValue::Markup(Token::new(&text, loc)));
                 }
                 State::InValue(key, value, loc, bracecount) => {
                     if key.eq_ignore_ascii_case("tooltip") {
@@ -752,7 +766,7 @@ impl<'a> ValueParser<'a> {
                         warn(ErrorKey::Markup).msg(msg).loc(self.loc).push();
                         self.value.push(LocaValue::Error);
                     } else {
-                        self.value.push(LocaValue::Markup);
+                        self.value.push(LocaValue::Markup(Token::new(&text, loc)));
                     }
                 }
             }
@@ -908,3 +922,30 @@ pub fn parse_loca(entry: &FileEntry, content: String, lang: Language) -> LocaRea
     let parser = LocaParser::new(loc, content, lang);
     LocaReader { parser }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound::{Excluded, Included};
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::fileset::FileKind;
+    use crate::report::take_ignores_for;
+
+    #[test]
+    fn test_ignore_range() {
+        let input = "l_english:
+ # tiger-ignore(begin)
+ loca_a: \"a\"
+ loca_b: \"b\"
+ # tiger-ignore(end)
+ loca_c: \"c\"
+";
+        let path = PathBuf::from("test_ignore_range_l_english.yml");
+        let entry = FileEntry::new(path.clone(), FileKind::Internal, path);
+        assert_eq!(parse_loca(&entry, input.to_owned(), Language::English).count(), 3);
+        // The range covers the lines between the begin and end directives.
+        let ignores = take_ignores_for("test_ignore_range_l_english.yml");
+        assert_eq!(ignores, [(Included(3), Excluded(5))]);
+    }
+}
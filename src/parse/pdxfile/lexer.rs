@@ -5,7 +5,9 @@ use std::str::CharIndices;
 use crate::block::Comparator;
 use crate::block::Eq::Single;
 use crate::game::Game;
-use crate::parse::ignore::{parse_comment, IgnoreFilter, IgnoreSize};
+use crate::parse::ignore::{
+    begin_without_end, end_without_begin, parse_comment, IgnoreFilter, IgnoreSize,
+};
 use crate::parse::pdxfile::{CharExt, Cob};
 use crate::report::{err, register_ignore_filter, untidy, warn, ErrorKey};
 use crate::token::{Loc, Token};
@@ -152,8 +154,8 @@ pub struct Lexer<'input> {
     /// Track the brace depth and starting line of each open block-ignore.
     /// Kept sorted by ascending brace depth.
     active_block_ignores: Vec<(usize, u32, IgnoreFilter)>,
-    /// Track the nested begin/end ignore ranges.
-    active_range_ignores: Vec<(u32, IgnoreFilter)>,
+    /// Track the nested begin/end ignore ranges, with the location of each begin directive.
+    active_range_ignores: Vec<(Loc, IgnoreFilter)>,
 }
 
 impl<'input> Lexer<'input> {
@@ -504,6 +506,7 @@ impl Iterator for Lexer<'_> {
                 }
                 '#' => {
                     // A comment
+                    let comment_loc = self.loc;
                     self.consume();
                     let mut comment = self.start_cob();
                     while let Some((_, c)) = self.peek() {
@@ -519,7 +522,7 @@ impl Iterator for Lexer<'_> {
                     } else {
                         comment.take_to_token().as_str()
                     };
-                    if let Some(spec) = parse_comment(s) {
+                    if let Some(spec) = parse_comment(s).map(|spec| spec.at(comment_loc)) {
                         match spec.size {
                             IgnoreSize::Line => self.pending_line_ignores.push(spec.filter),
                             IgnoreSize::Block => self.pending_block_ignores.push(spec.filter),
@@ -528,13 +531,15 @@ impl Iterator for Lexer<'_> {
                                 register_ignore_filter(path, .., spec.filter);
                             }
                             IgnoreSize::Begin => {
-                                self.active_range_ignores.push((self.loc.line + 1, spec.filter));
+                                self.active_range_ignores.push((comment_loc, spec.filter));
                             }
                             IgnoreSize::End => {
-                                if let Some((start_line, filter)) = self.active_range_ignores.pop()
-                                {
+                                if let Some((begin_loc, filter)) = self.active_range_ignores.pop() {
                                     let path = self.loc.pathname().to_path_buf();
-                                    register_ignore_filter(path, start_line..self.loc.line, filter);
+                                    let range = begin_loc.line + 1..comment_loc.line;
+                                    register_ignore_filter(path, range, filter);
+                                } else {
+                                    end_without_begin(comment_loc);
                                 }
                             }
                         }
@@ -669,6 +674,9 @@ impl Iterator for Lexer<'_> {
                 }
             }
         }
+        for (begin_loc, _) in self.active_range_ignores.drain(..) {
+            begin_without_end(begin_loc);
+        }
         None
     }
 }
@@ -681,3 +689,25 @@ fn parse_comparator(token: &Token) -> Comparator {
         Comparator::Equals(Single) // fallback
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound::{Excluded, Included};
+
+    use crate::parse::pdxfile::parse_pdx_internal;
+    use crate::report::take_ignores_for;
+
+    #[test]
+    fn test_ignore_range() {
+        let input = "a = 1
+# tiger-ignore(begin)
+b = 2
+c = 3
+# tiger-ignore(end)
+d = 4
+";
+        parse_pdx_internal(input, "test_ignore_range.txt");
+        // The range covers the lines between the begin and end directives.
+        assert_eq!(take_ignores_for("test_ignore_range.txt"), [(Included(3), Excluded(5))]);
+    }
+}
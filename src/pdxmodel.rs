@@ -0,0 +1,216 @@
+//! Reader for the binary `.mesh` and `.anim` files, to find out which shapes and bones they
+//! define.
+//!
+//! Both use the same format: after the `@@b@` header comes a tree of objects and properties.
+//! An object is a run of `[` characters giving its depth in the tree, followed by its
+//! zero-terminated name. A property is a `!`, the length of its name in one byte, the name, and
+//! then its data: a type character (`i`, `f`, or `s`), a 32-bit count, and the values. A string
+//! value is a 32-bit length followed by that many bytes, including a terminating zero.
+
+use std::fs::read;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+use crate::helpers::{TigerHashMap, TigerHashSet};
+
+/// An object in the tree of a binary file. Properties are skipped, because only the names of
+/// the objects are checked.
+#[derive(Debug, Default)]
+struct Node {
+    name: String,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn child(&self, name: &str) -> Option<&Node> {
+        self.children.iter().find(|child| child.name == name)
+    }
+}
+
+fn parse_tree(bytes: &[u8]) -> Result<Node> {
+    let Some(mut rest) = bytes.strip_prefix(b"@@b@") else {
+        bail!("not a binary model file: it does not start with @@b@");
+    };
+    // The path from the root to the object that was read last.
+    let mut stack = vec![Node::default()];
+    while let Some(&c) = rest.first() {
+        match c {
+            b'[' => {
+                let depth = rest.iter().take_while(|&&c| c == b'[').count();
+                rest = &rest[depth..];
+                let len =
+                    rest.iter().position(|&c| c == 0).context("object name is not terminated")?;
+                let name = String::from_utf8_lossy(&rest[..len]).into_owned();
+                rest = &rest[len + 1..];
+                if depth > stack.len() {
+                    bail!("object `{name}` is nested too deep");
+                }
+                while stack.len() > depth {
+                    let node = stack.pop().unwrap();
+                    stack.last_mut().unwrap().children.push(node);
+                }
+                stack.push(Node { name, ..Node::default() });
+            }
+            b'!' => {
+                let len = usize::from(*rest.get(1).context("file is truncated")?);
+                let name = rest.get(2..2 + len).context("file is truncated")?;
+                let name = String::from_utf8_lossy(name);
+                rest = &rest[2 + len..];
+                let (&datatype, data) = rest.split_first().context("file is truncated")?;
+                let count = le32(data, 0)? as usize;
+                rest = &data[4..];
+                match datatype {
+                    b'i' | b'f' => {
+                        rest = rest.get(count * 4..).context("file is truncated")?;
+                    }
+                    b's' => {
+                        for _ in 0..count {
+                            let len = le32(rest, 0)? as usize;
+                            rest = rest.get(4 + len..).context("file is truncated")?;
+                        }
+                    }
+                    _ => bail!("property `{name}` has unknown data type {datatype:#x}"),
+                }
+            }
+            _ => bail!("unexpected byte {c:#x} at offset {}", bytes.len() - rest.len()),
+        }
+    }
+    while stack.len() > 1 {
+        let node = stack.pop().unwrap();
+        stack.last_mut().unwrap().children.push(node);
+    }
+    Ok(stack.pop().unwrap())
+}
+
+fn le32(bytes: &[u8], offset: usize) -> Result<u32> {
+    match bytes.get(offset..offset + 4) {
+        Some(&[a, b, c, d]) => Ok(u32::from_le_bytes([a, b, c, d])),
+        _ => bail!("file is truncated"),
+    }
+}
+
+/// The contents of a `.mesh` file.
+#[derive(Debug, Default)]
+pub struct Mesh {
+    /// The shapes, with the number of meshes in each.
+    shapes: TigerHashMap<String, usize>,
+    /// The bones of the skeletons of all the shapes.
+    bones: TigerHashSet<String>,
+}
+
+impl Mesh {
+    pub fn read(path: &Path) -> Result<Self> {
+        Self::parse(&read(path)?)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let root = parse_tree(bytes)?;
+        let mut mesh = Self::default();
+        if let Some(object) = root.child("object") {
+            for shape in &object.children {
+                let meshes = shape.children.iter().filter(|node| node.name == "mesh").count();
+                mesh.shapes.insert(shape.name.clone(), meshes);
+                if let Some(skeleton) = shape.child("skeleton") {
+                    mesh.bones.extend(skeleton.children.iter().map(|bone| bone.name.clone()));
+                }
+            }
+        } else {
+            bail!("mesh file has no `object` section");
+        }
+        Ok(mesh)
+    }
+
+    /// Return the number of meshes in the shape, or `None` if there is no such shape.
+    pub fn shape_meshes(&self, name: &str) -> Option<usize> {
+        self.shapes.get(name).copied()
+    }
+
+    pub fn iter_shapes(&self) -> impl Iterator<Item = &String> {
+        self.shapes.keys()
+    }
+
+    pub fn has_skeleton(&self) -> bool {
+        !self.bones.is_empty()
+    }
+
+    pub fn has_bone(&self, name: &str) -> bool {
+        self.bones.contains(name)
+    }
+}
+
+/// The contents of an `.anim` file.
+#[derive(Debug, Default)]
+pub struct Anim {
+    /// The bones that the animation moves.
+    bones: Vec<String>,
+}
+
+impl Anim {
+    pub fn read(path: &Path) -> Result<Self> {
+        Self::parse(&read(path)?)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let root = parse_tree(bytes)?;
+        let Some(info) = root.child("info") else {
+            bail!("animation file has no `info` section");
+        };
+        Ok(Self { bones: info.children.iter().map(|bone| bone.name.clone()).collect() })
+    }
+
+    pub fn iter_bones(&self) -> impl Iterator<Item = &String> {
+        self.bones.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(depth: usize, name: &str) -> Vec<u8> {
+        let mut bytes = vec![b'['; depth];
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.push(0);
+        bytes
+    }
+
+    fn property(name: &str, datatype: u8, data: &[u8], count: u32) -> Vec<u8> {
+        let mut bytes = vec![b'!', u8::try_from(name.len()).unwrap()];
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.push(datatype);
+        bytes.extend_from_slice(&count.to_le_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn test_mesh() {
+        let mut bytes = b"@@b@".to_vec();
+        bytes.extend(property("pdxasset", b'i', &[1, 0, 0, 0, 0, 0, 0, 0], 2));
+        bytes.extend(object(1, "object"));
+        bytes.extend(object(2, "bodyShape"));
+        bytes.extend(object(3, "mesh"));
+        bytes.extend(property("p", b'f', &[0; 12], 3));
+        bytes.extend(object(4, "material"));
+        bytes.extend(property("shader", b's', b"\x08\0\0\0PdxMesh\0", 1));
+        bytes.extend(object(3, "mesh"));
+        bytes.extend(object(3, "skeleton"));
+        bytes.extend(object(4, "root"));
+        bytes.extend(object(4, "arm"));
+        bytes.extend(property("pa", b'i', &[0; 4], 1));
+        bytes.extend(object(1, "locator"));
+        bytes.extend(object(2, "hand"));
+        bytes.extend(property("pa", b's', b"\x04\0\0\0arm\0", 1));
+
+        let mesh = Mesh::parse(&bytes).unwrap();
+        assert_eq!(mesh.shape_meshes("bodyShape"), Some(2));
+        assert_eq!(mesh.shape_meshes("mesh"), None);
+        assert!(mesh.has_bone("arm"));
+        assert!(!mesh.has_bone("hand"));
+
+        bytes.truncate(bytes.len() - 3);
+        assert!(Mesh::parse(&bytes).is_err());
+        assert!(Anim::parse(b"@@b@[info\0[[root\0").is_ok_and(|anim| anim.bones == ["root"]));
+    }
+}
@@ -0,0 +1,297 @@
+//! Render localization keys to the text they would show in the game, as well as can be done
+//! without running it.
+//!
+//! `$macros$` are expanded recursively. The values of `[code]` can't be known, so they are shown
+//! as placeholders unless the user supplies sample values for them.
+
+use ansiterm::Colour::{Cyan, Green, Red, Yellow};
+use ansiterm::{Colour, Style};
+use anyhow::{bail, Result};
+use strum_macros::{Display, EnumString};
+
+use crate::data::localization::{Language, LocaEntry, LocaValue, Localization, MacroValue};
+use crate::helpers::TigerHashMap;
+use crate::parse::localization::ValueParser;
+use crate::token::Token;
+
+/// How to show `#markup` in the rendered text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum MarkupStyle {
+    /// Leave out the markup.
+    #[default]
+    Strip,
+    /// Keep the markup as it is in the localization.
+    Show,
+    /// Leave out the markup, but color the text it applies to with ANSI escape codes.
+    Color,
+}
+
+/// Settings for [`render_loca`].
+#[derive(Debug, Default)]
+pub struct RenderOptions {
+    /// The language to render. `None` means the reference language.
+    pub lang: Option<Language>,
+    /// Sample values for code, like `ROOT.Char.GetName`, and for macros that are not
+    /// localization keys, like `VALUE`.
+    pub samples: TigerHashMap<String, String>,
+    pub markup: MarkupStyle,
+}
+
+/// The ANSI escape code that turns off all styles.
+const RESET: &str = "\x1b[0m";
+
+/// Macros nested deeper than this are probably a loop.
+const MAX_DEPTH: usize = 50;
+
+/// Return the text of localization `key` with all macros expanded.
+pub fn render_loca(loca: &Localization, key: &str, options: &RenderOptions) -> Result<String> {
+    let lang = options.lang.unwrap_or_else(|| loca.reference_lang());
+    let Some(entry) = loca.get_entry(lang, key) else {
+        bail!("localization key `{key}` does not exist in {lang}");
+    };
+    let mut renderer = Renderer { loca, lang, options, styles: Vec::new(), text: String::new() };
+    renderer.render_entry(entry, 0);
+    if !renderer.styles.is_empty() {
+        // Don't let unclosed markup color the rest of the terminal.
+        renderer.text.push_str(RESET);
+    }
+    Ok(renderer.text)
+}
+
+struct Renderer<'a> {
+    loca: &'a Localization,
+    lang: Language,
+    options: &'a RenderOptions,
+    /// The styles of the `#markup` that is currently open, innermost last.
+    styles: Vec<Style>,
+    text: String,
+}
+
+impl Renderer<'_> {
+    fn render_entry(&mut self, entry: &LocaEntry, depth: usize) {
+        let mut tokens = Vec::new();
+        self.expand_macros(entry, &mut tokens, depth);
+        if tokens.is_empty() {
+            return;
+        }
+        let value = ValueParser::new(tokens.iter().collect()).parse();
+        self.render_value(&value, depth);
+    }
+
+    /// Collect the unparsed text of `entry`, with its macros replaced.
+    fn expand_macros(&self, entry: &LocaEntry, tokens: &mut Vec<Token>, depth: usize) {
+        let LocaValue::Macro(values) = entry.value() else {
+            tokens.extend(entry.orig().cloned());
+            return;
+        };
+        for value in values {
+            match value {
+                MacroValue::Text(token) => tokens.push(token.clone()),
+                MacroValue::Keyword(keyword) => {
+                    if let Some(sample) = self.options.samples.get(keyword.as_str()) {
+                        tokens.push(Token::new(sample, keyword.loc));
+                    } else if let Some(entry) = self.loca.get_entry(self.lang, keyword.as_str()) {
+                        if depth < MAX_DEPTH {
+                            self.expand_macros(entry, tokens, depth + 1);
+                        } else {
+                            tokens.push(Token::new("<macro loop>", keyword.loc));
+                        }
+                    } else {
+                        tokens.push(Token::new(&format!("<${keyword}$>"), keyword.loc));
+                    }
+                }
+            }
+        }
+    }
+
+    fn render_value(&mut self, value: &LocaValue, depth: usize) {
+        match value {
+            LocaValue::Concat(values) => {
+                for value in values {
+                    self.render_value(value, depth);
+                }
+            }
+            LocaValue::Text(token) => self.text.push_str(token.as_str()),
+            LocaValue::Markup(token) => match self.options.markup {
+                MarkupStyle::Strip => (),
+                MarkupStyle::Show => {
+                    self.text.push_str(token.as_str());
+                    self.text.push(' ');
+                }
+                MarkupStyle::Color => {
+                    let style = markup_style(token.as_str());
+                    self.styles.push(style);
+                    self.text.push_str(&style.prefix().to_string());
+                }
+            },
+            LocaValue::MarkupEnd => match self.options.markup {
+                MarkupStyle::Strip => (),
+                MarkupStyle::Show => self.text.push_str("#!"),
+                MarkupStyle::Color => {
+                    self.styles.pop();
+                    self.text.push_str(RESET);
+                    for style in &self.styles {
+                        self.text.push_str(&style.prefix().to_string());
+                    }
+                }
+            },
+            LocaValue::Code(chain, format) => {
+                let code = chain.to_string();
+                if let Some(sample) = self.options.samples.get(&code) {
+                    self.text.push_str(sample);
+                    return;
+                }
+                if format.as_ref().is_some_and(|f| f.as_str().contains(['E', 'e'])) {
+                    // A game concept link shows the name of the concept.
+                    let concept = chain.codes.first().map_or("", |code| code.name.as_str());
+                    let concept_key = format!("game_concept_{concept}");
+                    if let Some(entry) = self.loca.get_entry(self.lang, &concept_key) {
+                        if depth < MAX_DEPTH {
+                            self.render_entry(entry, depth + 1);
+                            return;
+                        }
+                    }
+                    if chain.codes.len() == 1 && chain.codes[0].arguments.is_empty() {
+                        self.text.push_str(concept);
+                        return;
+                    }
+                }
+                self.text.push('<');
+                self.text.push_str(&code);
+                self.text.push('>');
+            }
+            LocaValue::Icon(token) => {
+                self.text.push_str("<icon:");
+                self.text.push_str(token.as_str());
+                self.text.push('>');
+            }
+            LocaValue::CalculatedIcon(_) => self.text.push_str("<icon>"),
+            LocaValue::Flag(token) => {
+                self.text.push_str("<flag:");
+                self.text.push_str(token.as_str());
+                self.text.push('>');
+            }
+            LocaValue::Error => self.text.push_str("<error>"),
+            // Only values that were not expanded are macros, and the tooltips come with their
+            // own markup.
+            LocaValue::Macro(_) | LocaValue::Tooltip(_) | LocaValue::ComplexTooltip(_, _) => (),
+        }
+    }
+}
+
+/// Choose a terminal style for the text in a `#markup`. The game's markup is defined by the
+/// `text_formatting` gui types, so this is only an approximation of the common ones.
+fn markup_style(markup: &str) -> Style {
+    let markup = markup.trim_start_matches('#').to_ascii_lowercase();
+    let mut style = Style::new();
+    for part in markup.split(';') {
+        let (key, value) = part.split_once(':').unwrap_or((part, ""));
+        style = match key {
+            "bold" | "b" => style.bold(),
+            "italic" | "i" => style.italic(),
+            "p" | "positive_value" | "good" => style.fg(Green),
+            "n" | "negative_value" | "bad" => style.fg(Red),
+            "v" | "value" => style.fg(Cyan),
+            "e" | "emphasis" | "h" | "high" | "warning" | "x" => style.fg(Yellow),
+            "color" => parse_color(value).map_or(style, |c| style.fg(c)),
+            _ => style.underline(),
+        };
+    }
+    style
+}
+
+/// Parse a color of the form `{1.0,0.5,0}`.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // clamped to 0..=255
+fn parse_color(value: &str) -> Option<Colour> {
+    let value = value.trim_start_matches('{').trim_end_matches('}');
+    let rgb: Vec<u8> = value
+        .split(',')
+        .map(|c| c.trim().parse::<f64>().ok().map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+        .collect::<Option<_>>()?;
+    match rgb[..] {
+        [r, g, b] | [r, g, b, _] => Some(Colour::RGB(r, g, b)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::fileset::{FileEntry, FileHandler, FileKind};
+    use crate::parse::localization::parse_loca;
+
+    /// Make a `Localization` with the given lines of an english localization file.
+    fn localization(lines: &str) -> Localization {
+        let path = PathBuf::from("localization/english/test_l_english.yml");
+        let entry = FileEntry::new(path.clone(), FileKind::Mod, path);
+        let content = format!("\u{feff}l_english:\n{lines}");
+        let entries = parse_loca(&entry, content, Language::English).collect();
+        let mut loca = Localization::default();
+        loca.handle_file(&entry, (Language::English, entries));
+        loca
+    }
+
+    fn render(
+        loca: &Localization,
+        key: &str,
+        samples: &[(&str, &str)],
+        markup: MarkupStyle,
+    ) -> String {
+        let samples = samples.iter().map(|(k, v)| ((*k).to_string(), (*v).to_string())).collect();
+        let options = RenderOptions { lang: None, samples, markup };
+        render_loca(loca, key, &options).unwrap()
+    }
+
+    #[test]
+    fn test_macros() {
+        let loca = localization(
+            " a: \"A $b$ end\"\n b: \"B $c$\"\n c: \"C\"\n d: \"You get $VALUE$ gold\"\n",
+        );
+        assert_eq!(render(&loca, "a", &[], MarkupStyle::Strip), "A B C end");
+        assert_eq!(render(&loca, "d", &[], MarkupStyle::Strip), "You get <$VALUE$> gold");
+        assert_eq!(render(&loca, "d", &[("VALUE", "5")], MarkupStyle::Strip), "You get 5 gold");
+        // A sample replaces a localization key too.
+        assert_eq!(render(&loca, "a", &[("b", "X")], MarkupStyle::Strip), "A X end");
+        assert!(render_loca(&loca, "missing", &RenderOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_macro_loop() {
+        let loca = localization(" loop: \"x $loop$\"\n");
+        let text = render(&loca, "loop", &[], MarkupStyle::Strip);
+        assert_eq!(text, format!("{}<macro loop>", "x ".repeat(MAX_DEPTH + 1)));
+    }
+
+    #[test]
+    fn test_code() {
+        let loca = localization(
+            " a: \"Hi [ROOT.Char.GetName]!\"\n b: \"Your [faith|E]\"\n game_concept_faith: \"Faith\"\n c: \"Your [piety|E]\"\n",
+        );
+        assert_eq!(render(&loca, "a", &[], MarkupStyle::Strip), "Hi <ROOT.Char.GetName>!");
+        let samples = [("ROOT.Char.GetName", "Bob")];
+        assert_eq!(render(&loca, "a", &samples, MarkupStyle::Strip), "Hi Bob!");
+        assert_eq!(render(&loca, "b", &[], MarkupStyle::Strip), "Your Faith");
+        assert_eq!(render(&loca, "c", &[], MarkupStyle::Strip), "Your piety");
+    }
+
+    #[test]
+    fn test_markup() {
+        let loca = localization(" a: \"#bold Big#! text\"\n b: \"#N Bad\"\n");
+        assert_eq!(render(&loca, "a", &[], MarkupStyle::Strip), "Big text");
+        assert_eq!(render(&loca, "a", &[], MarkupStyle::Show), "#bold Big#! text");
+        let bold = Style::new().bold().prefix().to_string();
+        assert_eq!(render(&loca, "a", &[], MarkupStyle::Color), format!("{bold}Big{RESET} text"));
+        // Unclosed markup is reset at the end.
+        let red = Style::new().fg(Red).prefix().to_string();
+        assert_eq!(render(&loca, "b", &[], MarkupStyle::Color), format!("{red}Bad{RESET}"));
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("{1,0.5,0}"), Some(Colour::RGB(255, 128, 0)));
+        assert_eq!(parse_color("{1,0.5}"), None);
+    }
+}
@@ -0,0 +1,141 @@
+//! Find the lines of the mod that were changed since a git revision, so that only reports about
+//! those lines are shown.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+
+use crate::fileset::FileKind;
+use crate::helpers::TigerHashMap;
+use crate::token::Loc;
+
+/// The added or modified lines of each changed file, by path relative to the mod root.
+#[derive(Debug, Default)]
+pub struct ChangedLines {
+    /// Inclusive ranges of line numbers. A file with no ranges was changed as a whole.
+    files: TigerHashMap<PathBuf, Vec<(u32, u32)>>,
+}
+
+impl ChangedLines {
+    /// Ask the local `git` binary which lines in the mod directory changed since revision `rev`,
+    /// including uncommitted changes and new files that git does not track yet.
+    pub fn from_git(mod_root: &Path, rev: &str) -> Result<Self> {
+        let diff = run_git(
+            mod_root,
+            &["diff", "--unified=0", "--no-color", "--no-ext-diff", "--relative", rev, "--"],
+        )?;
+        let mut changed = Self::parse_diff(&diff);
+        let untracked = run_git(mod_root, &["ls-files", "--others", "--exclude-standard"])?;
+        for path in untracked.lines() {
+            changed.files.insert(PathBuf::from(path), Vec::new());
+        }
+        Ok(changed)
+    }
+
+    /// Parse the output of `git diff --unified=0`.
+    fn parse_diff(diff: &str) -> Self {
+        let mut files: TigerHashMap<PathBuf, Vec<(u32, u32)>> = TigerHashMap::default();
+        let mut current = None;
+        for line in diff.lines() {
+            if let Some(path) = line.strip_prefix("+++ ") {
+                // Deleted files have `/dev/null` as their new name.
+                current = path.strip_prefix("b/").map(PathBuf::from);
+                if let Some(path) = &current {
+                    files.entry(path.clone()).or_default();
+                }
+            } else if let Some(hunk) = line.strip_prefix("@@ ") {
+                let Some(path) = &current else {
+                    continue;
+                };
+                // The hunk header looks like `@@ -12,3 +12,4 @@`, where the count defaults to 1.
+                let Some(new) = hunk.split(' ').find_map(|part| part.strip_prefix('+')) else {
+                    continue;
+                };
+                let (start, count) = new.split_once(',').unwrap_or((new, "1"));
+                if let (Ok(start), Ok(count)) = (start.parse::<u32>(), count.parse::<u32>()) {
+                    // A count of 0 means lines were only removed here.
+                    if count > 0 {
+                        files.entry(path.clone()).or_default().push((start, start + count - 1));
+                    }
+                }
+            }
+        }
+        // Files that only had lines removed have nothing left to report on.
+        files.retain(|_, ranges| !ranges.is_empty());
+        Self { files }
+    }
+
+    /// Return true iff the location is in the mod on a changed line. Locations that refer to a
+    /// whole file count as changed if anything in the file changed.
+    pub fn contains(&self, loc: Loc) -> bool {
+        if loc.kind != FileKind::Mod {
+            return false;
+        }
+        match self.files.get(loc.pathname()) {
+            Some(ranges) => {
+                loc.line == 0
+                    || ranges.is_empty()
+                    || ranges.iter().any(|(start, end)| (*start..=*end).contains(&loc.line))
+            }
+            None => false,
+        }
+    }
+
+    /// Return true iff anything in the mod file at this path changed.
+    pub fn contains_file(&self, loc: Loc) -> bool {
+        loc.kind == FileKind::Mod && self.files.contains_key(loc.pathname())
+    }
+}
+
+fn run_git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "core.quotePath=false"])
+        .args(args)
+        .output()
+        .context("could not run git")?;
+    if !output.status.success() {
+        bail!("git {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/events/a.txt b/events/a.txt
+index 1234567..89abcde 100644
+--- a/events/a.txt
++++ b/events/a.txt
+@@ -3 +3 @@ namespace = a
+-old
++new
+@@ -10,0 +11,2 @@ a.1 = {
++added
++added
+diff --git a/events/b.txt b/events/b.txt
+--- a/events/b.txt
++++ b/events/b.txt
+@@ -5,2 +4,0 @@
+-removed
+-removed
+diff --git a/events/c.txt b/events/c.txt
+deleted file mode 100644
+--- a/events/c.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-gone
+";
+
+    #[test]
+    fn test_parse_diff() {
+        let changed = ChangedLines::parse_diff(DIFF);
+        assert_eq!(changed.files.get(Path::new("events/a.txt")), Some(&vec![(3, 3), (11, 12)]));
+        assert!(!changed.files.contains_key(Path::new("events/b.txt")));
+        assert!(!changed.files.contains_key(Path::new("events/c.txt")));
+    }
+}
@@ -71,6 +71,15 @@ pub enum ErrorKey {
     Overflow,
     Deprecated,
     Variables,
+    Upgrade,
+    FileOverride,
+    IgnoreDirective,
+    LocalizationStructure,
+    StaleTranslation,
+    LocaFormat,
+    MissingGlyph,
+    Spelling,
+    UnusedSound,
 
     PrincesOfDarkness,
 
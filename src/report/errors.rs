@@ -19,8 +19,14 @@ use crate::report::error_loc::ErrorLoc;
 use crate::report::filter::ReportFilter;
 use crate::report::suppress::{Suppression, SuppressionKey};
 use crate::report::writer::log_report;
+use crate::report::writer_checkstyle::log_reports_checkstyle;
+use crate::report::writer_gitlab::log_reports_gitlab;
 use crate::report::writer_json::log_report_json;
-use crate::report::{ErrorKey, FilterRule, LogReport, OutputStyle, PointedMessage};
+use crate::report::writer_junit::log_reports_junit;
+use crate::report::{
+    untidy, ChangedLines, ErrorKey, FilterRule, LogReport, OutputFormat, OutputStyle,
+    PointedMessage,
+};
 use crate::token::{leak, Loc};
 
 static ERRORS: LazyLock<Mutex<Errors>> = LazyLock::new(|| Mutex::new(Errors::default()));
@@ -92,6 +98,10 @@ impl Errors {
     }
 
     fn should_ignore(&self, report: &LogReport) -> bool {
+        // Otherwise a directive could hide the report about itself.
+        if report.key == ErrorKey::IgnoreDirective {
+            return false;
+        }
         for p in &report.pointers {
             if let Some(vec) = self.ignore.get(p.loc.pathname()) {
                 for entry in vec {
@@ -106,9 +116,51 @@ impl Errors {
         false
     }
 
+    /// Mark the `#tiger-ignore` directives that match a report with this key and message at any
+    /// of these locations as used. This is done before any filtering, so that a directive counts
+    /// as used even if the report it matched would not have been printed anyway.
+    fn mark_ignores_used<'a>(
+        &mut self,
+        key: ErrorKey,
+        msg: &str,
+        locs: impl IntoIterator<Item = &'a Loc>,
+    ) {
+        if key == ErrorKey::IgnoreDirective {
+            return;
+        }
+        for loc in locs {
+            if let Some(vec) = self.ignore.get_mut(loc.pathname()) {
+                for entry in vec {
+                    if (entry.start, entry.end).contains(&loc.line)
+                        && entry.filter.matches(key, msg)
+                    {
+                        entry.used = true;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Return the locations and filter descriptions of the `#tiger-ignore` directives that didn't
+    /// match any report.
+    fn unused_ignores(&self) -> Vec<(Loc, Option<String>)> {
+        let mut unused = Vec::new();
+        for vec in self.ignore.values() {
+            for entry in vec {
+                if let Some(loc) = entry.filter.loc() {
+                    if !entry.used {
+                        unused.push((loc, entry.filter.describe()));
+                    }
+                }
+            }
+        }
+        unused
+    }
+
     /// Perform some checks to see whether the report should actually be logged.
     /// If yes, it will add it to the storage.
     fn push_report(&mut self, report: LogReport) {
+        self.mark_ignores_used(report.key, &report.msg, report.pointers.iter().map(|p| &p.loc));
         if !self.filter.should_print_report(&report) || self.should_suppress(&report) {
             return;
         }
@@ -122,6 +174,7 @@ impl Errors {
     // TODO: integrate this function into the error reporting framework.
     pub fn push_abbreviated<E: ErrorLoc>(&mut self, eloc: E, key: ErrorKey) {
         let loc = eloc.into_loc();
+        self.mark_ignores_used(key, "", [&loc]);
         if self.filter.should_maybe_print(key, loc) {
             if loc.line == 0 {
                 _ = writeln!(self.output.get_mut(), "({key}) {}", loc.pathname().to_string_lossy());
@@ -174,37 +227,36 @@ impl Errors {
         reports
     }
 
-    /// Print the stored reports.
-    /// Set `json` if they should be printed as a JSON array. Otherwise they are printed in the
-    /// default output format.
+    /// Print the stored reports in the given format.
     ///
     /// Note that the default output format is not stable across versions. It is meant for human
     /// readability and occasionally gets changed to improve that.
     ///
     /// Reports matched by `#tiger-ignore` directives will not be printed.
-    pub fn emit_reports(&mut self, json: bool) {
-        let reports = self.take_reports();
-        if json {
-            _ = writeln!(self.output.get_mut(), "[");
-            let mut first = true;
-            for report in &reports {
-                if self.should_ignore(report) {
-                    continue;
+    pub fn emit_reports(&mut self, format: OutputFormat) {
+        let mut reports = self.take_reports();
+        reports.retain(|report| !self.should_ignore(report));
+        match format {
+            OutputFormat::Text => {
+                for report in &reports {
+                    log_report(self, report);
                 }
-                if !first {
-                    _ = writeln!(self.output.get_mut(), ",");
-                }
-                first = false;
-                log_report_json(self, report);
             }
-            _ = writeln!(self.output.get_mut(), "\n]");
-        } else {
-            for report in &reports {
-                if self.should_ignore(report) {
-                    continue;
+            OutputFormat::Json => {
+                _ = writeln!(self.output.get_mut(), "[");
+                let mut first = true;
+                for report in &reports {
+                    if !first {
+                        _ = writeln!(self.output.get_mut(), ",");
+                    }
+                    first = false;
+                    log_report_json(self, report);
                 }
-                log_report(self, report);
+                _ = writeln!(self.output.get_mut(), "\n]");
             }
+            OutputFormat::Junit => log_reports_junit(self, &reports),
+            OutputFormat::Checkstyle => log_reports_checkstyle(self, &reports),
+            OutputFormat::Gitlab => log_reports_gitlab(self, &reports),
         }
     }
 
@@ -280,6 +332,8 @@ struct IgnoreEntry {
     start: Bound<u32>,
     end: Bound<u32>,
     filter: IgnoreFilter,
+    /// Whether this entry matched any report, including ones that were filtered out.
+    used: bool,
 }
 
 /// Record a secondary mod to be loaded before the one being validated.
@@ -337,14 +391,12 @@ pub fn will_maybe_log<E: ErrorLoc>(eloc: E, key: ErrorKey) -> bool {
     Errors::get().filter.should_maybe_print(key, eloc.into_loc())
 }
 
-/// Print all the stored reports to the error output.
-/// Set `json` if they should be printed as a JSON array. Otherwise they are printed in the
-/// default output format.
+/// Print all the stored reports to the error output, in the given format.
 ///
 /// Note that the default output format is not stable across versions. It is meant for human
 /// readability and occasionally gets changed to improve that.
-pub fn emit_reports(json: bool) {
-    Errors::get_mut().emit_reports(json);
+pub fn emit_reports(format: OutputFormat) {
+    Errors::get_mut().emit_reports(format);
 }
 
 /// Extract the stored reports, sort them, and return them as a vector of [`LogReport`].
@@ -353,6 +405,25 @@ pub fn take_reports() -> Vec<LogReport> {
     Errors::get_mut().take_reports()
 }
 
+/// Extract the stored reports whose main location is in the given file, leaving the others.
+/// Tests run in parallel, so this keeps them from taking each other's reports.
+#[cfg(test)]
+pub(crate) fn take_reports_for(pathname: &str) -> Vec<LogReport> {
+    let mut errors = Errors::get_mut();
+    let (reports, others): (Vec<LogReport>, Vec<LogReport>) = take(&mut errors.storage)
+        .into_iter()
+        .partition(|report| report.primary().loc.pathname() == Path::new(pathname));
+    errors.storage.extend(others);
+    reports
+}
+
+/// Take the line ranges of the `#tiger-ignore` directives registered for `pathname`.
+#[cfg(test)]
+pub(crate) fn take_ignores_for(pathname: &str) -> Vec<(Bound<u32>, Bound<u32>)> {
+    let entries = Errors::get_mut().ignore.remove(Path::new(pathname)).unwrap_or_default();
+    entries.into_iter().map(|entry| (entry.start, entry.end)).collect()
+}
+
 pub fn store_source_file(fullpath: PathBuf, source: &'static str) {
     Errors::get_mut().store_source_file(fullpath, source);
 }
@@ -363,8 +434,28 @@ where
 {
     let start = lines.start_bound().cloned();
     let end = lines.end_bound().cloned();
-    let entry = IgnoreEntry { start, end, filter };
-    Errors::get_mut().ignore.entry(pathname).or_default().push(entry);
+    let mut errors = Errors::get_mut();
+    let vec = errors.ignore.entry(pathname).or_default();
+    // Files with macros are parsed again for each macro expansion.
+    if !vec.iter().any(|e| e.start == start && e.end == end && e.filter == filter) {
+        vec.push(IgnoreEntry { start, end, filter, used: false });
+    }
+}
+
+/// Report the `#tiger-ignore` directives that did not match any of the reports made so far.
+/// This should be called after validation is done and before [`emit_reports`].
+pub fn check_ignore_directives() {
+    let mut unused = Errors::get().unused_ignores();
+    unused.sort_unstable_by_key(|(loc, _)| *loc);
+    for (loc, filter) in unused {
+        let msg = "this `#tiger-ignore` directive did not suppress any reports";
+        let info = if let Some(filter) = filter {
+            format!("no report matching `{filter}` was found in its range; it can be removed")
+        } else {
+            "it can be removed".to_string()
+        };
+        untidy(ErrorKey::IgnoreDirective).msg(msg).info(info).loc(loc).push();
+    }
 }
 
 // =================================================================================================
@@ -415,6 +506,11 @@ pub fn set_show_loaded_mods(v: bool) {
     Errors::get_mut().filter.show_loaded_mods = v;
 }
 
+/// Configure the error reporter to only show errors whose main location is on one of these lines.
+pub(crate) fn set_changed_lines(changed_lines: ChangedLines) {
+    Errors::get_mut().filter.changed_lines = Some(changed_lines);
+}
+
 /// Configure the error reporter to only show errors that match this [`FilterRule`].
 pub(crate) fn set_predicate(predicate: FilterRule) {
     Errors::get_mut().filter.predicate = predicate;
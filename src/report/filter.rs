@@ -3,6 +3,7 @@ use std::path::PathBuf;
 use crate::block::Comparator;
 
 use crate::fileset::FileKind;
+use crate::report::changed_lines::ChangedLines;
 use crate::report::{Confidence, ErrorKey, LogReport, Severity};
 use crate::token::Loc;
 
@@ -16,6 +17,8 @@ pub struct ReportFilter {
     pub show_loaded_mods: bool,
     /// A complex trigger that evaluates a report to assess whether it should be printed.
     pub predicate: FilterRule,
+    /// If set, only reports about the mod's changed lines are printed.
+    pub changed_lines: Option<ChangedLines>,
 }
 
 impl ReportFilter {
@@ -24,6 +27,7 @@ impl ReportFilter {
     /// - Its Severity or Confidence level is too low.
     /// - It's from vanilla or a loaded mod and the program is configured to ignore those locations.
     /// - The filter has a trigger, and the report doesn't match it.
+    /// - Only changed lines are wanted, and the report's main location is not on one.
     pub fn should_print_report(&self, report: &LogReport) -> bool {
         if report.key == ErrorKey::Config {
             // Any errors concerning the Config should be easy to fix and will fundamentally
@@ -38,6 +42,11 @@ impl ReportFilter {
         if out_of_scope {
             return false;
         }
+        if let Some(changed_lines) = &self.changed_lines {
+            if !report.pointers.first().is_some_and(|p| changed_lines.contains(p.loc)) {
+                return false;
+            }
+        }
         self.predicate.apply(report)
     }
 
@@ -53,6 +62,9 @@ impl ReportFilter {
         {
             return false;
         }
+        if let Some(changed_lines) = &self.changed_lines {
+            return changed_lines.contains_file(loc);
+        }
         true
     }
 }
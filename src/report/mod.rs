@@ -1,15 +1,18 @@
 //! Error report collection and printing facilities.
 
 pub(crate) use builder::{err, fatal, report, tips, untidy, warn, ReportBuilderStage3};
+pub(crate) use changed_lines::ChangedLines;
 pub(crate) use error_key::ErrorKey;
 pub(crate) use error_loc::ErrorLoc;
 pub use errors::*;
 pub(crate) use filter::FilterRule;
 pub(crate) use output_style::OutputStyle;
-pub use report_struct::{Confidence, LogReport, PointedMessage, Severity};
+pub use report_struct::{Confidence, LogReport, OutputFormat, PointedMessage, Severity};
 pub use suppress::suppress_from_json;
+pub(crate) use writer::xml_escape;
 
 mod builder;
+mod changed_lines;
 mod error_key;
 mod error_loc;
 mod errors;
@@ -18,4 +21,7 @@ mod output_style;
 mod report_struct;
 mod suppress;
 mod writer;
+mod writer_checkstyle;
+mod writer_gitlab;
 mod writer_json;
+mod writer_junit;
@@ -142,3 +142,20 @@ pub enum Confidence {
     /// Very confident that this problem is real.
     Strong,
 }
+
+/// The ways the reports can be printed.
+#[derive(Default, Debug, Display, Clone, Copy, PartialEq, Eq, EnumString, EnumIter)]
+#[strum(serialize_all = "kebab-case")]
+pub enum OutputFormat {
+    /// The default output, meant for people to read.
+    #[default]
+    Text,
+    /// A JSON array of reports, with all the details.
+    Json,
+    /// `JUnit` XML, with one test case per file and one failure per report.
+    Junit,
+    /// Checkstyle XML.
+    Checkstyle,
+    /// The `GitLab` Code Quality JSON format.
+    Gitlab,
+}
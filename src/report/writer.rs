@@ -175,6 +175,31 @@ fn log_line_carets(
     _ = writeln!(errors.output.borrow_mut(), "{}", ANSIStrings(line_carets));
 }
 
+/// Escape a string for use in XML text or attribute values.
+pub(crate) fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The report's message together with its info line, if any.
+pub(crate) fn full_message(report: &LogReport) -> String {
+    match &report.info {
+        Some(info) => format!("{}\n{info}", report.msg),
+        None => report.msg.clone(),
+    }
+}
+
 pub(crate) fn kind_tag(errors: &Errors, kind: FileKind) -> &str {
     match kind {
         FileKind::Internal => "Internal",
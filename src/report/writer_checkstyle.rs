@@ -0,0 +1,40 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+use crate::report::errors::Errors;
+use crate::report::writer::{full_message, xml_escape};
+use crate::report::{LogReport, Severity};
+
+/// Log the reports as a Checkstyle XML document.
+pub fn log_reports_checkstyle(errors: &mut Errors, reports: &[LogReport]) {
+    let mut files: BTreeMap<&Path, Vec<&LogReport>> = BTreeMap::new();
+    for report in reports {
+        files.entry(report.primary().loc.pathname()).or_default().push(report);
+    }
+
+    let out = errors.output.get_mut();
+    _ = writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    _ = writeln!(out, r#"<checkstyle version="4.3">"#);
+    for (path, reports) in files {
+        _ = writeln!(out, r#"  <file name="{}">"#, xml_escape(&path.to_string_lossy()));
+        for report in reports {
+            let loc = report.primary().loc;
+            let severity = match report.severity {
+                Severity::Fatal | Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Untidy | Severity::Tips => "info",
+            };
+            _ = writeln!(
+                out,
+                r#"    <error line="{}" column="{}" severity="{severity}" message="{}" source="tiger.{}"/>"#,
+                loc.line,
+                loc.column,
+                xml_escape(&full_message(report)),
+                report.key,
+            );
+        }
+        _ = writeln!(out, "  </file>");
+    }
+    _ = writeln!(out, "</checkstyle>");
+}
@@ -0,0 +1,68 @@
+use std::io::{Cursor, Write};
+use std::path::Path;
+
+use murmur3::murmur3_x64_128;
+use serde_json::json;
+
+use crate::report::errors::Errors;
+use crate::report::writer::full_message;
+use crate::report::{ErrorKey, LogReport, Severity};
+
+/// Log the reports in the GitLab Code Quality format, which is a JSON array of issues.
+pub fn log_reports_gitlab(errors: &mut Errors, reports: &[LogReport]) {
+    let issues: Vec<_> = reports
+        .iter()
+        .map(|report| {
+            let loc = report.primary().loc;
+            let severity = match report.severity {
+                Severity::Fatal => "blocker",
+                Severity::Error => "critical",
+                Severity::Warning => "major",
+                Severity::Untidy => "minor",
+                Severity::Tips => "info",
+            };
+            let line = errors.cache.get_line(loc);
+            let fingerprint = fingerprint(report.key, &report.msg, loc.pathname(), line);
+            json!({
+                "description": full_message(report),
+                "check_name": report.key,
+                "fingerprint": fingerprint,
+                "severity": severity,
+                "location": {
+                    "path": loc.pathname(),
+                    "lines": { "begin": loc.line.max(1) },
+                },
+            })
+        })
+        .collect();
+
+    if let Err(e) = serde_json::to_writer_pretty(errors.output.get_mut(), &issues) {
+        eprintln!("JSON error: {e:#}");
+    }
+    _ = writeln!(errors.output.get_mut());
+}
+
+/// Return an id for the issue that stays the same between runs and versions of the program.
+///
+/// It should also stay the same when unrelated lines are added to the file, so it uses the text
+/// of the line instead of the line number.
+fn fingerprint(key: ErrorKey, msg: &str, path: &Path, line: Option<&str>) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let text = format!("{key}\0{msg}\0{path}\0{}", line.unwrap_or_default());
+    format!("{:032x}", murmur3_x64_128(&mut Cursor::new(text), 0).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint() {
+        let path = Path::new("events/test.txt");
+        let line = Some("    trigger = { is_adult = yes }");
+        let print = fingerprint(ErrorKey::Logic, "test", path, line);
+        assert_eq!(print, "ebbdd6ed6d35fd2e8bec3475c5f51d7a");
+        assert_ne!(print, fingerprint(ErrorKey::Logic, "test", path, None));
+        assert_ne!(print, fingerprint(ErrorKey::Validation, "test", path, line));
+    }
+}
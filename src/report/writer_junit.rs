@@ -0,0 +1,51 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::Path;
+
+use crate::report::errors::Errors;
+use crate::report::writer::xml_escape;
+use crate::report::LogReport;
+
+/// Log the reports as a `JUnit` XML document, with one test case per file and one failure per
+/// report in that file.
+pub fn log_reports_junit(errors: &mut Errors, reports: &[LogReport]) {
+    let mut files: BTreeMap<&Path, Vec<&LogReport>> = BTreeMap::new();
+    for report in reports {
+        files.entry(report.primary().loc.pathname()).or_default().push(report);
+    }
+
+    let out = errors.output.get_mut();
+    _ = writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    _ = writeln!(out, r#"<testsuites name="tiger" tests="{0}" failures="{0}">"#, files.len());
+    _ = writeln!(out, r#"  <testsuite name="tiger" tests="{0}" failures="{0}">"#, files.len());
+    for (path, reports) in files {
+        let path = xml_escape(&path.to_string_lossy());
+        _ = writeln!(out, r#"    <testcase name="{path}" classname="{path}">"#);
+        for report in reports {
+            let message = xml_escape(&report.msg);
+            let kind = format!("{}({})", report.severity, report.key);
+            let mut text = String::new();
+            for pointer in &report.pointers {
+                let loc = pointer.loc;
+                _ = write!(text, "{}:{}:{}", loc.pathname().display(), loc.line, loc.column);
+                if let Some(msg) = &pointer.msg {
+                    _ = write!(text, " {msg}");
+                }
+                text.push('\n');
+            }
+            if let Some(info) = &report.info {
+                text.push_str(info);
+                text.push('\n');
+            }
+            let text = xml_escape(&text);
+            _ = writeln!(
+                out,
+                r#"      <failure message="{message}" type="{kind}">{text}</failure>"#
+            );
+        }
+        _ = writeln!(out, "    </testcase>");
+    }
+    _ = writeln!(out, "  </testsuite>");
+    _ = writeln!(out, "</testsuites>");
+}
@@ -10,7 +10,7 @@ use crate::everything::Everything;
 use crate::helpers::TriBool;
 use crate::item::Item;
 use crate::lowercase::Lowercase;
-use crate::report::{err, tips, untidy, warn, ErrorKey, Severity};
+use crate::report::{err, report, tips, untidy, warn, ErrorKey, Severity};
 use crate::scopes::{scope_iterator, Scopes};
 use crate::token::Token;
 use crate::tooltipped::Tooltipped;
@@ -20,6 +20,7 @@ use crate::validate::{
     validate_iterator_fields, validate_scope_chain, ListType,
 };
 use crate::validator::Validator;
+use crate::value_range::ValueRange;
 
 /// Validate a block that's part of a script value.
 /// * `have_value`: indicates whether this script value has had some sort of value set already.
@@ -90,6 +91,9 @@ fn validate_inner(
                 warn(ErrorKey::Logic).msg(msg).loc(token).push();
             }
             validate_bv(bv, data, sc, check_desc);
+            if token.is("divide") || token.is("modulo") {
+                validate_divisor(token, bv, data);
+            }
             made_changes = true;
             saved_value = false;
         } else if token.is("round") || token.is("ceiling") || token.is("floor") || token.is("abs") {
@@ -314,6 +318,19 @@ fn validate_else(
     }
 }
 
+/// Warn if the divisor of a `divide` or `modulo` operation can be zero.
+fn validate_divisor(key: &Token, bv: &BV, data: &Everything) {
+    let range = ValueRange::from_script_value(bv, data);
+    if range.is_exactly(0.0) {
+        let msg = format!("{key} by zero");
+        err(ErrorKey::Logic).strong().msg(msg).loc(key).push();
+    } else if range.can_be_zero() {
+        let msg = format!("{key} by a value that can be zero");
+        let info = format!("the value can be {range}; the game will log an error when it is zero");
+        warn(ErrorKey::Logic).weak().msg(msg).info(info).loc(key).push();
+    }
+}
+
 /// Validate a script value. It can be a block or a value.
 /// As a value, it may be an integer or boolean literal, or a target scope sequence, or a named script value.
 /// As a block, it may be a { min max } range, or a calculation block which is validated with `validate_inner`.
@@ -352,6 +369,28 @@ pub fn validate_script_value_no_breakdown(bv: &BV, data: &Everything, sc: &mut S
     validate_bv(bv, data, sc, false);
 }
 
+/// Validate a script value, and warn if it can be shown to always be outside of `min..=max`.
+pub fn validate_script_value_range(
+    bv: &BV,
+    data: &Everything,
+    sc: &mut ScopeContext,
+    min: f64,
+    max: f64,
+    sev: Severity,
+) {
+    validate_bv(bv, data, sc, true);
+    let range = ValueRange::from_script_value(bv, data);
+    if range.max < min || range.min > max {
+        let msg = if max == f64::INFINITY {
+            format!("expected number of at least {min}")
+        } else {
+            format!("expected number between {min} and {max}")
+        };
+        let info = format!("this value can only be {range}");
+        report(ErrorKey::Range, sev).msg(msg).info(info).loc(bv).push();
+    }
+}
+
 /// Validate a script value that's not allowed to do calculations. It must be a literal or the name of another script value
 /// that's also not allowed to do calculations.
 pub fn validate_non_dynamic_script_value(bv: &BV, data: &Everything) {
@@ -0,0 +1,198 @@
+//! Sound events defined in FMOD banks, which the game's built-in list of sounds doesn't know about.
+//!
+//! The event names are read from `GUIDs.txt`, which FMOD Studio can export along with the banks,
+//! and from the `.strings.bank` files. The strings banks of recent FMOD versions may store the
+//! names in compressed form, in which case only `GUIDs.txt` will have them.
+
+use std::fs::{read, read_to_string};
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use crate::fileset::{FileEntry, FileHandler};
+use crate::helpers::{TigerHashMap, TigerHashSet};
+use crate::lowercase::Lowercase;
+use crate::parse::ParserMemory;
+use crate::report::{err, warn_abbreviated, warn_header, will_maybe_log, ErrorKey};
+use crate::token::{Loc, Token};
+
+/// The only kind of FMOD path that is used as a sound in script and gui.
+const EVENT_PREFIX: &str = "event:/";
+
+#[derive(Debug, Default)]
+pub struct SoundBanks {
+    /// The event names, such as `event:/SFX/UI/click`, by their lowercase version.
+    events: TigerHashMap<String, Token>,
+    /// The lowercase names of the events that were looked up.
+    used: RwLock<TigerHashSet<String>>,
+}
+
+impl SoundBanks {
+    /// Return true iff the sound event is defined in one of the loaded banks, and remember that
+    /// it was used.
+    pub fn exists(&self, name: &str) -> bool {
+        let name = Lowercase::new(name);
+        let Some((name, _)) = self.events.get_key_value(name.as_str()) else {
+            return false;
+        };
+        // Most lookups are of events that were already seen, so avoid the write lock for those.
+        if !self.used.read().unwrap().contains(name) {
+            self.used.write().unwrap().insert(name.clone());
+        }
+        true
+    }
+
+    pub fn iter_keys(&self) -> impl Iterator<Item = &Token> {
+        self.events.values()
+    }
+
+    /// Report the mod's sound events that nothing refers to.
+    pub fn check_unused(&self) {
+        let used = self.used.read().unwrap();
+        let mut unused: Vec<&Token> = self
+            .events
+            .iter()
+            .filter(|(name, token)| !token.loc.kind.counts_as_vanilla() && !used.contains(*name))
+            .map(|(_, token)| token)
+            .collect();
+        unused.sort_unstable_by_key(|token| token.loc);
+        let mut printed_header = false;
+        for token in unused {
+            if !printed_header && will_maybe_log(token, ErrorKey::UnusedSound) {
+                warn_header(ErrorKey::UnusedSound, "Unused sound events:\n");
+                printed_header = true;
+            }
+            warn_abbreviated(token, ErrorKey::UnusedSound);
+        }
+        if printed_header {
+            warn_header(ErrorKey::UnusedSound, "\n");
+        }
+    }
+}
+
+/// Read the event names from a `GUIDs.txt` file, which has lines like
+/// `{01234567-89ab-cdef-0123-456789abcdef} event:/SFX/UI/click`.
+fn parse_guids(entry: &FileEntry, text: &str) -> Vec<Token> {
+    let mut events = Vec::new();
+    for (line, text) in (1..).zip(text.lines()) {
+        let Some((_, path)) = text.split_once(' ') else {
+            continue;
+        };
+        let path = path.trim();
+        if path.starts_with(EVENT_PREFIX) {
+            let mut loc = Loc::from(entry);
+            loc.line = line;
+            loc.column = u32::try_from(text.len() - path.len() + 1).unwrap_or(0);
+            events.push(Token::new(path, loc));
+        }
+    }
+    events
+}
+
+/// Find the event names that are stored as plain strings in a `.strings.bank` file.
+fn scan_bank(entry: &FileEntry, bytes: &[u8]) -> Vec<Token> {
+    let mut events = Vec::new();
+    let prefix = EVENT_PREFIX.as_bytes();
+    let mut rest = bytes;
+    while let Some(start) = rest.windows(prefix.len()).position(|window| window == prefix) {
+        rest = &rest[start..];
+        let len = rest.iter().position(|&b| b < 0x20).unwrap_or(rest.len());
+        if let Ok(name) = std::str::from_utf8(&rest[..len]) {
+            if name.len() > prefix.len() {
+                events.push(Token::new(name, Loc::from(entry)));
+            }
+        }
+        rest = &rest[len..];
+    }
+    events
+}
+
+impl FileHandler<Vec<Token>> for SoundBanks {
+    fn subpath(&self) -> PathBuf {
+        PathBuf::from("sound")
+    }
+
+    fn load_file(&self, entry: &FileEntry, _parser: &ParserMemory) -> Option<Vec<Token>> {
+        let filename = entry.filename().to_string_lossy();
+        if filename.eq_ignore_ascii_case("GUIDs.txt") {
+            match read_to_string(entry.fullpath()) {
+                Ok(text) => Some(parse_guids(entry, &text)),
+                Err(e) => {
+                    err(ErrorKey::ReadError)
+                        .msg(format!("could not read file: {e}"))
+                        .loc(entry)
+                        .push();
+                    None
+                }
+            }
+        } else if filename.ends_with(".strings.bank") {
+            match read(entry.fullpath()) {
+                Ok(bytes) => Some(scan_bank(entry, &bytes)),
+                Err(e) => {
+                    err(ErrorKey::ReadError)
+                        .msg(format!("could not read file: {e}"))
+                        .loc(entry)
+                        .push();
+                    None
+                }
+            }
+        } else {
+            None
+        }
+    }
+
+    fn handle_file(&mut self, _entry: &FileEntry, events: Vec<Token>) {
+        for token in events {
+            // The same event is usually in both GUIDs.txt and the strings bank. Prefer the
+            // GUIDs.txt one because it points at a line.
+            let name = token.as_str().to_ascii_lowercase();
+            if self.events.get(&name).is_none_or(|other| other.loc.line == 0) {
+                self.events.insert(name, token);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::fileset::FileKind;
+
+    #[test]
+    fn test_parse() {
+        let entry = FileEntry::new(
+            PathBuf::from("sound/GUIDs.txt"),
+            FileKind::Mod,
+            Path::new("/mod/sound/GUIDs.txt").to_path_buf(),
+        );
+        let text = "{0a1b2c3d-0000-0000-0000-000000000000} bus:/\n\
+                    {0a1b2c3d-0000-0000-0000-000000000001} event:/SFX/UI/my_click\n";
+        let events = parse_guids(&entry, text);
+        assert_eq!(events.len(), 1);
+        assert!(events[0].is("event:/SFX/UI/my_click"));
+        assert_eq!((events[0].loc.line, events[0].loc.column), (2, 40));
+
+        let bank = b"RIFF\0\0event:/SFX/a\0\x05event:/SFX/b\0event:/";
+        let events = scan_bank(&entry, bank);
+        let names: Vec<&str> = events.iter().map(Token::as_str).collect();
+        assert_eq!(names, vec!["event:/SFX/a", "event:/SFX/b"]);
+    }
+
+    #[test]
+    fn test_exists() {
+        let entry = FileEntry::new(
+            PathBuf::from("sound/GUIDs.txt"),
+            FileKind::Mod,
+            Path::new("/mod/sound/GUIDs.txt").to_path_buf(),
+        );
+        let mut banks = SoundBanks::default();
+        banks.handle_file(&entry, vec![Token::new("event:/SFX/UI/my_click", Loc::from(&entry))]);
+        assert!(!banks.exists("event:/SFX/UI/other"));
+        assert!(banks.used.read().unwrap().is_empty());
+        assert!(banks.exists("event:/sfx/ui/My_Click"));
+        assert!(banks.exists("event:/SFX/UI/my_click"));
+        let used = banks.used.read().unwrap();
+        assert_eq!(used.iter().collect::<Vec<_>>(), ["event:/sfx/ui/my_click"]);
+    }
+}
@@ -0,0 +1,214 @@
+//! Settings and helpers for the optional spell checking of localization text.
+
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+
+use strum::IntoEnumIterator;
+
+use crate::block::Block;
+use crate::data::localization::Language;
+#[cfg(feature = "ck3")]
+use crate::data::localization::LocaEntry;
+use crate::everything::Everything;
+use crate::helpers::TigerHashSet;
+use crate::hunspell::Dictionary;
+use crate::item::Item;
+use crate::report::{err, warn, ErrorKey};
+use crate::token::Token;
+use crate::translations::language_code;
+
+/// The `spelling` section of the config file.
+#[derive(Debug)]
+pub struct SpellingConfig {
+    /// The `dictionaries` value, to point at when a dictionary can't be used.
+    dictionaries_token: Token,
+    dictionaries: PathBuf,
+    /// Words that are spelled correctly in every language, in lowercase.
+    words: TigerHashSet<String>,
+}
+
+impl SpellingConfig {
+    /// Read the `spelling` section. Spell checking is only done if it names a directory of
+    /// dictionaries.
+    pub fn from_config(block: &Block) -> Option<Self> {
+        let dictionaries_token = block.get_field_value("dictionaries")?.clone();
+        // Relative paths are relative to the config file.
+        let config_dir = block.loc.fullpath().parent().unwrap_or(Path::new(""));
+        let dictionaries = config_dir.join(dictionaries_token.as_str());
+        let mut words = TigerHashSet::default();
+        for token in block.get_field_list("words").unwrap_or_default() {
+            words.insert(token.as_str().to_lowercase());
+        }
+        if let Some(token) = block.get_field_value("word_list") {
+            match read_to_string(config_dir.join(token.as_str())) {
+                Ok(list) => {
+                    for line in list.lines().map(str::trim) {
+                        if !line.is_empty() && !line.starts_with('#') {
+                            words.insert(line.to_lowercase());
+                        }
+                    }
+                }
+                Err(e) => {
+                    let msg = format!("could not read word list: {e}");
+                    err(ErrorKey::Config).msg(msg).loc(token).push();
+                }
+            }
+        }
+        Some(Self { dictionaries_token, dictionaries, words })
+    }
+
+    /// Load the dictionary for `lang`. It is found by the name of the language, such as
+    /// `english.aff`, or by the language code, such as `en.aff` or `en_US.aff`.
+    pub fn dictionary(&self, lang: Language) -> Option<Dictionary> {
+        let code = language_code(lang).replace('-', "_");
+        let mut names: Vec<String> = read_dir(&self.dictionaries)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| entry.file_name().to_str()?.strip_suffix(".aff").map(str::to_owned))
+            .collect();
+        names.sort_unstable();
+        let lang_name = lang.to_string();
+        let name = names
+            .iter()
+            .find(|name| **name == lang_name)
+            .or_else(|| names.iter().find(|name| **name == code))
+            .or_else(|| names.iter().find(|name| name.starts_with(&format!("{code}_"))));
+        let Some(name) = name else {
+            let msg = format!("no {lang} dictionary found for spell checking");
+            let info = format!("expected a file like {code}.aff and {code}.dic in this directory");
+            warn(ErrorKey::Config).msg(msg).info(info).loc(&self.dictionaries_token).push();
+            return None;
+        };
+        let aff = self.dictionaries.join(format!("{name}.aff"));
+        let dic = self.dictionaries.join(format!("{name}.dic"));
+        match Dictionary::read(&aff, &dic) {
+            Ok(dict) => Some(dict),
+            Err(e) => {
+                let msg = format!("could not load the {lang} dictionary: {e:#}");
+                err(ErrorKey::Config).msg(msg).loc(&self.dictionaries_token).push();
+                None
+            }
+        }
+    }
+
+    /// Collect the words that count as spelled correctly in every language: the configured ones,
+    /// the words in the names of the game's and the mod's items, and for ck3 the words in the
+    /// names of titles in the reference language.
+    pub fn known_words(&self, data: &Everything) -> TigerHashSet<String> {
+        let mut words = self.words.clone();
+        for itype in Item::iter() {
+            // Localization keys and file names are too far from being names to be useful here.
+            if itype != Item::Localization && itype != Item::File {
+                for key in data.iter_keys(itype) {
+                    words.extend(key_words(key.as_str()).map(str::to_lowercase));
+                }
+            }
+        }
+        #[cfg(feature = "ck3")]
+        {
+            let mut add = |text: &str| {
+                for (_, word) in split_words(text) {
+                    words.insert(word.to_lowercase());
+                }
+            };
+            data.characters.iter_names().for_each(|name| add(name.as_str()));
+            let lang = data.localization.reference_lang();
+            for title in data.iter_keys(Item::Title) {
+                for key in [title.to_string(), format!("{title}_adj")] {
+                    if let Some(text) =
+                        data.localization.get_entry(lang, &key).and_then(LocaEntry::orig)
+                    {
+                        add(text.as_str());
+                    }
+                }
+            }
+        }
+        words
+    }
+}
+
+/// Split an item key like `k_france` or `trait.brave` into the words in it.
+fn key_words(key: &str) -> impl Iterator<Item = &str> {
+    key.split(|c: char| !c.is_alphabetic()).filter(|word| !word.is_empty())
+}
+
+/// Split localization text into words, with the character column at which each word starts.
+/// Code, icons, markup, and escape sequences that may still be in the text are skipped, and so
+/// are words with digits or underscores in them.
+pub fn split_words(text: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut chars = text.char_indices().enumerate().peekable();
+    // The byte offset and column of the word in progress.
+    let mut start: Option<(usize, usize)> = None;
+    let mut skip_word = false;
+    while let Some((col, (i, c))) = chars.next() {
+        let apostrophe_in_word = (c == '\'' || c == '’')
+            && start.is_some()
+            && chars.peek().is_some_and(|(_, (_, next))| next.is_alphabetic());
+        if c.is_alphabetic() || apostrophe_in_word {
+            start.get_or_insert((i, col));
+            continue;
+        }
+        if c.is_ascii_digit() || c == '_' {
+            skip_word = true;
+            start.get_or_insert((i, col));
+            continue;
+        }
+        if let Some((start, start_col)) = start.take() {
+            if !std::mem::take(&mut skip_word) {
+                words.push((start_col, &text[start..i]));
+            }
+        }
+        match c {
+            // Escape sequences, hoi4 color codes, and the ends of markup are two characters.
+            '\\' | '§' => _ = chars.next(),
+            '#' if chars.peek().is_some_and(|(_, (_, next))| *next == '!') => _ = chars.next(),
+            '[' => skip_until(&mut chars, |c| c == ']'),
+            '@' => skip_until(&mut chars, |c| c == '!'),
+            '#' | '£' => skip_until(&mut chars, char::is_whitespace),
+            _ => (),
+        }
+    }
+    if let Some((start, start_col)) = start {
+        if !skip_word {
+            words.push((start_col, &text[start..]));
+        }
+    }
+    words
+}
+
+fn skip_until<I: Iterator<Item = (usize, (usize, char))>>(chars: &mut I, end: fn(char) -> bool) {
+    for (_, (_, c)) in chars.by_ref() {
+        if end(c) {
+            break;
+        }
+    }
+}
+
+/// Return true iff the word should be checked. Single letters and words in all capitals, which
+/// are usually abbreviations, are not.
+pub fn should_check(word: &str) -> bool {
+    word.chars().nth(1).is_some() && word.chars().any(char::is_lowercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_words() {
+        let text = "It's the king's [ROOT.Char.GetName] @gold_icon! #bold sword#!\\nand 2nd_try";
+        let words: Vec<&str> = split_words(text).into_iter().map(|(_, word)| word).collect();
+        assert_eq!(words, vec!["It's", "the", "king's", "sword", "and"]);
+        assert_eq!(split_words("a word")[1], (2, "word"));
+    }
+
+    #[test]
+    fn test_key_words() {
+        let words: Vec<&str> = key_words("c_saint_denis").collect();
+        assert_eq!(words, vec!["c", "saint", "denis"]);
+        let words: Vec<&str> = key_words("lifestyle_2.tier-3").collect();
+        assert_eq!(words, vec!["lifestyle", "tier"]);
+    }
+}
@@ -0,0 +1,573 @@
+//! Export the mod's missing and out of date translations for use in translation tools, and import
+//! the results back into localization files.
+//!
+//! The supported formats are CSV, XLIFF 1.2, and gettext PO. The texts are exported unparsed, so
+//! codes like `[ROOT.Char.GetName]` and escapes like `\n` are kept as they are in the `.yml` files.
+
+use std::fmt::Write as _;
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use strum_macros::{Display, EnumString};
+
+use crate::data::localization::{Language, Untranslated};
+use crate::everything::Everything;
+use crate::helpers::TigerHashMap;
+use crate::report::xml_escape;
+
+/// The file formats that translations can be exported to and imported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum TranslationFormat {
+    /// Comma-separated values, with a header line.
+    Csv,
+    /// XML Localization Interchange File Format, version 1.2.
+    Xliff,
+    /// Gettext portable object.
+    Po,
+}
+
+impl TranslationFormat {
+    /// Guess the format from a file's extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "xliff" | "xlf" => Some(Self::Xliff),
+            "po" | "pot" => Some(Self::Po),
+            _ => None,
+        }
+    }
+}
+
+/// One translation read from an imported file.
+#[derive(Debug, PartialEq, Eq)]
+struct Imported {
+    key: String,
+    text: String,
+}
+
+/// The language code used by translation tools for this language.
+pub(crate) fn language_code(lang: Language) -> &'static str {
+    match lang {
+        Language::English => "en",
+        Language::Spanish => "es",
+        Language::French => "fr",
+        Language::German => "de",
+        Language::Russian => "ru",
+        #[cfg(any(feature = "ck3", feature = "vic3"))]
+        Language::Korean => "ko",
+        Language::SimpChinese => "zh-Hans",
+        #[cfg(any(feature = "vic3", feature = "hoi4"))]
+        Language::BrazPor => "pt-BR",
+        #[cfg(any(feature = "vic3", feature = "hoi4"))]
+        Language::Japanese => "ja",
+        #[cfg(any(feature = "ck3", feature = "vic3", feature = "hoi4"))]
+        Language::Polish => "pl",
+        #[cfg(feature = "vic3")]
+        Language::Turkish => "tr",
+    }
+}
+
+/// Return the text of the file of the mod's localizations that are missing or out of date in
+/// `lang`, in the given format.
+pub fn export_translations(
+    data: &Everything,
+    lang: Language,
+    format: TranslationFormat,
+    hashes_path: Option<&Path>,
+) -> Result<String> {
+    let reference_lang = data.localization.reference_lang();
+    if lang == reference_lang {
+        bail!("{lang} is the reference language that the others are translated from");
+    }
+    let entries = data.localization.untranslated(lang, hashes_path)?;
+    Ok(match format {
+        TranslationFormat::Csv => export_csv(&entries),
+        TranslationFormat::Xliff => export_xliff(&entries, reference_lang, lang),
+        TranslationFormat::Po => export_po(&entries, lang),
+    })
+}
+
+fn origin(entry: &Untranslated) -> String {
+    format!("{}:{}", entry.key.loc.pathname().display(), entry.key.loc.line)
+}
+
+fn context(entry: &Untranslated) -> String {
+    match entry.used_at {
+        Some(loc) => format!("used at {}:{}", loc.pathname().display(), loc.line),
+        None => String::new(),
+    }
+}
+
+fn status(entry: &Untranslated) -> &'static str {
+    if entry.current.is_some() {
+        "stale"
+    } else {
+        "missing"
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn export_csv(entries: &[Untranslated]) -> String {
+    let mut csv = "key,source,translation,status,file,context\n".to_string();
+    for entry in entries {
+        let fields = [
+            entry.key.as_str(),
+            entry.source,
+            entry.current.unwrap_or(""),
+            status(entry),
+            &origin(entry),
+            &context(entry),
+        ];
+        let fields: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn export_xliff(entries: &[Untranslated], reference_lang: Language, lang: Language) -> String {
+    let mut xliff = String::new();
+    xliff.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xliff.push_str("<xliff version=\"1.2\" xmlns=\"urn:oasis:names:tc:xliff:document:1.2\">\n");
+    // Group the entries by their file of origin, keeping the order.
+    let mut files: Vec<(&Path, Vec<&Untranslated>)> = Vec::new();
+    for entry in entries {
+        let path = entry.key.loc.pathname();
+        match files.iter_mut().find(|(p, _)| *p == path) {
+            Some((_, file_entries)) => file_entries.push(entry),
+            None => files.push((path, vec![entry])),
+        }
+    }
+    for (path, file_entries) in files {
+        let _ = writeln!(
+            xliff,
+            "  <file original=\"{}\" source-language=\"{}\" target-language=\"{}\" datatype=\"plaintext\">",
+            xml_escape(&path.to_string_lossy()),
+            language_code(reference_lang),
+            language_code(lang),
+        );
+        xliff.push_str("    <body>\n");
+        for entry in file_entries {
+            let key = xml_escape(entry.key.as_str());
+            let _ = writeln!(xliff, "      <trans-unit id=\"{key}\" resname=\"{key}\">");
+            let _ = writeln!(xliff, "        <source>{}</source>", xml_escape(entry.source));
+            match entry.current {
+                Some(current) => {
+                    let _ = writeln!(
+                        xliff,
+                        "        <target state=\"needs-review-translation\">{}</target>",
+                        xml_escape(current)
+                    );
+                }
+                None => xliff.push_str("        <target state=\"needs-translation\"/>\n"),
+            }
+            let _ = writeln!(xliff, "        <note>{}</note>", xml_escape(&origin(entry)));
+            let context = context(entry);
+            if !context.is_empty() {
+                let _ = writeln!(xliff, "        <note>{}</note>", xml_escape(&context));
+            }
+            xliff.push_str("      </trans-unit>\n");
+        }
+        xliff.push_str("    </body>\n");
+        xliff.push_str("  </file>\n");
+    }
+    xliff.push_str("</xliff>\n");
+    xliff
+}
+
+fn po_string(text: &str) -> String {
+    let mut result = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            _ => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+fn export_po(entries: &[Untranslated], lang: Language) -> String {
+    let mut po = String::new();
+    po.push_str("msgid \"\"\nmsgstr \"\"\n");
+    po.push_str("\"Content-Type: text/plain; charset=UTF-8\\n\"\n");
+    let _ = writeln!(po, "\"Language: {}\\n\"", language_code(lang).replace('-', "_"));
+    for entry in entries {
+        po.push('\n');
+        let context = context(entry);
+        if !context.is_empty() {
+            let _ = writeln!(po, "#. {context}");
+        }
+        let _ = writeln!(po, "#: {}", origin(entry));
+        if entry.current.is_some() {
+            po.push_str("#, fuzzy\n");
+        }
+        let _ = writeln!(po, "msgctxt {}", po_string(entry.key.as_str()));
+        let _ = writeln!(po, "msgid {}", po_string(entry.source));
+        let _ = writeln!(po, "msgstr {}", po_string(entry.current.unwrap_or("")));
+    }
+    po
+}
+
+/// Read the translations into `lang` from the file at `path` and write them into the mod's
+/// localization files. Translations that are empty, unchanged, or still marked as needing work
+/// are skipped. Return the paths of the files that were written.
+pub fn import_translations(
+    data: &Everything,
+    lang: Language,
+    format: TranslationFormat,
+    path: &Path,
+) -> Result<Vec<PathBuf>> {
+    let content =
+        read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+    let content = content.strip_prefix('\u{feff}').unwrap_or(&content);
+    let imported = match format {
+        TranslationFormat::Csv => import_csv(content)?,
+        TranslationFormat::Xliff => import_xliff(content),
+        TranslationFormat::Po => import_po(content),
+    };
+
+    // Collect the new lines per destination file, keeping the order of the imported file.
+    let mut files: Vec<(PathBuf, Vec<(String, String)>)> = Vec::new();
+    let mut unknown = Vec::new();
+    for Imported { key, text } in imported {
+        let text = loca_text(&text);
+        if text.is_empty() {
+            continue;
+        }
+        let Some((dest, version, current)) = data.localization.translation_destination(lang, &key)
+        else {
+            unknown.push(key);
+            continue;
+        };
+        if current == Some(text.as_str()) {
+            continue;
+        }
+        let line = format!(" {key}:{version} \"{text}\"");
+        match files.iter_mut().find(|(p, _)| *p == dest) {
+            Some((_, lines)) => lines.push((key, line)),
+            None => files.push((dest, vec![(key, line)])),
+        }
+    }
+    if !unknown.is_empty() {
+        eprintln!("Skipped keys that the mod does not define: {}", unknown.join(", "));
+    }
+
+    let mod_root = data.fileset.mod_root();
+    let mut written = Vec::new();
+    for (dest, lines) in files {
+        let fullpath = mod_root.join(&dest);
+        let old = match read_to_string(&fullpath) {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                return Err(e).with_context(|| format!("could not read {}", fullpath.display()))
+            }
+        };
+        let content = merge_lines(old.as_deref(), lang, &lines);
+        if let Some(parent) = fullpath.parent() {
+            create_dir_all(parent)?;
+        }
+        write(&fullpath, content)
+            .with_context(|| format!("could not write {}", fullpath.display()))?;
+        written.push(dest);
+    }
+    Ok(written)
+}
+
+/// Escape the line breaks and tabs in an imported text the way they are written in `.yml` files,
+/// since a localization value has to be on a single line.
+fn loca_text(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n").replace('\n', "\\n").replace('\t', "\\t")
+}
+
+/// Replace the lines for the given keys in the localization file text `old`, or add them at the
+/// end. Create a new file text if there is none. A key that is given more than once gets the
+/// last of its lines. The result always starts with a BOM.
+fn merge_lines(old: Option<&str>, lang: Language, lines: &[(String, String)]) -> String {
+    let old = old.map(|old| old.strip_prefix('\u{feff}').unwrap_or(old));
+    let mut result: Vec<String> = match old {
+        Some(old) => old.lines().map(str::to_string).collect(),
+        None => vec![format!("l_{lang}:")],
+    };
+    let mut index: TigerHashMap<String, usize> = TigerHashMap::default();
+    for (i, line) in result.iter().enumerate() {
+        if let Some((key, _)) = line.trim_start().split_once(':') {
+            if !key.starts_with('#') && !key.contains(char::is_whitespace) {
+                index.entry(key.to_string()).or_insert(i);
+            }
+        }
+    }
+    for (key, line) in lines {
+        if let Some(&i) = index.get(key) {
+            result[i].clone_from(line);
+        } else {
+            index.insert(key.clone(), result.len());
+            result.push(line.clone());
+        }
+    }
+    let mut content = "\u{feff}".to_string();
+    for line in result {
+        content.push_str(&line);
+        content.push('\n');
+    }
+    content
+}
+
+/// Split CSV text into records of fields.
+fn parse_csv(content: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(c);
+            }
+        } else {
+            match c {
+                '"' => in_quotes = true,
+                ',' => record.push(std::mem::take(&mut field)),
+                '\r' => (),
+                '\n' => {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+                _ => field.push(c),
+            }
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+fn import_csv(content: &str) -> Result<Vec<Imported>> {
+    let mut records = parse_csv(content).into_iter();
+    let Some(header) = records.next() else {
+        return Ok(Vec::new());
+    };
+    let column = |name: &str| header.iter().position(|h| h.trim().eq_ignore_ascii_case(name));
+    let (Some(key), Some(translation)) = (column("key"), column("translation")) else {
+        bail!("the CSV file must have a header line with `key` and `translation` columns");
+    };
+    Ok(records
+        .filter_map(|record| {
+            Some(Imported { key: record.get(key)?.clone(), text: record.get(translation)?.clone() })
+        })
+        .collect())
+}
+
+fn xml_unescape(text: &str) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(i) = rest.find('&') {
+        result.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        if let Some(c) = decoded {
+            result.push(c);
+            rest = &rest[end + 1..];
+        } else {
+            result.push('&');
+            rest = &rest[1..];
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Return the value of the attribute `name` in the XML start tag `tag`.
+fn xml_attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let pattern = format!(" {name}=\"");
+    let start = tag.find(&pattern)? + pattern.len();
+    let end = tag[start..].find('"')?;
+    Some(&tag[start..start + end])
+}
+
+/// Read the `<trans-unit>` elements. This is not a full XML parser, but it handles the files
+/// written by translation tools.
+fn import_xliff(content: &str) -> Vec<Imported> {
+    let mut result = Vec::new();
+    for unit in content.split("<trans-unit").skip(1) {
+        let Some(tag_end) = unit.find('>') else {
+            continue;
+        };
+        let tag = &unit[..tag_end];
+        let Some(key) = xml_attribute(tag, "resname").or_else(|| xml_attribute(tag, "id")) else {
+            continue;
+        };
+        let unit = unit.split("</trans-unit>").next().unwrap_or(unit);
+        let Some(target_start) = unit.find("<target") else {
+            continue;
+        };
+        let target = &unit[target_start..];
+        let Some(target_tag_end) = target.find('>') else {
+            continue;
+        };
+        let target_tag = &target[..target_tag_end];
+        if target_tag.ends_with('/') {
+            continue;
+        }
+        if xml_attribute(target_tag, "state").is_some_and(|state| state.starts_with("needs-")) {
+            continue;
+        }
+        let Some(text_end) = target.find("</target>") else {
+            continue;
+        };
+        let text = &target[target_tag_end + 1..text_end];
+        result.push(Imported { key: xml_unescape(key), text: xml_unescape(text) });
+    }
+    result
+}
+
+/// Decode the contents of a PO string, without the enclosing quotes.
+fn po_unescape(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some(c) => result.push(c),
+                None => (),
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Read the entries of a PO file that have a `msgctxt`, which is where the keys are.
+fn import_po(content: &str) -> Vec<Imported> {
+    let mut result = Vec::new();
+    let mut fuzzy = false;
+    let mut fields: TigerHashMap<&str, String> = TigerHashMap::default();
+    let mut current = None;
+    // Entries are separated by blank lines. Going by lines handles both `\n` and `\r\n`.
+    for line in content.lines().map(str::trim).chain([""]) {
+        if line.is_empty() {
+            if let (Some(key), Some(text)) = (fields.remove("msgctxt"), fields.remove("msgstr")) {
+                if !fuzzy {
+                    result.push(Imported { key, text });
+                }
+            }
+            fuzzy = false;
+            fields.clear();
+            current = None;
+        } else if let Some(flags) = line.strip_prefix("#,") {
+            fuzzy |= flags.split(',').any(|flag| flag.trim() == "fuzzy");
+        } else if let Some(text) = line.strip_prefix('"') {
+            if let Some(field) = current {
+                let value: &mut String = fields.entry(field).or_default();
+                value.push_str(&po_unescape(text.strip_suffix('"').unwrap_or(text)));
+            }
+        } else if let Some((name, text)) = line.split_once(' ').filter(|_| !line.starts_with('#')) {
+            let text = text.trim();
+            let text = text.strip_prefix('"').unwrap_or(text);
+            let text = text.strip_suffix('"').unwrap_or(text);
+            fields.insert(name, po_unescape(text));
+            current = Some(name);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_xliff() {
+        let xliff = r#"<xliff><file><body>
+<trans-unit id="k1" resname="k1"><source>A &amp; B</source><target state="translated">A &amp; B&#x21;</target></trans-unit>
+<trans-unit id="k2"><source>C</source><target state="needs-translation"/></trans-unit>
+<trans-unit id="k3"><source>D</source><target state="needs-review-translation">E</target></trans-unit>
+</body></file></xliff>"#;
+        let imported = import_xliff(xliff);
+        assert_eq!(imported, vec![Imported { key: "k1".to_string(), text: "A & B!".to_string() }]);
+    }
+
+    #[test]
+    fn test_import_po() {
+        let po = "msgid \"\"\nmsgstr \"\"\n\"Language: de\\n\"\n\n#: a.yml:2\nmsgctxt \"k1\"\nmsgid \"Line\\\\n two\"\nmsgstr \"\"\n\"Zeile\\\\n \"\n\"zwei\"\n\n#, fuzzy\nmsgctxt \"k2\"\nmsgid \"x\"\nmsgstr \"y\"\n";
+        let imported = import_po(po);
+        assert_eq!(
+            imported,
+            vec![Imported { key: "k1".to_string(), text: "Zeile\\n zwei".to_string() }]
+        );
+        assert_eq!(import_po(&po.replace('\n', "\r\n")), imported);
+    }
+
+    #[test]
+    fn test_merge_lines() {
+        let old = "\u{feff}l_german:\n # comment\n k1:0 \"old\"\n k2:0 \"keep\"\n";
+        let lines = vec![
+            ("k1".to_string(), " k1:1 \"new\"".to_string()),
+            ("k3".to_string(), " k3:0 \"added\"".to_string()),
+        ];
+        let merged = merge_lines(Some(old), Language::German, &lines);
+        assert_eq!(
+            merged,
+            "\u{feff}l_german:\n # comment\n k1:1 \"new\"\n k2:0 \"keep\"\n k3:0 \"added\"\n"
+        );
+        let new = merge_lines(None, Language::German, &lines[1..]);
+        assert_eq!(new, "\u{feff}l_german:\n k3:0 \"added\"\n");
+    }
+
+    #[test]
+    fn test_merge_lines_twice() {
+        let lines = vec![
+            ("k1".to_string(), " k1:0 \"first\"".to_string()),
+            ("k1".to_string(), " k1:0 \"second\"".to_string()),
+        ];
+        let new = merge_lines(None, Language::German, &lines);
+        assert_eq!(new, "\u{feff}l_german:\n k1:0 \"second\"\n");
+    }
+
+    #[test]
+    fn test_loca_text() {
+        assert_eq!(loca_text("one\r\ntwo\tthree\nfour"), "one\\ntwo\\tthree\\nfour");
+        assert_eq!(loca_text("already\\nescaped"), "already\\nescaped");
+    }
+}
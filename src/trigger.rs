@@ -13,6 +13,7 @@ use crate::data::trigger_localization::TriggerLocalization;
 use crate::date::Date;
 use crate::desc::validate_desc;
 use crate::everything::Everything;
+use crate::explain::probe_scope;
 use crate::game::Game;
 use crate::helpers::is_country_tag;
 use crate::helpers::stringify_choices;
@@ -32,6 +33,7 @@ use crate::scopes::{
 use crate::script_value::validate_script_value;
 use crate::token::{Loc, Token};
 use crate::tooltipped::Tooltipped;
+use crate::trigger_logic::check_trigger_logic;
 use crate::validate::{
     precheck_iterator_fields, validate_identifier, validate_ifelse_sequence,
     validate_inside_iterator, validate_iterator_fields, ListType,
@@ -176,6 +178,8 @@ pub fn validate_trigger_internal(
         }
     }
 
+    check_trigger_logic(caller, block);
+
     if caller == "trigger_if"
         || caller == "trigger_else_if"
         || caller == "trigger_else"
@@ -265,6 +269,9 @@ pub fn validate_trigger_internal(
     validate_ifelse_sequence(block, "trigger_if", "trigger_else_if", "trigger_else");
 
     vd.unknown_fields_any_cmp(|key, cmp, bv| {
+        probe_scope(key, sc);
+        data.variable_flow.record_field(key, bv);
+
         #[cfg(feature = "jomini")]
         if Game::is_jomini() && key.is("value") {
             validate_script_value(bv, data, sc);
@@ -1600,6 +1607,7 @@ pub fn validate_argument_scope(
 ) {
     validate_inscopes(part_flags, func, inscopes, sc);
     validate_argument_internal(arg, validation, data, sc);
+    data.variable_flow.record_target(func, arg);
 
     let mut outscopes_token = func.clone();
     outscopes_token.combine(arg, ':');
@@ -0,0 +1,328 @@
+//! Checks for trigger blocks whose conditions contradict each other or are redundant.
+//!
+//! These look only at the script text of a single block, not at what the triggers mean in
+//! general, so they catch the simple cases: the same trigger compared to literal numbers in
+//! incompatible ways, a yes/no trigger required to be both, and so on.
+
+use crate::block::{Block, Comparator, Eq::*, Field, BV};
+use crate::game::GameFlags;
+use crate::helpers::TigerHashMap;
+use crate::lowercase::Lowercase;
+use crate::report::{err, tips, untidy, warn, ErrorKey};
+use crate::token::Token;
+
+/// Fields that are parameters of the block they're in rather than conditions.
+/// Repeating them is either harmless or already checked elsewhere.
+const NOT_CONDITIONS: &[&str] = &[
+    "add",
+    "amount",
+    "count",
+    "desc",
+    "factor",
+    "filter",
+    "limit",
+    "list",
+    "max",
+    "min",
+    "object",
+    "order_by",
+    "percent",
+    "position",
+    "save_temporary_scope_as",
+    "subject",
+    "text",
+    "trigger_else",
+    "trigger_else_if",
+    "trigger_if",
+    "type",
+    "value",
+    "variable",
+];
+
+/// Pairs of yes/no triggers of which exactly one is always true.
+const COMPLEMENTS: &[(&str, &str, GameFlags)] =
+    &[("is_female", "is_male", GameFlags::Ck3.union(GameFlags::Imperator))];
+
+/// Check the combination of the conditions in a trigger block.
+/// `caller` is the key that opened the block, as for `validate_trigger_internal`.
+pub fn check_trigger_logic(caller: &Lowercase, block: &Block) {
+    if caller == "not" {
+        check_not(block);
+    }
+    // Only blocks that require all their conditions to be true can contradict themselves this way.
+    if caller == "or"
+        || caller == "nor"
+        || caller == "all_false"
+        || caller == "calc_true_if"
+        || caller == "count_triggers"
+        || caller == "switch"
+    {
+        return;
+    }
+    check_duplicates(block);
+    // In `NOT` and `NAND` the contradictions would make the trigger always true instead, which is
+    // just as much a mistake, but the messages would be misleading.
+    if caller != "not" && caller != "nand" {
+        check_yes_no(block);
+        check_numeric(block);
+    }
+}
+
+/// Warn about the ways `NOT` is commonly misused.
+fn check_not(block: &Block) {
+    if block.num_items() > 1 {
+        let msg = "`NOT` with more than one item is true if any of them is false";
+        let info = "if you meant that none of them may be true, use `NOR` instead, or use `NAND` to make the intent clear";
+        warn(ErrorKey::Logic).weak().msg(msg).info(info).loc(block).push();
+        return;
+    }
+    let Some(Field(key, Comparator::Equals(Single), bv)) = block.iter_fields().next() else {
+        return;
+    };
+    match bv {
+        BV::Block(_) => {
+            let replacement = if key.lowercase_is("not") {
+                "the contents of the inner `NOT`"
+            } else if key.lowercase_is("nor") {
+                "`OR`"
+            } else if key.lowercase_is("nand") {
+                "`AND`"
+            } else {
+                return;
+            };
+            let msg = format!("double negation with `NOT = {{ {key} = {{ ... }} }}`");
+            let info = format!("this can be replaced by {replacement}");
+            untidy(ErrorKey::Logic).msg(msg).info(info).loc(key).push();
+        }
+        BV::Value(value) => {
+            if value.is("no") {
+                let msg = format!("double negation with `NOT = {{ {key} = no }}`");
+                let info = format!("this can be replaced by `{key} = yes`");
+                tips(ErrorKey::Logic).msg(msg).info(info).loc(key).push();
+            }
+        }
+    }
+}
+
+/// Return the fields in the block that are conditions, leaving out the ones that came from macro
+/// expansion because those might be different in other expansions.
+fn conditions(block: &Block) -> impl Iterator<Item = &Field> {
+    block.iter_fields().filter(|Field(key, _, _)| {
+        key.loc.link_idx.is_none()
+            && !NOT_CONDITIONS.iter().any(|k| key.lowercase_is(k))
+            && !key.as_str().contains('$')
+    })
+}
+
+/// Warn about conditions that are exactly repeated in the same block.
+fn check_duplicates(block: &Block) {
+    let mut seen: TigerHashMap<Lowercase, Vec<&Field>> = TigerHashMap::default();
+    for field in conditions(block) {
+        let earlier = seen.entry(Lowercase::new(field.key().as_str())).or_default();
+        if let Some(other) = earlier.iter().find(|other| other.equivalent(field)) {
+            let msg = "this condition is the same as an earlier one in this block";
+            let info = "one of them is redundant, or was meant to be different";
+            warn(ErrorKey::Logic)
+                .msg(msg)
+                .info(info)
+                .loc(field.key())
+                .loc_msg(other.key(), "earlier condition")
+                .push();
+        } else {
+            earlier.push(field);
+        }
+    }
+}
+
+/// Return `Some(true)` or `Some(false)` if the field is `key = yes` or `key = no`.
+fn yes_no(field: &Field) -> Option<bool> {
+    match field {
+        Field(_, Comparator::Equals(Single), BV::Value(value)) => {
+            if value.is("yes") {
+                Some(true)
+            } else if value.is("no") {
+                Some(false)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Warn about yes/no triggers that are required to have incompatible values.
+fn check_yes_no(block: &Block) {
+    let game = GameFlags::game();
+    let mut seen: Vec<(&Token, bool)> = Vec::new();
+    for field in conditions(block) {
+        let Some(value) = yes_no(field) else {
+            continue;
+        };
+        let key = field.key();
+        for &(other, other_value) in &seen {
+            let contradiction = if key.as_str() == other.as_str() {
+                value != other_value
+            } else {
+                // For complements, exactly one of the two must be yes.
+                value == other_value
+                    && COMPLEMENTS.iter().any(|(a, b, games)| {
+                        games.contains(game)
+                            && ((key.is(a) && other.is(b)) || (key.is(b) && other.is(a)))
+                    })
+            };
+            if contradiction {
+                let msg = format!("`{key}` contradicts `{other}` here, so this can never be true");
+                err(ErrorKey::Logic)
+                    .msg(msg)
+                    .loc(key)
+                    .loc_msg(other, "contradicted condition")
+                    .push();
+            }
+        }
+        seen.push((key, value));
+    }
+}
+
+/// A lower or upper bound on the value of a trigger.
+#[derive(Debug, Clone, Copy)]
+struct Bound<'a> {
+    value: f64,
+    inclusive: bool,
+    token: &'a Token,
+}
+
+/// Warn about numeric triggers that are compared to literal numbers in incompatible ways, such
+/// as `age > 50` together with `age < 20`.
+fn check_numeric(block: &Block) {
+    let mut bounds: TigerHashMap<&str, (Option<Bound>, Option<Bound>)> = TigerHashMap::default();
+    for Field(key, cmp, bv) in conditions(block) {
+        let Some(value) = bv.get_value().and_then(Token::get_number) else {
+            continue;
+        };
+        let new = |inclusive| Bound { value, inclusive, token: key };
+        let (lower, upper) = bounds.entry(key.as_str()).or_default();
+        match cmp {
+            Comparator::GreaterThan => tighten_lower(lower, new(false)),
+            Comparator::AtLeast => tighten_lower(lower, new(true)),
+            Comparator::LessThan => tighten_upper(upper, new(false)),
+            Comparator::AtMost => tighten_upper(upper, new(true)),
+            Comparator::Equals(Double) => {
+                tighten_lower(lower, new(true));
+                tighten_upper(upper, new(true));
+            }
+            _ => continue,
+        }
+        if let (Some(low), Some(high)) = (*lower, *upper) {
+            if is_empty_range(low, high) {
+                let other = if std::ptr::eq(low.token, key) { high.token } else { low.token };
+                let msg = format!("`{key}` can never be in this range, so this can never be true");
+                err(ErrorKey::Logic).msg(msg).loc(key).loc_msg(other, "other limit").push();
+                // Only report the first contradiction for each trigger.
+                bounds.remove(key.as_str());
+            }
+        }
+    }
+}
+
+#[allow(clippy::float_cmp)] // Exact comparison is intended
+fn is_empty_range(low: Bound, high: Bound) -> bool {
+    low.value > high.value || (low.value == high.value && !(low.inclusive && high.inclusive))
+}
+
+#[allow(clippy::float_cmp)] // Exact comparison is intended
+fn tighten_lower<'a>(lower: &mut Option<Bound<'a>>, new: Bound<'a>) {
+    if lower.is_none_or(|old| new.value > old.value || (new.value == old.value && !new.inclusive)) {
+        *lower = Some(new);
+    }
+}
+
+#[allow(clippy::float_cmp)] // Exact comparison is intended
+fn tighten_upper<'a>(upper: &mut Option<Bound<'a>>, new: Bound<'a>) {
+    if upper.is_none_or(|old| new.value < old.value || (new.value == old.value && !new.inclusive)) {
+        *upper = Some(new);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::pdxfile::parse_pdx_internal;
+    use crate::report::{take_reports_for, LogReport, Severity};
+
+    /// Run the checks on `input` as the contents of a `caller` block and return the reports.
+    fn check(caller: &str, input: &'static str, desc: &str) -> Vec<LogReport> {
+        let block = parse_pdx_internal(input, desc);
+        check_trigger_logic(&Lowercase::new(caller), &block);
+        let mut reports = take_reports_for(desc);
+        reports.sort_by_key(|report| report.primary().loc);
+        reports
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let reports = check(
+            "trigger",
+            "is_adult = yes\nhas_trait = brave\nhas_trait = shy\nhas_trait = brave\ncount = 2\ncount = 2",
+            "test_duplicates.txt",
+        );
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].severity, Severity::Warning);
+        assert_eq!(reports[0].primary().loc.line, 4);
+        assert_eq!(reports[0].pointers[1].loc.line, 2);
+
+        // In an OR, repeating a condition is harmless.
+        assert!(check("or", "a = yes\na = yes", "test_duplicates_or.txt").is_empty());
+    }
+
+    #[test]
+    fn test_contradictions() {
+        let reports = check("trigger", "is_ruler = yes\nis_ruler = no\nage = 3", "test_yes_no.txt");
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].severity, Severity::Error);
+        assert_eq!(reports[0].primary().loc.line, 2);
+
+        let reports = check(
+            "and",
+            "age > 50\nage < 60\nage <= 50\ngold >= 10\ngold <= 10\nprestige < 5\nprestige == 5",
+            "test_numeric.txt",
+        );
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].primary().loc.line, 3);
+        assert_eq!(reports[0].pointers[1].loc.line, 1);
+        assert_eq!(reports[1].primary().loc.line, 7);
+
+        // Inside a NOT or NAND the same conditions are not a contradiction.
+        assert!(check("nand", "age > 50\nage < 20", "test_numeric_nand.txt").is_empty());
+        assert!(check("nor", "is_ruler = yes\nis_ruler = no", "test_yes_no_nor.txt").is_empty());
+    }
+
+    #[cfg(any(feature = "ck3", feature = "imperator"))]
+    #[test]
+    fn test_complements() {
+        let reports = check("trigger", "is_female = yes\nis_male = yes", "test_complements.txt");
+        assert_eq!(reports.len(), 1);
+        assert!(
+            check("trigger", "is_female = yes\nis_male = no", "test_complements_ok.txt").is_empty()
+        );
+    }
+
+    #[test]
+    fn test_not() {
+        let reports = check("not", "a = yes\nb = yes", "test_not_many.txt");
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].severity, Severity::Warning);
+        assert_eq!(reports[0].confidence, crate::report::Confidence::Weak);
+
+        let reports = check("not", "NOR = { a = yes b = yes }", "test_not_nor.txt");
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].severity, Severity::Untidy);
+        assert!(reports[0].info.as_ref().unwrap().contains("`OR`"));
+
+        let reports = check("not", "is_ruler = no", "test_not_no.txt");
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].severity, Severity::Tips);
+
+        assert!(check("not", "is_ruler = yes", "test_not_ok.txt").is_empty());
+        assert!(check("not", "OR = { a = yes b = yes }", "test_not_or.txt").is_empty());
+    }
+}
@@ -0,0 +1,283 @@
+//! Compare two versions of the base game to find what in the mod is affected by a game update.
+//!
+//! Both versions are loaded together with the mod, and the mod's overrides are compared against
+//! the vanilla files and items they replace in each version.
+
+use std::fs::read;
+use std::path::Path;
+
+use strum::IntoEnumIterator;
+
+use crate::block::{Block, BlockItem, Field};
+use crate::data::localization::Language;
+use crate::everything::Everything;
+use crate::fileset::FileHandler;
+use crate::helpers::TigerHashMap;
+use crate::item::Item;
+use crate::pdxfile::PdxFile;
+use crate::report::{warn, ErrorKey};
+use crate::token::Token;
+
+/// A vanilla item or localization key that exists in the old version but not in the new one.
+struct Removed<'a> {
+    /// What kind of thing it was, for the report.
+    what: String,
+    /// A new item that has the same definition, if there is exactly one.
+    renamed: Option<&'a Token>,
+}
+
+/// Report what in the mod is affected by the differences between the vanilla files loaded in
+/// `old` and the ones loaded in `new`. Both must have been loaded with the same mod.
+pub fn check_upgrade(old: &Everything, new: &Everything) {
+    check_files(old, new);
+    let mut removed = TigerHashMap::default();
+    check_items(old, new, &mut removed);
+    check_localization(old, new, &mut removed);
+    check_references(new, &removed);
+}
+
+/// Report mod files that replace vanilla files whose contents changed.
+fn check_files(old: &Everything, new: &Everything) {
+    for entry in new.fileset.iter_mod_files() {
+        let old_vanilla = old.fileset.get_overridden(entry.path());
+        let new_vanilla = new.fileset.get_overridden(entry.path());
+        match (old_vanilla, new_vanilla) {
+            (Some(old_vanilla), Some(new_vanilla)) => {
+                if !same_contents(old_vanilla.fullpath(), new_vanilla.fullpath()) {
+                    let msg = "the vanilla file that this file replaces has changed";
+                    let info = "the changes may need to be merged into this file";
+                    warn(ErrorKey::Upgrade).msg(msg).info(info).loc(entry).push();
+                }
+            }
+            (Some(_), None) => {
+                let msg = "the vanilla file that this file replaced no longer exists";
+                let info = "this file may need to be renamed or moved";
+                warn(ErrorKey::Upgrade).msg(msg).info(info).loc(entry).push();
+            }
+            (None, Some(_)) => {
+                let msg = "this file now replaces a vanilla file that was added";
+                let info = "if that was not intended, this file should be renamed";
+                warn(ErrorKey::Upgrade).msg(msg).info(info).loc(entry).push();
+            }
+            (None, None) => (),
+        }
+    }
+}
+
+fn same_contents(path1: &Path, path2: &Path) -> bool {
+    match (read(path1), read(path2)) {
+        (Ok(contents1), Ok(contents2)) => contents1 == contents2,
+        _ => false,
+    }
+}
+
+/// Report mod items that override vanilla items that changed or were removed, and collect the
+/// removed vanilla items that the mod does not define itself.
+fn check_items<'a>(
+    old: &'a Everything,
+    new: &'a Everything,
+    removed: &mut TigerHashMap<&'a str, Removed<'a>>,
+) {
+    for itype in Item::iter() {
+        let old_items: TigerHashMap<&str, (&Token, &Block)> = old
+            .database
+            .iter_vanilla_key_block(itype)
+            .map(|(key, block)| (key.as_str(), (key, block)))
+            .collect();
+        let new_items: TigerHashMap<&str, (&Token, &Block)> = new
+            .database
+            .iter_vanilla_key_block(itype)
+            .map(|(key, block)| (key.as_str(), (key, block)))
+            .collect();
+        let added: Vec<(&Token, &Block)> = new_items
+            .values()
+            .filter(|(key, _)| !old_items.contains_key(key.as_str()))
+            .copied()
+            .collect();
+
+        for (name, (_, old_block)) in &old_items {
+            if let Some((new_key, new_block)) = new_items.get(name) {
+                if new.database.get_overridden(itype, name).is_none() {
+                    continue;
+                }
+                let fields = changed_fields(old_block, new_block);
+                if !fields.is_empty() {
+                    // The item is overridden, so this is the mod's key.
+                    let (key, _) = new.database.get_key_block(itype, name).unwrap();
+                    let msg = format!("vanilla {itype} `{name}` that this overrides has changed");
+                    let info = format!("changed fields: {}", fields.join(", "));
+                    warn(ErrorKey::Upgrade)
+                        .msg(msg)
+                        .info(info)
+                        .loc(key)
+                        .loc_msg(*new_key, "new vanilla version")
+                        .push();
+                }
+                continue;
+            }
+
+            let renamed = find_renamed(old_block, &added);
+            if let Some((key, _)) = new.database.get_key_block(itype, name) {
+                let msg = format!("this overrides vanilla {itype} `{name}`, which was removed");
+                let mut report = warn(ErrorKey::Upgrade).msg(msg);
+                if let Some(renamed) = renamed {
+                    report = report.info(format!("it may have been renamed to `{renamed}`"));
+                }
+                report.loc(key).push();
+            } else if !new.database.exists(itype, name) {
+                removed.entry(name).or_insert(Removed { what: itype.to_string(), renamed });
+            }
+        }
+    }
+}
+
+/// Find the only added item that has the same definition as `block`.
+fn find_renamed<'a>(block: &Block, added: &[(&'a Token, &Block)]) -> Option<&'a Token> {
+    // Items with no contents are too alike to tell apart.
+    if block.num_items() == 0 {
+        return None;
+    }
+    let mut candidates = added.iter().filter(|(_, other)| other.equivalent(block));
+    match (candidates.next(), candidates.next()) {
+        (Some((key, _)), None) => Some(key),
+        _ => None,
+    }
+}
+
+/// Return the names of the fields that are different between the two blocks, in order.
+fn changed_fields(old: &Block, new: &Block) -> Vec<String> {
+    let mut names: Vec<&str> = Vec::new();
+    for block in [old, new] {
+        for Field(key, _, _) in block.iter_fields() {
+            if !names.contains(&key.as_str()) {
+                names.push(key.as_str());
+            }
+        }
+    }
+    let mut changed = Vec::new();
+    for name in names {
+        let old_fields: Vec<&Field> = old.iter_fields().filter(|f| f.key().is(name)).collect();
+        let new_fields: Vec<&Field> = new.iter_fields().filter(|f| f.key().is(name)).collect();
+        if old_fields.len() != new_fields.len()
+            || old_fields.iter().zip(&new_fields).any(|(f1, f2)| !f1.equivalent(f2))
+        {
+            changed.push(format!("`{name}`"));
+        }
+    }
+    // The difference is not in the fields, or it's only in their order.
+    if changed.is_empty() && !old.equivalent(new) {
+        changed.push("contents without a key, or order of fields".to_string());
+    }
+    changed
+}
+
+/// Report mod localizations that override vanilla localizations that changed or were removed,
+/// and collect the removed vanilla localization keys that the mod does not define itself.
+fn check_localization<'a>(
+    old: &'a Everything,
+    new: &'a Everything,
+    removed: &mut TigerHashMap<&'a str, Removed<'a>>,
+) {
+    for lang in Language::iter() {
+        let new_locas: TigerHashMap<&str, &str> =
+            new.localization.iter_vanilla(lang).map(|(key, text)| (key.as_str(), text)).collect();
+        for (key, old_text) in old.localization.iter_vanilla(lang) {
+            let current = new.localization.get_key(lang, key.as_str());
+            let overriding = current.filter(|current| !current.loc.kind.counts_as_vanilla());
+            match (new_locas.get(key.as_str()), overriding) {
+                (Some(new_text), Some(overriding)) => {
+                    if old_text != *new_text {
+                        let msg =
+                            format!("vanilla localization `{key}` that this overrides has changed");
+                        let info = format!("it was \"{old_text}\" and is now \"{new_text}\"");
+                        warn(ErrorKey::Upgrade).msg(msg).info(info).loc(overriding).push();
+                    }
+                }
+                (None, Some(overriding)) => {
+                    let msg =
+                        format!("this overrides vanilla localization `{key}`, which was removed");
+                    warn(ErrorKey::Upgrade).msg(msg).loc(overriding).push();
+                }
+                (None, None) => {
+                    if current.is_none() && !removed.contains_key(key.as_str()) {
+                        let what = "localization key".to_string();
+                        removed.insert(key.as_str(), Removed { what, renamed: None });
+                    }
+                }
+                (Some(_), None) => (),
+            }
+        }
+    }
+}
+
+/// Report the places in the mod's script that refer to removed vanilla items.
+fn check_references(new: &Everything, removed: &TigerHashMap<&str, Removed>) {
+    if removed.is_empty() {
+        return;
+    }
+    for entry in new.fileset.iter_mod_files() {
+        // Only `.txt` files, because `.gui` files have their own syntax and would get parse errors.
+        // The parser memory from loading is reused so that files parse the same way as then.
+        let is_script = entry.path().extension().is_some_and(|ext| ext == "txt");
+        if !is_script || entry.path().starts_with(new.localization.subpath()) {
+            continue;
+        }
+        if let Some(block) = PdxFile::read_optional_bom(entry, &new.parser) {
+            check_block_references(&block, removed);
+        }
+    }
+}
+
+fn check_block_references(block: &Block, removed: &TigerHashMap<&str, Removed>) {
+    for item in block.iter_items() {
+        match item {
+            BlockItem::Value(token) => check_token_reference(token, false, removed),
+            BlockItem::Block(block) => check_block_references(block, removed),
+            BlockItem::Field(Field(key, _, bv)) => {
+                check_token_reference(key, true, removed);
+                if let Some(token) = bv.get_value() {
+                    check_token_reference(token, false, removed);
+                } else if let Some(block) = bv.get_block() {
+                    check_block_references(block, removed);
+                }
+            }
+        }
+    }
+}
+
+/// Return the part of `token` that may name an item: the whole token if it's a plain name, or
+/// the target of a scope chain like `trait:brave` or `title:k_france.holder`.
+fn item_part(token: &Token) -> Option<&str> {
+    if let Some((prefix, target)) = token.as_str().split_once(':') {
+        // Saved scopes and variables are named by the mod, not items.
+        if prefix == "scope" || prefix.ends_with("var") {
+            return None;
+        }
+        target.split(['.', '|']).next()
+    } else if token.as_str().contains(['.', '|']) || token.as_str().parse::<f64>().is_ok() {
+        None
+    } else {
+        Some(token.as_str())
+    }
+}
+
+/// Report `token` if it names a removed item. Any plain token is matched, not just the ones in
+/// fields that take items, so these are only warnings. Plain keys are often the names of
+/// triggers and effects rather than items, so those are reported with less confidence.
+fn check_token_reference(token: &Token, is_key: bool, removed: &TigerHashMap<&str, Removed>) {
+    let Some(part) = item_part(token) else {
+        return;
+    };
+    if let Some(removed) = removed.get(part) {
+        let msg = format!("vanilla {} `{part}` was removed", removed.what);
+        let mut builder = warn(ErrorKey::Upgrade);
+        if is_key && part == token.as_str() {
+            builder = builder.weak();
+        }
+        let mut report = builder.msg(msg);
+        if let Some(renamed) = removed.renamed {
+            report = report.info(format!("it may have been renamed to `{renamed}`"));
+        }
+        report.loc(token).push();
+    }
+}
@@ -35,6 +35,8 @@ use crate::trigger::{
     validate_trigger_internal, warn_not_first, Part, PartFlags,
 };
 use crate::validator::Validator;
+#[cfg(feature = "jomini")]
+use crate::value_range::ValueRange;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ListType {
@@ -127,7 +129,7 @@ pub fn validate_mandatory_duration(block: &Block, vd: &mut Validator, sc: &mut S
     let mut count = 0;
 
     for field in &["days", "weeks", "months", "years"] {
-        if vd.field_script_value(field, sc) {
+        if vd.field_script_value_range(field, sc, 0.0, f64::INFINITY, Severity::Warning) {
             count += 1;
         }
     }
@@ -817,7 +819,29 @@ pub fn validate_scripted_modifier_calls(
 pub fn validate_ai_chance(bv: &BV, data: &Everything, sc: &mut ScopeContext) {
     match bv {
         BV::Value(t) => _ = t.expect_number(),
-        BV::Block(b) => validate_modifiers_with_base(b, data, sc),
+        BV::Block(b) => {
+            validate_modifiers_with_base(b, data, sc);
+            #[cfg(feature = "jomini")]
+            if Game::is_jomini() {
+                validate_ai_chance_not_zero(b, data);
+            }
+        }
+    }
+}
+
+/// Warn if an `ai_chance` block always comes out as zero even though it has modifiers.
+/// A block with only `base = 0` is fine, because then it's clearly deliberate.
+#[cfg(feature = "jomini")]
+fn validate_ai_chance_not_zero(block: &Block, data: &Everything) {
+    if block.iter_items().all(|item| item.get_field().is_some_and(|f| f.key().is("base"))) {
+        return;
+    }
+    if let Some(range) = ValueRange::from_modifiers_with_base(block, data) {
+        if range.is_exactly(0.0) {
+            let msg = "this `ai_chance` is always zero";
+            let info = "the AI will never choose this option, no matter which modifiers apply";
+            warn(ErrorKey::Logic).msg(msg).info(info).loc(block).push();
+        }
     }
 }
 
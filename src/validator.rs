@@ -18,7 +18,9 @@ use crate::report::fatal;
 use crate::report::{report, ErrorKey, Severity};
 use crate::scopes::Scopes;
 #[cfg(feature = "jomini")]
-use crate::script_value::{validate_script_value, validate_script_value_no_breakdown};
+use crate::script_value::{
+    validate_script_value, validate_script_value_no_breakdown, validate_script_value_range,
+};
 use crate::token::Token;
 use crate::tooltipped::Tooltipped;
 use crate::trigger::{validate_target, validate_target_ok_this, validate_trigger_internal};
@@ -1006,6 +1008,22 @@ impl<'a> Validator<'a> {
         })
     }
 
+    /// Just like [`Validator::field_script_value`], but also warn at `sev` if the script value can be shown to always be
+    /// outside of `min..=max`.
+    #[cfg(feature = "jomini")]
+    pub fn field_script_value_range(
+        &mut self,
+        name: &str,
+        sc: &mut ScopeContext,
+        min: f64,
+        max: f64,
+        sev: Severity,
+    ) -> bool {
+        self.field_check(name, |_, bv| {
+            validate_script_value_range(bv, self.data, sc, min, max, sev);
+        })
+    }
+
     /// Just like [`Validator::field_script_value`], but does not warn if it is an inline script value and the `desc` fields
     /// in it do not contain valid localizations. This is generally used for script values that will never be shown to
     /// the user except in debugging contexts, such as `ai_will_do`.
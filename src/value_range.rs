@@ -0,0 +1,509 @@
+//! Static evaluation of script values.
+//!
+//! Script values are calculated by the game at runtime, but often enough of them is known in
+//! advance to say something about the possible results. This module computes the range of values
+//! a script value can have, by following its `add`, `multiply`, `min`, `if`, etc. operations on
+//! intervals instead of on numbers.
+//!
+//! Anything that depends on the game state, such as triggers and variables, is treated as
+//! completely unknown, so the ranges computed here are always safe over-estimates.
+
+use std::fmt::{Display, Formatter};
+
+use crate::block::{Block, BlockItem, Comparator, Eq::*, Field, BV};
+use crate::everything::Everything;
+use crate::validate::ListType;
+
+/// Where named script values are looked up. This is `Everything`, except in the tests.
+pub trait ScriptValues {
+    fn get_script_value(&self, name: &str) -> Option<&BV>;
+}
+
+impl ScriptValues for Everything {
+    fn get_script_value(&self, name: &str) -> Option<&BV> {
+        self.script_values.get_bv(name)
+    }
+}
+
+/// How deeply to follow references to named script values before giving up.
+/// This also protects against script values that refer to themselves.
+const MAX_DEPTH: usize = 16;
+
+/// The range of values that a script value can have. Both ends are inclusive, and either end may
+/// be infinite.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ValueRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+impl ValueRange {
+    /// A range about which nothing is known.
+    pub const UNKNOWN: ValueRange = ValueRange { min: f64::NEG_INFINITY, max: f64::INFINITY };
+
+    pub fn exact(value: f64) -> Self {
+        Self { min: value, max: value }
+    }
+
+    /// Build a range from two bounds that may be in either order.
+    fn new(a: f64, b: f64) -> Self {
+        if a.is_nan() || b.is_nan() {
+            Self::UNKNOWN
+        } else {
+            Self { min: a.min(b), max: a.max(b) }
+        }
+    }
+
+    /// Build the smallest range that contains all the given values.
+    fn spanning(values: [f64; 4]) -> Self {
+        if values.iter().any(|v| v.is_nan()) {
+            return Self::UNKNOWN;
+        }
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        Self { min, max }
+    }
+
+    #[allow(clippy::float_cmp)] // Exact comparison is intended
+    pub fn is_exactly(self, value: f64) -> bool {
+        self.min == value && self.max == value
+    }
+
+    pub fn contains(self, value: f64) -> bool {
+        self.min <= value && value <= self.max
+    }
+
+    pub fn is_bounded(self) -> bool {
+        self.min.is_finite() && self.max.is_finite()
+    }
+
+    /// Return true iff zero is a possible value, and this is not just because nothing is known.
+    /// Zero has to be either one of the ends of the range, or the range has to be bounded.
+    pub fn can_be_zero(self) -> bool {
+        self.contains(0.0) && (self.is_bounded() || self.min == 0.0 || self.max == 0.0)
+    }
+
+    /// The smallest range that contains both `self` and `other`.
+    pub fn union(self, other: Self) -> Self {
+        Self { min: self.min.min(other.min), max: self.max.max(other.max) }
+    }
+
+    pub fn add(self, other: Self) -> Self {
+        Self::new(self.min + other.min, self.max + other.max)
+    }
+
+    pub fn subtract(self, other: Self) -> Self {
+        Self::new(self.min - other.max, self.max - other.min)
+    }
+
+    pub fn multiply(self, other: Self) -> Self {
+        // Multiplying zero by infinity should give zero here, because an infinite end just means
+        // "unbounded", and zero times any actual number is zero.
+        fn times(x: f64, y: f64) -> f64 {
+            if x == 0.0 || y == 0.0 {
+                0.0
+            } else {
+                x * y
+            }
+        }
+        Self::spanning([
+            times(self.min, other.min),
+            times(self.min, other.max),
+            times(self.max, other.min),
+            times(self.max, other.max),
+        ])
+    }
+
+    pub fn divide(self, other: Self) -> Self {
+        if other.contains(0.0) {
+            return Self::UNKNOWN;
+        }
+        Self::spanning([
+            self.min / other.min,
+            self.min / other.max,
+            self.max / other.min,
+            self.max / other.max,
+        ])
+    }
+
+    pub fn modulo(self, other: Self) -> Self {
+        if other.contains(0.0) {
+            return Self::UNKNOWN;
+        }
+        let limit = other.min.abs().max(other.max.abs());
+        let low = if self.min >= 0.0 { 0.0 } else { -limit };
+        let high = if self.max <= 0.0 { 0.0 } else { limit };
+        Self::new(low, high)
+    }
+
+    /// The result of the script value operation `min = other`, which raises the value to at least
+    /// `other`.
+    pub fn at_least(self, other: Self) -> Self {
+        Self { min: self.min.max(other.min), max: self.max.max(other.max) }
+    }
+
+    /// The result of the script value operation `max = other`, which lowers the value to at most
+    /// `other`.
+    pub fn at_most(self, other: Self) -> Self {
+        Self { min: self.min.min(other.min), max: self.max.min(other.max) }
+    }
+
+    pub fn abs(self) -> Self {
+        if self.min >= 0.0 {
+            self
+        } else if self.max <= 0.0 {
+            Self { min: -self.max, max: -self.min }
+        } else {
+            Self { min: 0.0, max: self.max.max(-self.min) }
+        }
+    }
+
+    /// Apply a function that never decreases when its argument increases, such as `floor`.
+    fn monotonic(self, f: fn(f64) -> f64) -> Self {
+        Self { min: f(self.min), max: f(self.max) }
+    }
+
+    /// The result of rounding to a multiple of `other`, which can move the value by at most
+    /// `other` in either direction.
+    fn round_to(self, other: Self) -> Self {
+        let step = other.min.abs().max(other.max.abs());
+        Self::new(self.min - step, self.max + step)
+    }
+
+    /// Compute the range of a script value, which may be a literal, the name of a script value,
+    /// a `{ min max }` range, or a calculation block.
+    pub fn from_script_value(bv: &BV, data: &dyn ScriptValues) -> Self {
+        Self::from_bv(bv, data, 0)
+    }
+
+    /// Compute the range of a `base`/`add`/`factor`/`modifier` block such as `ai_chance`.
+    /// Returns `None` if there is no `base`, because then the starting value isn't known.
+    pub fn from_modifiers_with_base(block: &Block, data: &dyn ScriptValues) -> Option<Self> {
+        let base = block.get_field("base")?;
+        let mut range = Self::from_bv(base, data, 0);
+        for item in block.iter_items() {
+            if let BlockItem::Field(Field(key, _, bv)) = item {
+                if key.is("base") || key.is("desc") {
+                    continue;
+                }
+                range = if key.is("modifier") {
+                    match bv {
+                        BV::Block(block) => range.union(eval_modifier(range, block, data)),
+                        BV::Value(_) => Self::UNKNOWN,
+                    }
+                } else {
+                    eval_weight_op(key.as_str(), range, bv, data).unwrap_or(Self::UNKNOWN)
+                };
+            }
+        }
+        Some(range)
+    }
+
+    fn from_bv(bv: &BV, data: &dyn ScriptValues, depth: usize) -> Self {
+        match bv {
+            BV::Value(token) => {
+                if let Some(value) = token.get_number() {
+                    Self::exact(value)
+                } else if depth < MAX_DEPTH {
+                    data.get_script_value(token.as_str())
+                        .map_or(Self::UNKNOWN, |bv| Self::from_bv(bv, data, depth + 1))
+                } else {
+                    Self::UNKNOWN
+                }
+            }
+            BV::Block(block) => {
+                if matches!(
+                    block.iter_items().next(),
+                    Some(BlockItem::Block(_) | BlockItem::Value(_))
+                ) {
+                    // It's a range like { 1 5 }
+                    let mut values = block.iter_items().filter_map(|item| match item {
+                        BlockItem::Value(token) => {
+                            Some(Self::from_bv(&BV::Value(token.clone()), data, depth))
+                        }
+                        _ => None,
+                    });
+                    match (values.next(), values.next(), values.next()) {
+                        (Some(low), Some(high), None) => Self::new(low.min, high.max),
+                        _ => Self::UNKNOWN,
+                    }
+                } else {
+                    eval_block(Self::exact(0.0), block, data, depth)
+                }
+            }
+        }
+    }
+}
+
+impl Display for ValueRange {
+    #[allow(clippy::float_cmp)] // Exact comparison is intended
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        if self.min == self.max {
+            write!(f, "{}", self.min)
+        } else {
+            write!(f, "{} to {}", self.min, self.max)
+        }
+    }
+}
+
+/// Evaluate the operations in a script value calculation block, starting from `range`.
+fn eval_block(
+    mut range: ValueRange,
+    block: &Block,
+    data: &dyn ScriptValues,
+    depth: usize,
+) -> ValueRange {
+    // The range before the current `if` / `else_if` / `else` sequence started, and the union of
+    // the results of its branches so far.
+    let mut ifelse: Option<(ValueRange, ValueRange)> = None;
+
+    for item in block.iter_items() {
+        let BlockItem::Field(Field(key, cmp, bv)) = item else {
+            return ValueRange::UNKNOWN;
+        };
+        let key_str = key.as_str();
+
+        if key_str == "else_if" || key_str == "else" {
+            if let (Some((start, branches)), BV::Block(block)) = (ifelse, bv) {
+                let branches = branches.union(eval_block(start, block, data, depth));
+                if key_str == "else" && !block.has_key("limit") {
+                    range = branches;
+                    ifelse = None;
+                } else {
+                    range = branches.union(start);
+                    ifelse = Some((start, branches));
+                }
+                continue;
+            }
+        }
+        ifelse = None;
+
+        range = match key_str {
+            // The `limit` of an `if` is a trigger, which doesn't change the value. Whether it's
+            // true is unknown, which the `if` handles.
+            "limit" | "desc" | "format" | "save_temporary_scope_as" | "save_temporary_value_as" => {
+                range
+            }
+            "value" => ValueRange::from_bv(bv, data, depth),
+            "add" => range.add(ValueRange::from_bv(bv, data, depth)),
+            "subtract" => range.subtract(ValueRange::from_bv(bv, data, depth)),
+            "multiply" => range.multiply(ValueRange::from_bv(bv, data, depth)),
+            "divide" => range.divide(ValueRange::from_bv(bv, data, depth)),
+            "modulo" => range.modulo(ValueRange::from_bv(bv, data, depth)),
+            "min" => range.at_least(ValueRange::from_bv(bv, data, depth)),
+            "max" => range.at_most(ValueRange::from_bv(bv, data, depth)),
+            "round_to" => range.round_to(ValueRange::from_bv(bv, data, depth)),
+            "round" | "ceiling" | "floor" | "abs" => {
+                if bv.get_value().is_some_and(|v| v.is("no")) {
+                    range
+                } else {
+                    match key_str {
+                        "round" => range.monotonic(f64::round),
+                        "ceiling" => range.monotonic(f64::ceil),
+                        "floor" => range.monotonic(f64::floor),
+                        _ => range.abs(),
+                    }
+                }
+            }
+            "fixed_range" | "integer_range" => match bv {
+                BV::Block(block) => {
+                    let min = block.get_field("min").map(|bv| ValueRange::from_bv(bv, data, depth));
+                    let max = block.get_field("max").map(|bv| ValueRange::from_bv(bv, data, depth));
+                    match (min, max) {
+                        (Some(min), Some(max)) => ValueRange::new(min.min, max.max),
+                        _ => ValueRange::UNKNOWN,
+                    }
+                }
+                BV::Value(_) => ValueRange::UNKNOWN,
+            },
+            "if" | "else_if" | "else" => match bv {
+                BV::Block(block) => {
+                    let branch = eval_block(range, block, data, depth);
+                    ifelse = Some((range, branch));
+                    range.union(branch)
+                }
+                BV::Value(_) => ValueRange::UNKNOWN,
+            },
+            "switch" => match bv {
+                BV::Block(block) => {
+                    let mut result = None;
+                    let mut has_fallback = false;
+                    for (key, block) in block.iter_definitions() {
+                        has_fallback |= key.is("fallback");
+                        let branch = eval_block(range, block, data, depth);
+                        result = Some(result.map_or(branch, |r: ValueRange| r.union(branch)));
+                    }
+                    match result {
+                        Some(result) if has_fallback => result,
+                        Some(result) => result.union(range),
+                        None => range,
+                    }
+                }
+                BV::Value(_) => ValueRange::UNKNOWN,
+            },
+            _ => {
+                if let Some((it_type, _)) = key.split_once('_') {
+                    if ListType::try_from(it_type.as_str()).is_ok() {
+                        // An iterator can run any number of times
+                        return ValueRange::UNKNOWN;
+                    }
+                }
+                match bv {
+                    // A `target = { ... }` block. The target might not exist, which is
+                    // always the case to consider with `?=`, so include the unchanged value.
+                    BV::Block(block) => {
+                        let branch = eval_block(range, block, data, depth);
+                        if matches!(cmp, Comparator::Equals(Single | Question)) {
+                            range.union(branch)
+                        } else {
+                            ValueRange::UNKNOWN
+                        }
+                    }
+                    BV::Value(_) => ValueRange::UNKNOWN,
+                }
+            }
+        };
+    }
+    range
+}
+
+/// Evaluate a `modifier = { ... }` block inside an `ai_chance` or similar weight block, for the
+/// case where its triggers are true.
+fn eval_modifier(mut range: ValueRange, block: &Block, data: &dyn ScriptValues) -> ValueRange {
+    for item in block.iter_items() {
+        if let BlockItem::Field(Field(key, _, bv)) = item {
+            if key.is("add") || key.is("factor") {
+                range = eval_weight_op(key.as_str(), range, bv, data).unwrap_or(range);
+            }
+            // Everything else is either a trigger or a `desc`, which don't change the value.
+        }
+    }
+    range
+}
+
+/// Apply one of the value-changing fields of a weight block to `range`.
+/// Returns `None` if the field is not one of those.
+fn eval_weight_op(
+    key: &str,
+    range: ValueRange,
+    bv: &BV,
+    data: &dyn ScriptValues,
+) -> Option<ValueRange> {
+    let value = || ValueRange::from_bv(bv, data, 0);
+    match key {
+        "add" => Some(range.add(value())),
+        "factor" => Some(range.multiply(value())),
+        "min" => Some(range.at_least(value())),
+        "max" => Some(range.at_most(value())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::TigerHashMap;
+    use crate::parse::pdxfile::parse_pdx_internal;
+
+    #[derive(Default)]
+    struct Values(TigerHashMap<String, BV>);
+
+    impl ScriptValues for Values {
+        fn get_script_value(&self, name: &str) -> Option<&BV> {
+            self.0.get(name)
+        }
+    }
+
+    fn range(min: f64, max: f64) -> ValueRange {
+        ValueRange { min, max }
+    }
+
+    /// Evaluate the script value `test_value` in `input`, with the other fields as named
+    /// script values.
+    fn eval(input: &'static str) -> ValueRange {
+        let block = parse_pdx_internal(input, "test");
+        let mut values = Values::default();
+        for (key, bv) in block.iter_assignments_and_definitions() {
+            values.0.insert(key.to_string(), bv.clone());
+        }
+        ValueRange::from_script_value(&values.0["test_value"], &values)
+    }
+
+    #[test]
+    fn test_operations() {
+        let unknown = ValueRange::UNKNOWN;
+        assert_eq!(range(1.0, 2.0).add(range(10.0, 20.0)), range(11.0, 22.0));
+        assert_eq!(range(1.0, 2.0).subtract(range(10.0, 20.0)), range(-19.0, -8.0));
+        assert_eq!(range(-1.0, 2.0).multiply(range(3.0, 4.0)), range(-4.0, 8.0));
+        assert_eq!(ValueRange::exact(0.0).multiply(unknown), ValueRange::exact(0.0));
+        assert_eq!(range(0.0, 1.0).multiply(range(2.0, f64::INFINITY)), range(0.0, f64::INFINITY));
+        assert_eq!(range(2.0, 4.0).divide(range(1.0, 2.0)), range(1.0, 4.0));
+        assert_eq!(range(2.0, 4.0).divide(range(-1.0, 1.0)), unknown);
+        assert_eq!(range(0.0, 100.0).modulo(ValueRange::exact(7.0)), range(0.0, 7.0));
+        assert_eq!(range(1.0, 2.0).modulo(range(0.0, 1.0)), unknown);
+        assert_eq!(range(-5.0, 5.0).at_least(ValueRange::exact(0.0)), range(0.0, 5.0));
+        assert_eq!(range(-5.0, 5.0).at_most(ValueRange::exact(0.0)), range(-5.0, 0.0));
+        assert_eq!(range(-5.0, 3.0).abs(), range(0.0, 5.0));
+        assert_eq!(range(-5.0, -3.0).abs(), range(3.0, 5.0));
+        assert_eq!(range(1.0, 2.0).union(range(5.0, 6.0)), range(1.0, 6.0));
+
+        assert!(range(-1.0, 1.0).can_be_zero());
+        assert!(range(0.0, f64::INFINITY).can_be_zero());
+        assert!(!unknown.can_be_zero());
+        assert!(!range(1.0, 2.0).can_be_zero());
+    }
+
+    #[test]
+    fn test_script_values() {
+        assert_eq!(eval("test_value = 5"), ValueRange::exact(5.0));
+        assert_eq!(eval("test_value = { 1 5 }"), range(1.0, 5.0));
+        assert_eq!(
+            eval("test_value = { value = 2 multiply = 3 add = -1 }"),
+            ValueRange::exact(5.0)
+        );
+        assert_eq!(
+            eval("base = 4 test_value = { value = base divide = 2 }"),
+            ValueRange::exact(2.0)
+        );
+        assert_eq!(eval("test_value = { value = 5 min = 10 max = 20 }"), ValueRange::exact(10.0));
+        assert_eq!(eval("test_value = { value = 10 divide = { 0 2 } }"), ValueRange::UNKNOWN);
+        // A value that refers to itself is cut off instead of looping forever.
+        assert_eq!(eval("test_value = { value = test_value add = 1 }"), ValueRange::UNKNOWN);
+        // Anything that depends on the game state is unknown.
+        assert_eq!(eval("test_value = { value = gold }"), ValueRange::UNKNOWN);
+        assert_eq!(
+            eval("test_value = { value = 0 every_vassal = { add = 1 } }"),
+            ValueRange::UNKNOWN
+        );
+    }
+
+    #[test]
+    fn test_if_limit() {
+        // The `limit` can be true or false, so both outcomes are possible.
+        assert_eq!(
+            eval("test_value = { value = 5 if = { limit = { gold > 10 } add = 10 } }"),
+            range(5.0, 15.0)
+        );
+        // A final `else` makes sure that one of the branches is taken.
+        assert_eq!(
+            eval(
+                "test_value = { value = 5 if = { limit = { gold > 10 } add = 1 } \
+                 else = { add = 2 } }"
+            ),
+            range(6.0, 7.0)
+        );
+        assert_eq!(
+            eval(
+                "test_value = { value = 5 if = { limit = { gold > 10 } add = 1 } \
+                 else_if = { limit = { gold > 5 } add = 2 } }"
+            ),
+            range(5.0, 7.0)
+        );
+        assert_eq!(
+            eval("test_value = { value = 1 switch = { trigger = gold 1 = { add = 1 } fallback = { add = 2 } } }"),
+            range(2.0, 3.0)
+        );
+        // A target that might not exist leaves the value unchanged.
+        assert_eq!(eval("test_value = { value = 1 liege ?= { add = 1 } }"), range(1.0, 2.0));
+    }
+}
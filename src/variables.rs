@@ -102,6 +102,18 @@ impl Variables {
         false
     }
 
+    /// Just like `variable_exists` but for lists.
+    #[allow(dead_code)]
+    pub fn list_exists(&self, name: &str) -> bool {
+        if self.lists.contains(name) {
+            return true;
+        }
+        if let Some(prefix) = remove_suffix_tag(name) {
+            return self.list_prefixes.contains(prefix);
+        }
+        false
+    }
+
     /// Check if a variable name has been previously registered.
     /// This takes a bare variable name that did not have an `@` suffix (in Hoi4).
     #[allow(dead_code)]
@@ -189,8 +201,9 @@ fn remove_suffix_tag(name: &str) -> Option<&str> {
         .then(|| &name[..name.len() - 3])
 }
 
+/// How to find the variable or flag name in the arguments of an effect or trigger.
 #[derive(Debug, Clone, Copy)]
-enum Extract {
+pub(crate) enum Extract {
     Field(&'static str),
     AssignOrField(&'static str),
     InternalAssignOrField(&'static str),
@@ -198,31 +211,35 @@ enum Extract {
 
 impl Extract {
     pub fn extract(&self, bv: &BV) -> Option<&'static str> {
+        self.extract_token(bv).map(Token::as_str)
+    }
+
+    pub fn extract_token<'a>(&self, bv: &'a BV) -> Option<&'a Token> {
         match self {
             Self::Field(field) => {
                 if let Some(block) = bv.get_block() {
                     if let Some(name) = block.get_field_value(field) {
-                        return Some(name.as_str());
+                        return Some(name);
                     }
                 }
             }
             Self::AssignOrField(field) => match bv {
                 BV::Value(name) => {
-                    return Some(name.as_str());
+                    return Some(name);
                 }
                 BV::Block(block) => {
                     if let Some(name) = block.get_field_value(field) {
-                        return Some(name.as_str());
+                        return Some(name);
                     }
                 }
             },
             Self::InternalAssignOrField(field) => {
                 if let Some(block) = bv.get_block() {
                     if let Some(name) = block.get_field_value(field) {
-                        return Some(name.as_str());
+                        return Some(name);
                     } else if block.num_items() == 1 {
                         if let Some((name, _)) = block.iter_assignments().next() {
-                            return Some(name.as_str());
+                            return Some(name);
                         }
                     }
                 }
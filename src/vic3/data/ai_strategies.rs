@@ -4,9 +4,9 @@ use crate::db::{Db, DbKind};
 use crate::everything::Everything;
 use crate::game::GameFlags;
 use crate::item::{Item, ItemLoader};
-use crate::report::{warn, ErrorKey};
+use crate::report::{warn, ErrorKey, Severity};
 use crate::scopes::Scopes;
-use crate::script_value::validate_script_value;
+use crate::script_value::{validate_script_value, validate_script_value_range};
 use crate::token::Token;
 use crate::tooltipped::Tooltipped;
 use crate::validator::Validator;
@@ -89,7 +89,7 @@ impl DbKind for AiStrategy {
         vd.field_validated_key("wargoal_maneuvers_fraction", |key, bv, data| {
             let mut sc = ScopeContext::new(Scopes::Country, key);
             sc.define_name("enemy_country", Scopes::Country, key);
-            validate_script_value(bv, data, &mut sc);
+            validate_script_value_range(bv, data, &mut sc, 0.0, 1.0, Severity::Warning);
         });
         vd.field_script_value_rooted("change_law_chance", Scopes::Country);
 
@@ -5,13 +5,13 @@ use crate::everything::Everything;
 use crate::game::GameFlags;
 use crate::item::{Item, ItemLoader};
 use crate::modif::{validate_modifs, ModifKinds};
-use crate::report::{err, ErrorKey};
+use crate::report::{err, warn, ErrorKey};
 use crate::scopes::Scopes;
 use crate::script_value::validate_non_dynamic_script_value;
 use crate::token::Token;
 use crate::tooltipped::Tooltipped;
 use crate::validator::Validator;
-use crate::vic3::data::production_methods::ProductionMethodGroup;
+use crate::vic3::data::production_methods::{ProductionMethod, ProductionMethodGroup};
 
 #[derive(Clone, Debug)]
 pub struct BuildingType {}
@@ -167,6 +167,20 @@ impl DbKind for BuildingType {
 
         // docs say production_methods
         vd.field_list_items("production_method_groups", Item::ProductionMethodGroup);
+        for group in block.get_field_list("production_method_groups").unwrap_or_default() {
+            let Some((_, block)) = data.get_key_block(Item::ProductionMethodGroup, group.as_str())
+            else {
+                continue;
+            };
+            let pms = block.get_field_list("production_methods").unwrap_or_default();
+            if !pms.is_empty()
+                && !pms.iter().any(|pm| ProductionMethod::is_unlockable(pm.as_str(), data))
+            {
+                let msg = format!("every production method in `{group}` needs a technology that is missing or can't be researched");
+                let info = "the building can't be used unless the technology is granted by script";
+                warn(ErrorKey::Validation).msg(msg).info(info).loc(group).push();
+            }
+        }
 
         vd.field_trigger("should_auto_expand", Tooltipped::Yes, &mut building_sc);
 
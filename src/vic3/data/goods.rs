@@ -1,8 +1,10 @@
-use crate::block::Block;
+use crate::block::{Block, Field, BV};
 use crate::db::{Db, DbKind};
 use crate::everything::Everything;
 use crate::game::GameFlags;
+use crate::helpers::TigerHashSet;
 use crate::item::{Item, ItemLoader};
+use crate::report::{warn, ErrorKey};
 use crate::token::Token;
 use crate::validator::Validator;
 
@@ -17,6 +19,91 @@ impl Goods {
     pub fn add(db: &mut Db, key: Token, block: Block) {
         db.add(Item::Goods, key, block, Box::new(Self {}));
     }
+
+    /// Check that every good is both produced and consumed somewhere in the economy.
+    /// Only production methods that some building can use are counted.
+    pub fn crosscheck(data: &Everything) {
+        let mut produced = TigerHashSet::default();
+        let mut consumed = TigerHashSet::default();
+
+        for (_, block) in data.database.iter_key_block(Item::BuildingType) {
+            for group in block.get_field_list("production_method_groups").unwrap_or_default() {
+                let Some((_, block)) =
+                    data.get_key_block(Item::ProductionMethodGroup, group.as_str())
+                else {
+                    continue;
+                };
+                for pm in block.get_field_list("production_methods").unwrap_or_default() {
+                    if let Some(block) = data
+                        .get_key_block(Item::ProductionMethod, pm.as_str())
+                        .and_then(|(_, block)| block.get_field_block("building_modifiers"))
+                    {
+                        scan_goods_modifiers(block, &mut produced, &mut consumed);
+                    }
+                }
+            }
+        }
+        // Military upkeep is the other way goods get used up by modifiers
+        for itype in [Item::CombatUnit, Item::MobilizationOption] {
+            for (_, block) in data.database.iter_key_block(itype) {
+                scan_goods_modifiers(block, &mut TigerHashSet::default(), &mut consumed);
+            }
+        }
+        for (_, block) in data.database.iter_key_block(Item::PopNeed) {
+            if let Some(goods) = block.get_field_value("default") {
+                consumed.insert(goods.as_str());
+            }
+            for block in block.get_field_blocks("entry") {
+                if let Some(goods) = block.get_field_value("goods") {
+                    consumed.insert(goods.as_str());
+                }
+            }
+        }
+
+        for key in data.database.iter_keys(Item::Goods) {
+            let msg = match (produced.contains(key.as_str()), consumed.contains(key.as_str())) {
+                (true, true) => continue,
+                (false, false) => format!("goods `{key}` are neither produced nor consumed"),
+                (false, true) => format!("goods `{key}` are consumed but never produced"),
+                (true, false) => format!("goods `{key}` are produced but never consumed"),
+            };
+            let info = "only the production methods of buildings, military upkeep, and pop needs are counted";
+            warn(ErrorKey::Validation).msg(msg).info(info).loc(key).push();
+        }
+    }
+}
+
+/// Record the goods used by `goods_input_` and `goods_output_` modifiers in this block,
+/// including the obsolete `building_input_` and `building_output_` forms.
+fn scan_goods_modifiers<'a>(
+    block: &'a Block,
+    produced: &mut TigerHashSet<&'a str>,
+    consumed: &mut TigerHashSet<&'a str>,
+) {
+    for Field(key, _, bv) in block.iter_fields() {
+        match bv {
+            BV::Block(block) => scan_goods_modifiers(block, produced, consumed),
+            BV::Value(value) => {
+                if !value.get_number().is_some_and(|n| n > 0.0) {
+                    continue;
+                }
+                let Some(name) = key.as_str().strip_suffix("_add") else {
+                    continue;
+                };
+                if let Some(goods) = name
+                    .strip_prefix("goods_input_")
+                    .or_else(|| name.strip_prefix("building_input_"))
+                {
+                    consumed.insert(goods);
+                } else if let Some(goods) = name
+                    .strip_prefix("goods_output_")
+                    .or_else(|| name.strip_prefix("building_output_"))
+                {
+                    produced.insert(goods);
+                }
+            }
+        }
+    }
 }
 
 impl DbKind for Goods {
@@ -51,3 +138,33 @@ impl DbKind for Goods {
         vd.field_bool("pop_consumption_can_add_infrastructure"); // undocumented
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::report::take_reports_for;
+
+    #[test]
+    fn test_crosscheck() {
+        let mod_root = PathBuf::from("tests/files/vic3");
+        let mut data = Everything::new(None, None, &mod_root, Vec::new()).unwrap();
+        data.load_all();
+        let goods = "common/goods/goods.txt";
+        take_reports_for(goods);
+
+        Goods::crosscheck(&data);
+        let mut msgs: Vec<_> =
+            take_reports_for(goods).into_iter().map(|report| report.msg).collect();
+        msgs.sort();
+        assert_eq!(
+            msgs,
+            [
+                "goods `fabric` are produced but never consumed",
+                "goods `tools` are consumed but never produced",
+                "goods `wine` are neither produced nor consumed",
+            ]
+        );
+    }
+}
@@ -1,17 +1,20 @@
 use std::path::PathBuf;
 
-use crate::block::Block;
+use crate::block::{Block, Field};
 use crate::context::ScopeContext;
 use crate::effect::validate_effect;
 use crate::everything::Everything;
 use crate::fileset::{FileEntry, FileHandler};
-use crate::helpers::TigerHashMap;
+use crate::helpers::{TigerHashMap, TigerHashSet};
+use crate::item::Item;
 use crate::parse::ParserMemory;
 use crate::pdxfile::PdxFile;
+use crate::report::{err, ErrorKey};
 use crate::scopes::Scopes;
 use crate::token::Token;
 use crate::tooltipped::Tooltipped;
 use crate::variables::Variables;
+use crate::vic3::data::state_regions::StateRegion;
 
 /// The history files in Vic3 are fairly simple. Files under `common/history/` have `keyword = { effect... }` as top-level blocks,
 /// where the effects from the same keywords are all added together. The keywords seem to be arbitrary, except for GLOBAL which
@@ -37,7 +40,8 @@ impl History {
         }
     }
 
-    pub fn validate(&self, data: &Everything) {
+    /// Return the history effects in the order they are assumed to be executed.
+    fn ordered(&self) -> Vec<&HistoryEffect> {
         // Don't know what order the history effects are executed in, so let's do alphabetic
         // except for `GLOBAL`, which is documented to go last.
         let mut names: Vec<&'static str> =
@@ -45,14 +49,226 @@ impl History {
         names.sort_unstable();
         names.push("GLOBAL");
 
-        for name in names {
-            if let Some(item) = self.history.get(name) {
-                item.validate(data);
+        names.into_iter().filter_map(|name| self.history.get(name)).collect()
+    }
+
+    pub fn validate(&self, data: &Everything) {
+        for item in self.ordered() {
+            item.validate(data);
+        }
+    }
+
+    /// Check that the states, pops, buildings, and capitals that the history sets up at game
+    /// start fit together and fit the state regions.
+    pub fn crosscheck(&self, data: &Everything) {
+        let mut start = StartState::default();
+        for item in self.ordered() {
+            start.scan(&item.block);
+        }
+        // Report in file order, so that the same one of two conflicting entries is blamed
+        // every run.
+        start.states.sort_by_key(|(region, _, _)| region.loc);
+        start.buildings.sort_by_key(|(region, _, _)| region.loc);
+        start.check(data);
+    }
+}
+
+/// What the history files set up for each state region, as far as can be seen without running
+/// the effects.
+#[derive(Debug, Default)]
+struct StartState {
+    /// (state region, owning country, `create_state` block)
+    states: Vec<(Token, Token, Block)>,
+    /// (state region, country) pairs that get pops
+    pops: TigerHashSet<(Token, Token)>,
+    /// (state region, building type, levels)
+    buildings: Vec<(Token, Token, i64)>,
+    /// The capitals set by `set_capital` in the country history, by country tag.
+    /// Later ones replace earlier ones.
+    capitals: TigerHashMap<&'static str, Token>,
+}
+
+impl StartState {
+    fn scan(&mut self, block: &Block) {
+        for Field(key, _, bv) in block.iter_fields() {
+            if let (Some(country), Some(block)) = (key.strip_prefix("c:"), bv.get_block()) {
+                if let Some(capital) = block.get_field_value("set_capital") {
+                    self.capitals.insert(country.as_str(), capital.clone());
+                }
+                continue;
+            }
+            let (Some(region), Some(block)) = (key.strip_prefix("s:"), bv.get_block()) else {
+                continue;
+            };
+            for Field(key, _, bv) in block.iter_fields() {
+                let Some(block) = bv.get_block() else {
+                    continue;
+                };
+                if key.is("create_state") {
+                    if let Some(country) =
+                        block.get_field_value("country").and_then(|c| c.strip_prefix("c:"))
+                    {
+                        self.states.push((region.clone(), country, block.clone()));
+                    }
+                } else if let Some(country) = key.strip_prefix("region_state:") {
+                    for block in block.get_field_blocks("create_building") {
+                        if let Some(building) = block.get_field_value("building") {
+                            let levels = block.get_field_integer("level").unwrap_or_else(|| {
+                                block.get_field_block("add_ownership").map_or(0, |block| {
+                                    block
+                                        .iter_fields()
+                                        .filter_map(|Field(_, _, bv)| bv.get_block())
+                                        .filter_map(|block| block.get_field_integer("levels"))
+                                        .sum()
+                                })
+                            });
+                            self.buildings.push((region.clone(), building.clone(), levels));
+                        }
+                    }
+                    if block.has_key("create_pop") {
+                        self.pops.insert((region.clone(), country));
+                    }
+                }
+            }
+        }
+    }
+
+    fn check(&self, data: &Everything) {
+        self.check_provinces(data);
+
+        // Each state needs pops
+        let mut owned: TigerHashMap<&str, Vec<&str>> = TigerHashMap::default();
+        for (region, country, block) in &self.states {
+            owned.entry(country.as_str()).or_default().push(region.as_str());
+            if !self.pops.contains(&(region.clone(), country.clone())) {
+                let msg = format!("state in {region} owned by {country} has no pops");
+                let info = "states without pops can crash the game";
+                let loc = block.get_field_value("country").unwrap_or(region);
+                err(ErrorKey::History).strong().msg(msg).info(info).loc(loc).push();
+            }
+        }
+
+        // Each country that owns states at the start needs its capital to be one of them.
+        // The capital from the country history takes precedence over the country definition.
+        for (country, block) in data.database.iter_key_block(Item::Country) {
+            let capital =
+                self.capitals.get(country.as_str()).or_else(|| block.get_field_value("capital"));
+            if let (Some(regions), Some(capital)) = (owned.get(country.as_str()), capital) {
+                if !regions.contains(&capital.as_str()) {
+                    let msg = format!("{country} does not own a state in its capital {capital}");
+                    err(ErrorKey::History).msg(msg).loc(capital).push();
+                }
+            }
+        }
+
+        self.check_building_caps(data);
+    }
+
+    /// Check that the state splits only own provinces of their state region, and that no
+    /// province is owned twice.
+    fn check_provinces(&self, data: &Everything) {
+        let mut owners: TigerHashMap<String, &Token> = TigerHashMap::default();
+        for (region, _, block) in &self.states {
+            let region_provinces: TigerHashSet<String> = data
+                .get_key_block(Item::StateRegion, region.as_str())
+                .and_then(|(_, block)| block.get_field_list("provinces"))
+                .unwrap_or_default()
+                .iter()
+                .map(|p| StateRegion::province_key(p.as_str()))
+                .collect();
+            if region_provinces.is_empty() {
+                // Missing state regions are reported by the effect validation
+                continue;
+            }
+            for province in block.get_field_list("owned_provinces").unwrap_or_default() {
+                let id = StateRegion::province_key(province.as_str());
+                if !region_provinces.contains(&id) {
+                    let msg = format!("province {province} is not part of {region}");
+                    err(ErrorKey::History).msg(msg).loc(&province).push();
+                }
+                if let Some(other) = owners.get(&id) {
+                    let msg = format!("province {province} is owned by more than one state");
+                    err(ErrorKey::History)
+                        .msg(msg)
+                        .loc(&province)
+                        .loc_msg(*other, "other owner")
+                        .push();
+                } else if let Some(country) = block.get_field_value("country") {
+                    owners.insert(id, country);
+                }
+            }
+        }
+    }
+
+    /// Check that the buildings in each state region fit in its arable land and resource caps.
+    fn check_building_caps(&self, data: &Everything) {
+        // (state region, building group) -> levels used so far
+        let mut used: TigerHashMap<(&str, &str), i64> = TigerHashMap::default();
+        for (region, building, levels) in &self.buildings {
+            let Some((_, region_block)) = data.get_key_block(Item::StateRegion, region.as_str())
+            else {
+                continue;
+            };
+            let groups = building_groups(building, data);
+            let arable = region_block.get_field_list("arable_resources").unwrap_or_default();
+            let mut caps = Vec::new();
+            if groups.iter().any(|group| arable.iter().any(|a| a.is(group))) {
+                if let Some(cap) = region_block.get_field_integer("arable_land") {
+                    caps.push(("arable land", cap));
+                }
+            }
+            if let Some(block) = region_block.get_field_block("capped_resources") {
+                for Field(key, _, bv) in block.iter_fields() {
+                    if groups.contains(&key.as_str()) {
+                        if let Some(cap) = bv.get_value().and_then(Token::get_integer) {
+                            caps.push((key.as_str(), cap));
+                        }
+                    }
+                }
+            }
+            for block in region_block.get_field_blocks("resource") {
+                if let Some(group) = block.get_field_value("type") {
+                    if groups.contains(&group.as_str()) {
+                        let cap = block.get_field_integer("discovered_amount").unwrap_or(0);
+                        caps.push((group.as_str(), cap));
+                    }
+                }
+            }
+
+            for (what, cap) in caps {
+                let total = used.entry((region.as_str(), what)).or_default();
+                let before = *total;
+                *total += levels;
+                if before <= cap && *total > cap {
+                    let msg = format!(
+                        "buildings in {region} use {total} levels of {what}, but it only has {cap}"
+                    );
+                    err(ErrorKey::History).msg(msg).loc(building).push();
+                }
             }
         }
     }
 }
 
+/// Return the building group of this building type and all the groups it's part of.
+fn building_groups<'a>(building: &Token, data: &'a Everything) -> Vec<&'a str> {
+    let mut groups = Vec::new();
+    let mut next = data
+        .get_key_block(Item::BuildingType, building.as_str())
+        .and_then(|(_, block)| block.get_field_value("building_group"));
+    while let Some(group) = next {
+        if groups.contains(&group.as_str()) {
+            // Loops are reported elsewhere
+            break;
+        }
+        groups.push(group.as_str());
+        next = data
+            .get_key_block(Item::BuildingGroup, group.as_str())
+            .and_then(|(_, block)| block.get_field_value("parent_group"));
+    }
+    groups
+}
+
 impl FileHandler<Block> for History {
     fn subpath(&self) -> PathBuf {
         PathBuf::from("common/history/")
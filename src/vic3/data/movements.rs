@@ -4,8 +4,9 @@ use crate::db::{Db, DbKind};
 use crate::everything::Everything;
 use crate::game::GameFlags;
 use crate::item::{Item, ItemLoader};
+use crate::report::Severity;
 use crate::scopes::Scopes;
-use crate::script_value::validate_script_value;
+use crate::script_value::{validate_script_value, validate_script_value_range};
 use crate::token::Token;
 use crate::tooltipped::Tooltipped;
 use crate::validator::Validator;
@@ -128,7 +129,7 @@ impl DbKind for PoliticalMovement {
                 vd.field_validated_key("target_fraction_of_states", |key, bv, data| {
                     let mut sc = ScopeContext::new(Scopes::PoliticalMovement, key);
                     sc.define_name("clout", Scopes::Value, key);
-                    validate_script_value(bv, data, &mut sc);
+                    validate_script_value_range(bv, data, &mut sc, 0.0, 1.0, Severity::Warning);
                 });
             });
         }
@@ -4,6 +4,7 @@ use crate::everything::Everything;
 use crate::game::GameFlags;
 use crate::item::{Item, ItemLoader};
 use crate::modif::{validate_modifs, ModifKinds};
+use crate::report::{tips, ErrorKey};
 use crate::token::Token;
 use crate::validator::Validator;
 
@@ -18,6 +19,22 @@ impl ProductionMethod {
     pub fn add(db: &mut Db, key: Token, block: Block) {
         db.add(Item::ProductionMethod, key, block, Box::new(Self {}));
     }
+
+    /// Return false if the production method needs technologies and none of them can be
+    /// researched. It might still be granted by script, though.
+    pub fn is_unlockable(pm: &str, data: &Everything) -> bool {
+        let Some((_, block)) = data.get_key_block(Item::ProductionMethod, pm) else {
+            // Missing production methods are reported elsewhere
+            return true;
+        };
+        // Any one of the technologies is enough to unlock it.
+        let techs = block.get_field_list("unlocking_technologies").unwrap_or_default();
+        techs.is_empty()
+            || techs.iter().any(|tech| {
+                data.get_key_block(Item::Technology, tech.as_str())
+                    .is_some_and(|(_, block)| block.get_field_bool("can_research").unwrap_or(true))
+            })
+    }
 }
 
 impl DbKind for ProductionMethod {
@@ -135,7 +152,37 @@ impl DbKind for ProductionMethodGroup {
 
         vd.req_field("production_methods");
         vd.field_list_items("production_methods", Item::ProductionMethod);
+        if let Some(pms) = block.get_field_list("production_methods") {
+            let is_default = |pm: &Token| {
+                data.get_key_block(Item::ProductionMethod, pm.as_str())
+                    .is_some_and(|(_, block)| block.get_field_bool("is_default").unwrap_or(false))
+            };
+            if !pms.is_empty() && !pms.iter().any(is_default) {
+                let msg = "none of the production methods in this group is `is_default`";
+                let info = "the first one that is available will be used for new buildings";
+                tips(ErrorKey::Validation).msg(msg).info(info).loc(key).push();
+            }
+        }
 
         vd.field_choice("ai_selection", &["most_profitable", "most_productive"]);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_is_unlockable() {
+        let mod_root = PathBuf::from("tests/files/vic3");
+        let mut data = Everything::new(None, None, &mod_root, Vec::new()).unwrap();
+        data.load_all();
+
+        assert!(ProductionMethod::is_unlockable("pm_free", &data));
+        assert!(ProductionMethod::is_unlockable("pm_open", &data));
+        assert!(!ProductionMethod::is_unlockable("pm_closed", &data));
+        assert!(ProductionMethod::is_unlockable("pm_either", &data));
+    }
+}
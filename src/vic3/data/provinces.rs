@@ -4,11 +4,12 @@ use image::{DynamicImage, Rgb};
 
 use crate::everything::Everything;
 use crate::fileset::{FileEntry, FileHandler};
-use crate::helpers::TigerHashSet;
+use crate::helpers::{TigerHashMap, TigerHashSet};
 use crate::item::Item;
 use crate::parse::ParserMemory;
 use crate::report::{err, report, ErrorKey, Severity};
 use crate::token::Token;
+use crate::vic3::data::state_regions::StateRegion;
 
 #[derive(Clone, Debug, Default)]
 pub struct Vic3Provinces {
@@ -49,8 +50,37 @@ impl Vic3Provinces {
         false
     }
 
-    #[allow(clippy::unused_self)]
-    pub fn validate(&self, _data: &Everything) {}
+    /// Check that every province on the map is part of a state region.
+    pub fn validate(&self, data: &Everything) {
+        let Some(entry) = &self.provinces_png else {
+            return;
+        };
+        let missing = missing_provinces(&self.colors, &StateRegion::province_map(data));
+        if !missing.is_empty() {
+            let msg = format!("{} provinces are not part of any state region", missing.len());
+            let info = if missing.len() > 10 {
+                format!("including {}", missing[..10].join(", "))
+            } else {
+                format!("they are {}", missing.join(", "))
+            };
+            err(ErrorKey::Validation).msg(msg).info(info).loc(entry).push();
+        }
+    }
+}
+
+/// Return the ids of the provinces with these colors that are not in `in_regions`, in order.
+/// The keys of `in_regions` must be normalized with [`StateRegion::province_key`].
+fn missing_provinces<T>(
+    colors: &TigerHashSet<Rgb<u8>>,
+    in_regions: &TigerHashMap<String, T>,
+) -> Vec<String> {
+    let mut missing: Vec<String> = colors
+        .iter()
+        .map(|Rgb([r, g, b])| format!("x{r:02X}{g:02X}{b:02X}"))
+        .filter(|province| !in_regions.contains_key(province))
+        .collect();
+    missing.sort_unstable();
+    missing
 }
 
 impl FileHandler<DynamicImage> for Vic3Provinces {
@@ -92,3 +122,21 @@ impl FileHandler<DynamicImage> for Vic3Provinces {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_provinces() {
+        let colors: TigerHashSet<Rgb<u8>> =
+            [Rgb([0x1a, 0x2b, 0x3c]), Rgb([0xab, 0xcd, 0xef]), Rgb([1, 2, 3])]
+                .into_iter()
+                .collect();
+        let in_regions: TigerHashMap<String, &str> = ["x1A2B3C", "xabcdef"]
+            .map(|id| (StateRegion::province_key(id), id))
+            .into_iter()
+            .collect();
+        assert_eq!(missing_provinces(&colors, &in_regions), ["x010203"]);
+    }
+}
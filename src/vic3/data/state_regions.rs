@@ -2,7 +2,9 @@ use crate::block::Block;
 use crate::db::{Db, DbKind};
 use crate::everything::Everything;
 use crate::game::GameFlags;
+use crate::helpers::TigerHashMap;
 use crate::item::{Item, ItemLoader};
+use crate::report::{err, ErrorKey};
 use crate::token::Token;
 use crate::validator::Validator;
 
@@ -17,6 +19,52 @@ impl StateRegion {
     pub fn add(db: &mut Db, key: Token, block: Block) {
         db.add(Item::StateRegion, key, block, Box::new(Self {}));
     }
+
+    /// Return a map from each province to the state regions that contain it.
+    /// The keys are normalized with [`Self::province_key`].
+    pub fn province_map(data: &Everything) -> TigerHashMap<String, Vec<&Token>> {
+        let mut map: TigerHashMap<String, Vec<&Token>> = TigerHashMap::default();
+        for (_, block) in data.database.iter_key_block(Item::StateRegion) {
+            if let Some(block) = block.get_field_block("provinces") {
+                for province in block.iter_values() {
+                    map.entry(Self::province_key(province.as_str())).or_default().push(province);
+                }
+            }
+        }
+        map
+    }
+
+    /// Normalize a province id of the form `xRRGGBB`.
+    /// The hex digits can be in either case, so they are uppercased, but the `x` is kept.
+    pub fn province_key(id: &str) -> String {
+        match id.strip_prefix('x') {
+            Some(hex) => format!("x{}", hex.to_ascii_uppercase()),
+            None => id.to_owned(),
+        }
+    }
+
+    pub fn crosscheck(data: &Everything) {
+        // Each province must be part of one and only one state region.
+        // Sort by location so that the same state region is blamed every run.
+        let mut provinces: Vec<_> = Self::province_map(data).into_values().collect();
+        for tokens in &mut provinces {
+            tokens.sort_by_key(|token| token.loc);
+        }
+        provinces.sort_by_key(|tokens| tokens[0].loc);
+        for tokens in &provinces {
+            if let [first, others @ ..] = &tokens[..] {
+                for other in others {
+                    let msg = format!("province {first} is part of more than one state region");
+                    err(ErrorKey::Conflict)
+                        .strong()
+                        .msg(msg)
+                        .loc(*other)
+                        .loc_msg(*first, "the other one")
+                        .push();
+                }
+            }
+        }
+    }
 }
 
 impl DbKind for StateRegion {
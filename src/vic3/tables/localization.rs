@@ -1,5 +1,6 @@
 use std::sync::LazyLock;
 
+use crate::data::localization::FormatKind;
 use crate::helpers::TigerHashSet;
 
 pub(crate) static BUILTIN_MACROS_VIC3: LazyLock<TigerHashSet<&'static str>> =
@@ -1050,3 +1051,34 @@ const BUILTIN_MACROS: &[&str] = &[
     "YEARLY_VALUE",
     "YEARS",
 ];
+
+/// The built-in formatting codes that can follow the `|` in `[code|format]`.
+/// Single-letter textformats defined in the gui files can be used as color codes too, so they
+/// don't have to be listed here.
+pub(crate) const FORMAT_CODES_VIC3: &[(char, FormatKind)] = &[
+    ('0', FormatKind::Number),
+    ('1', FormatKind::Number),
+    ('2', FormatKind::Number),
+    ('3', FormatKind::Number),
+    ('4', FormatKind::Number),
+    ('5', FormatKind::Number),
+    ('6', FormatKind::Number),
+    ('7', FormatKind::Number),
+    ('8', FormatKind::Number),
+    ('9', FormatKind::Number),
+    ('%', FormatKind::Number),
+    ('+', FormatKind::Number),
+    ('-', FormatKind::Number),
+    ('=', FormatKind::Number),
+    ('*', FormatKind::Number),
+    ('K', FormatKind::Number),
+    ('U', FormatKind::Text),
+    ('L', FormatKind::Text),
+    ('E', FormatKind::Concept),
+    ('e', FormatKind::Concept),
+    ('P', FormatKind::Color),
+    ('N', FormatKind::Color),
+    ('V', FormatKind::Color),
+    ('v', FormatKind::Color),
+    ('H', FormatKind::Color),
+];
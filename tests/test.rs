@@ -1,7 +1,7 @@
 use std::path::PathBuf;
 use std::sync::{LazyLock, Mutex};
 
-use tiger_lib::{take_reports, Everything, LogReport};
+use tiger_lib::{take_reports, Confidence, Everything, LogReport, Severity};
 
 static TEST_MUTEX: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
 
@@ -158,3 +158,223 @@ fn test_mod2() {
     dbg!(&reports);
     assert!(reports.is_empty());
 }
+
+#[test]
+fn test_flow() {
+    let mut reports = check_mod_helper("flow");
+
+    let on_actions = "common/on_action/flow_on_actions.txt";
+    let report = take_report(
+        &mut reports,
+        on_actions,
+        "character flag `flow_use_only` is checked but never set",
+    );
+    report.expect("use-only flag test");
+    let report = take_report(
+        &mut reports,
+        on_actions,
+        "character flag `flow_set_only` is set but never checked",
+    );
+    report.expect("set-only flag test");
+    let report = take_report_contains(&mut reports, on_actions, "flow_macro");
+    assert!(report.is_none());
+    let report =
+        take_report_contains(&mut reports, "common/scripted_effects/flow_effects.txt", "flow_");
+    assert!(report.is_none());
+
+    dbg!(&reports);
+    assert!(reports.is_empty());
+}
+
+#[test]
+fn test_history() {
+    let mut reports = check_mod_helper("history");
+    // The fixture has no map, governments, or bookmark art.
+    reports.retain(|report| {
+        !report.msg.contains("not defined in") && !report.msg.contains("does not exist")
+    });
+
+    // Each problem is reported for the first bookmark only, and each loop only once.
+    let titles = "history/titles/history_titles.txt";
+    let report =
+        take_report(&mut reports, titles, "holder of k_dead is no longer alive on 1150.1.1");
+    report.expect("dead holder test");
+    let report = take_report(
+        &mut reports,
+        titles,
+        "history_first is indirectly their own liege on 1150.1.1",
+    );
+    let report = report.expect("liege loop test");
+    assert_eq!(report.pointers.len(), 2);
+    let report = take_report_contains(&mut reports, titles, "their liege history_second");
+    report.expect("liege tier test");
+    let report = take_report_contains(&mut reports, titles, "their liege history_first");
+    report.expect("liege tier test");
+    let report = take_report(
+        &mut reports,
+        titles,
+        "history_ruler has both clan_government and feudal_government on 1150.1.1",
+    );
+    report.expect("government conflict test");
+
+    dbg!(&reports);
+    assert!(reports.is_empty());
+}
+
+#[test]
+fn test_upgrade() {
+    let _guard = TEST_MUTEX.lock().unwrap();
+
+    let mod_root = PathBuf::from("tests/files/upgrade/mod");
+    let load = |vanilla: &str| {
+        let vanilla_dir = PathBuf::from(vanilla);
+        let mut everything =
+            Everything::new(None, Some(&vanilla_dir), &mod_root, Vec::new()).unwrap();
+        everything.keep_overridden();
+        everything.load_all();
+        everything
+    };
+    let old = load("tests/files/upgrade/old");
+    let new = load("tests/files/upgrade/new");
+    take_reports();
+
+    new.check_upgrade(&old);
+    let mut reports = take_reports();
+
+    let decisions = "common/decisions/upgrade_mod_decisions.txt";
+    let report = take_report(
+        &mut reports,
+        decisions,
+        "vanilla decision `upgrade_changed_decision` that this overrides has changed",
+    );
+    let report = report.expect("changed item test");
+    assert_eq!(report.info.as_deref(), Some("changed fields: `ai_check_interval`"));
+
+    let loca = "localization/english/upgrade_mod_l_english.yml";
+    let report = take_report(
+        &mut reports,
+        loca,
+        "vanilla localization `upgrade_changed_loca` that this overrides has changed",
+    );
+    report.expect("changed localization test");
+
+    let on_actions = "common/on_action/upgrade_on_actions.txt";
+    let report = take_report(
+        &mut reports,
+        on_actions,
+        "vanilla decision `upgrade_removed_decision` was removed",
+    );
+    let report = report.expect("removed item key test");
+    assert_eq!(report.severity, Severity::Warning);
+    assert_eq!(report.confidence, Confidence::Weak);
+    let report = take_report(
+        &mut reports,
+        on_actions,
+        "vanilla decision `upgrade_old_name_decision` was removed",
+    );
+    let report = report.expect("renamed item test");
+    assert_eq!(report.severity, Severity::Warning);
+    assert_eq!(
+        report.info.as_deref(),
+        Some("it may have been renamed to `upgrade_new_name_decision`")
+    );
+
+    dbg!(&reports);
+    assert!(reports.is_empty());
+}
+
+#[test]
+fn test_overrides() {
+    let _guard = TEST_MUTEX.lock().unwrap();
+
+    let vanilla_dir = PathBuf::from("tests/files/overrides/vanilla");
+    let mod_root = PathBuf::from("tests/files/overrides/mod");
+
+    let mut everything = Everything::new(None, Some(&vanilla_dir), &mod_root, Vec::new()).unwrap();
+    everything.load_all();
+    everything.validate_all();
+    let mut reports = take_reports();
+
+    let report = take_report(
+        &mut reports,
+        "common/scripted_effects/override_effects.txt",
+        "this file replaces the vanilla file but copies 1 of its 3 items unchanged",
+    );
+    let report = report.expect("partial copy test");
+    assert_eq!(report.severity, Severity::Tips);
+    let info = report.info.expect("partial copy info");
+    assert!(info.starts_with(
+        "changed: `override_changed_effect`; unchanged: `override_kept_effect`; dropped: `override_dropped_effect`. "
+    ));
+
+    let report = take_report(
+        &mut reports,
+        "common/scripted_effects/copy_effects.txt",
+        "this file is an unchanged copy of the vanilla file it replaces",
+    );
+    let report = report.expect("whole-file copy test");
+    assert_eq!(report.severity, Severity::Untidy);
+
+    // rewritten_effects.txt changes every item it replaces, so it gets no report.
+    dbg!(&reports);
+    assert!(reports.is_empty());
+}
+
+#[test]
+fn test_translation_hashes() {
+    let _guard = TEST_MUTEX.lock().unwrap();
+
+    let vanilla_dir = PathBuf::from("tests/files/ck3");
+    let hashes = std::env::temp_dir().join("tiger-test-translation-hashes.json");
+    let german = r#"{
+  "german": {
+    "translations_greeting": {
+      "source": "1",
+      "translation": "2"
+    }
+  }
+}
+"#;
+
+    // Recorded hashes for languages that aren't loaded are kept.
+    std::fs::write(&hashes, german).unwrap();
+    let mod_root = PathBuf::from("tests/files/translations/mod");
+    let mut everything = Everything::new(None, Some(&vanilla_dir), &mod_root, Vec::new()).unwrap();
+    everything.load_all();
+    everything.check_translation_hashes(&hashes);
+    let content = std::fs::read_to_string(&hashes).unwrap();
+    assert!(content.contains("\"german\""));
+    assert!(content.contains("\"french\""));
+    assert!(content.contains("translations_farewell"));
+
+    // Without the reference language there is nothing to record, and the file is left alone.
+    std::fs::write(&hashes, german).unwrap();
+    let mod_root = PathBuf::from("tests/files/translations/french_only");
+    let mut everything = Everything::new(None, Some(&vanilla_dir), &mod_root, Vec::new()).unwrap();
+    everything.load_all();
+    everything.check_translation_hashes(&hashes);
+    assert_eq!(std::fs::read_to_string(&hashes).unwrap(), german);
+
+    std::fs::remove_file(&hashes).unwrap();
+    let reports = take_reports();
+    dbg!(&reports);
+    assert!(reports.is_empty());
+}
+
+#[test]
+fn test_explain_scope() {
+    let _guard = TEST_MUTEX.lock().unwrap();
+
+    let vanilla_dir = PathBuf::from("tests/files/ck3");
+    let mod_root = PathBuf::from("tests/files/mod2");
+
+    let mut everything = Everything::new(None, Some(&vanilla_dir), &mod_root, Vec::new()).unwrap();
+    everything.load_all();
+    let lists = PathBuf::from("common/on_action/test-scripted-lists.txt");
+    let explanations = everything.explain_scope(&lists, 4, 20);
+    take_reports();
+
+    let explanation = explanations.first().expect("explanation for every_courtier_parent");
+    assert!(explanation.key.is("every_courtier_parent"));
+    assert_eq!(explanation.traceback.len(), 0);
+}
@@ -8,7 +8,7 @@ use console::Term;
 use tiger_lib::ModFile;
 #[cfg(feature = "vic3")]
 use tiger_lib::ModMetadata;
-use tiger_lib::{emit_reports, set_output_file, Everything};
+use tiger_lib::{check_ignore_directives, emit_reports, set_output_file, Everything, OutputFormat};
 
 use crate::gamedir::{find_game_directory_steam, find_paradox_directory};
 use crate::GameConsts;
@@ -139,12 +139,13 @@ fn validate_mod(
     // The colors can be enabled again in the config file.
     everything.load_output_settings(false);
     everything.load_config_filtering_rules();
-    emit_reports(false);
+    emit_reports(OutputFormat::Text);
 
     everything.load_all();
     everything.validate_all();
     everything.check_rivers();
-    emit_reports(false);
+    check_ignore_directives();
+    emit_reports(OutputFormat::Text);
 
     // Properly dropping `everything` takes a noticeable amount of time, and we're exiting anyway.
     forget(everything);
@@ -1,4 +1,6 @@
-use std::{mem::forget, path::PathBuf};
+use std::fs::write;
+use std::mem::forget;
+use std::path::{Path, PathBuf};
 
 use anyhow::{bail, Result};
 use clap::{Args, Parser, Subcommand};
@@ -7,8 +9,9 @@ use tiger_lib::ModFile;
 #[cfg(feature = "vic3")]
 use tiger_lib::ModMetadata;
 use tiger_lib::{
-    disable_ansi_colors, emit_reports, set_show_loaded_mods, set_show_vanilla, suppress_from_json,
-    validate_config_file, Everything,
+    check_ignore_directives, disable_ansi_colors, emit_reports, set_show_loaded_mods,
+    set_show_vanilla, suppress_from_json, take_reports, validate_config_file, Everything, Language,
+    MarkupStyle, OutputFormat, RenderOptions, TranslationFormat,
 };
 
 use crate::gamedir::find_game_directory_steam;
@@ -33,8 +36,102 @@ enum Commands {
         /// release version (e.g. 0.9.3)
         version: Option<String>,
     },
+    /// Show what is known about the scopes at a position in the mod's script: the scope types of
+    /// root, this, and prev, the saved scopes and lists, and the events and on-actions that lead
+    /// there.
+    ExplainScope {
+        /// Position in the form `FILE:LINE:COLUMN`, for example `events/my_events.txt:12:9`
+        position: String,
+        #[clap(flatten)]
+        mod_args: ModArgs,
+    },
+    /// Report what in the mod is affected by a game update: vanilla items and localizations that
+    /// the mod refers to but were removed, and vanilla files, items, and localizations that the
+    /// mod overrides but were changed. The `--game` directory is the new version.
+    Upgrade {
+        /// Path to the old version's game main directory.
+        #[clap(long)]
+        old_game: PathBuf,
+        #[clap(flatten)]
+        mod_args: ModArgs,
+    },
+    /// Print the text of a localization key with its `$macros$` expanded, for proofreading.
+    /// Code between `[` `]` is shown as a placeholder unless a sample value is given for it.
+    RenderLoca {
+        /// The localization key to render.
+        key: String,
+        /// The language to render. By default it's the reference language, usually english.
+        #[clap(long)]
+        lang: Option<Language>,
+        /// A sample value for code or for a macro, in the form `CODE=VALUE`, for example
+        /// `ROOT.Char.GetName=Alice`. Can be given more than once.
+        #[clap(long, value_name = "CODE=VALUE")]
+        sample: Vec<String>,
+        /// What to do with #markup: strip, show, or color.
+        #[clap(long, default_value = "strip")]
+        markup: MarkupStyle,
+        #[clap(flatten)]
+        mod_args: ModArgs,
+    },
+    /// Write the mod's localizations that are missing or out of date in a language to a file for
+    /// translation tools, with the reference language text, the file it is from, and where it's
+    /// used.
+    ExportTranslations {
+        /// The language to translate into, such as `german`.
+        #[clap(long)]
+        lang: Language,
+        /// File format: csv, xliff, or po. By default it's taken from the output file's extension,
+        /// or else csv.
+        #[clap(long)]
+        format: Option<TranslationFormat>,
+        /// The file to write. By default the output goes to stdout.
+        #[clap(long)]
+        output: Option<PathBuf>,
+        /// Also export translations that are out of date according to this file of hashes (see
+        /// `--translation-hashes`).
+        #[clap(long, value_name = "PATH")]
+        translation_hashes: Option<PathBuf>,
+        #[clap(flatten)]
+        mod_args: ModArgs,
+    },
+    /// Write the translated strings from a file made with `export-translations` into the mod's
+    /// localization files.
+    ImportTranslations {
+        /// The language the file was translated into, such as `german`.
+        #[clap(long)]
+        lang: Language,
+        /// File format: csv, xliff, or po. By default it's taken from the input file's extension.
+        #[clap(long)]
+        format: Option<TranslationFormat>,
+        /// The file to read.
+        #[clap(long)]
+        input: PathBuf,
+        #[clap(flatten)]
+        mod_args: ModArgs,
+    },
+}
+
+#[derive(Args)]
+struct ModArgs {
+    #[cfg(feature = "vic3")]
+    /// Path to folder of mod to check.
+    modpath: PathBuf,
+    #[cfg(any(feature = "ck3", feature = "imperator", feature = "hoi4"))]
+    /// Path to .mod file of mod to check.
+    modpath: PathBuf,
+    #[cfg_attr(feature = "ck3", clap(visible_alias = "ck3"))]
+    #[cfg_attr(feature = "vic3", clap(visible_alias = "vic3"))]
+    #[cfg_attr(feature = "imperator", clap(visible_alias = "imperator"))]
+    #[clap(long)]
+    /// Path to game main directory.
+    game: Option<PathBuf>,
+    /// Path to custom .conf file.
+    #[clap(long)]
+    config: Option<PathBuf>,
 }
 
+// The mod arguments are repeated here rather than flattened from `ModArgs`, because clap does not
+// detect the presence of an optional flattened struct by the arguments of a nested one.
 #[derive(Args)]
 struct ValidateArgs {
     #[cfg(feature = "vic3")]
@@ -58,9 +155,12 @@ struct ValidateArgs {
     /// Show errors in other loaded mods as well
     #[clap(long)]
     show_mods: bool,
-    /// Output the reports in JSON format
-    #[clap(long)]
+    /// Output the reports in JSON format. Same as `--format json`.
+    #[clap(long, conflicts_with = "format")]
     json: bool,
+    /// Output format of the reports: text, json, junit, checkstyle, or gitlab.
+    #[clap(long, default_value = "text")]
+    format: OutputFormat,
     /// Warn about items that are defined but unused
     #[clap(long)]
     unused: bool,
@@ -75,6 +175,12 @@ struct ValidateArgs {
     /// Load a JSON file of reports to remove from the output.
     #[clap(long)]
     suppress: Option<PathBuf>,
+    /// Only show reports about lines of the mod that changed since this git revision.
+    #[clap(long, value_name = "REV")]
+    changed_since: Option<String>,
+    /// Track which translations are older than the reference language text, using this file.
+    #[clap(long, value_name = "PATH")]
+    translation_hashes: Option<PathBuf>,
 }
 
 /// Run the main tiger application.
@@ -88,17 +194,40 @@ pub fn run(
 ) -> Result<()> {
     use clap::{CommandFactory, FromArgMatches};
 
-    let &GameConsts { name, name_short, version, app_id, signature_file, .. } = game_consts;
+    let &GameConsts { name, version, .. } = game_consts;
 
     let matches = Cli::command().version(current_version).name(bin_name).get_matches();
     let cli = Cli::from_arg_matches(&matches).map_err(|err| err.exit()).unwrap();
 
-    #[allow(clippy::single_match_else)]
     match cli.command {
         Some(Commands::Update { version: target_version }) => {
             update(current_version, target_version.as_deref())?;
             Ok(())
         }
+        Some(Commands::ExplainScope { position, mod_args }) => {
+            explain_scope(game_consts, &position, mod_args)
+        }
+        Some(Commands::Upgrade { old_game, mod_args }) => upgrade(game_consts, old_game, mod_args),
+        Some(Commands::RenderLoca { key, lang, sample, markup, mod_args }) => {
+            render_loca(game_consts, &key, lang, &sample, markup, mod_args)
+        }
+        Some(Commands::ExportTranslations {
+            lang,
+            format,
+            output,
+            translation_hashes,
+            mod_args,
+        }) => export_translations(
+            game_consts,
+            lang,
+            format,
+            output.as_deref(),
+            translation_hashes.as_deref(),
+            mod_args,
+        ),
+        Some(Commands::ImportTranslations { lang, format, input, mod_args }) => {
+            import_translations(game_consts, lang, format, &input, mod_args)
+        }
         None => {
             let mut args = cli.validate_args.unwrap();
             #[cfg(windows)]
@@ -111,30 +240,8 @@ pub fn run(
             eprintln!("If you are using a newer version of {name}, it may be inaccurate.");
             eprintln!("!! Currently it's inaccurate anyway because it's in beta state.");
 
-            if args.game.is_none() {
-                args.game = find_game_directory_steam(app_id).ok();
-            }
-            if let Some(ref mut game) = args.game {
-                eprintln!("Using {name_short} directory: {}", game.display());
-                let mut sig = game.clone();
-                sig.push(signature_file);
-                if !sig.is_file() {
-                    eprintln!("That does not look like a {name_short} directory.");
-                    game.push("..");
-                    eprintln!("Trying: {}", game.display());
-                    sig.clone_from(game);
-                    sig.push(signature_file);
-                    if sig.is_file() {
-                        eprintln!("Ok.");
-                    } else {
-                        bail!("Cannot find {name_short} directory. Please supply it as the --game option.");
-                    }
-                }
-            } else {
-                bail!("Cannot find {name_short} directory. Please supply it as the --game option.");
-            }
-
-            args.config = validate_config_file(args.config);
+            let game = find_game_directory(args.game.take(), game_consts)?;
+            args.config = validate_config_file(args.config.take());
 
             if let Some(suppress) = args.suppress {
                 eprintln!("Suppressing reports from: {}", suppress.display());
@@ -149,6 +256,10 @@ pub fn run(
                 eprintln!("Showing warnings for other loaded mods too.");
             }
 
+            if let Some(rev) = &args.changed_since {
+                eprintln!("Showing only warnings about lines changed since {rev}.");
+            }
+
             if args.unused {
                 eprintln!(
                     "Showing warnings for unused localization. There will be many false positives."
@@ -165,50 +276,23 @@ pub fn run(
                 disable_ansi_colors();
             }
 
-            let mut everything;
-
-            #[cfg(any(feature = "ck3", feature = "imperator", feature = "hoi4"))]
-            {
-                if args.modpath.is_dir() {
-                    args.modpath.push("descriptor.mod");
-                }
-
-                let modfile = ModFile::read(&args.modpath)?;
-                let modpath = modfile.modpath();
-                if !modpath.exists() {
-                    eprintln!("Looking for mod in {}", modpath.display());
-                    bail!("Cannot find mod directory. Please make sure the .mod file is correct.");
-                }
-                eprintln!("Using mod directory: {}", modpath.display());
-
-                everything = Everything::new(
-                    args.config.as_deref(),
-                    args.game.as_deref(),
-                    &modpath,
-                    modfile.replace_paths(),
-                )?;
-            }
-            #[cfg(feature = "vic3")]
-            {
-                let metadata = ModMetadata::read(&args.modpath)?;
-                eprintln!("Using mod directory: {}", metadata.modpath().display());
-
-                everything = Everything::new(
-                    args.config.as_deref(),
-                    args.game.as_deref(),
-                    &args.modpath,
-                    metadata.replace_paths(),
-                )?;
-            }
+            let mut everything = load_mod(&args.modpath, args.config.as_deref(), &game)?;
 
             // Print a blank line between the preamble and the first report:
             eprintln!();
 
             everything.load_output_settings(true);
             everything.load_config_filtering_rules();
+            if let Some(rev) = &args.changed_since {
+                everything.load_changed_lines(rev)?;
+            }
 
-            if !args.json {
-                emit_reports(false);
+            if args.json {
+                args.format = OutputFormat::Json;
+            }
+            // Machine-readable formats are printed as one document at the end.
+            if args.format == OutputFormat::Text {
+                emit_reports(OutputFormat::Text);
             }
 
             // We must apply the --no-color flag AFTER loading and applying the config,
@@ -227,12 +311,16 @@ pub fn run(
             everything.load_all();
             everything.validate_all();
             everything.check_rivers();
+            if let Some(path) = &args.translation_hashes {
+                everything.check_translation_hashes(path);
+            }
 
             #[cfg(feature = "ck3")]
             if args.pod {
                 everything.check_pod();
             }
-            emit_reports(args.json);
+            check_ignore_directives();
+            emit_reports(args.format);
             if args.unused {
                 everything.check_unused();
             }
@@ -243,3 +331,228 @@ pub fn run(
         }
     }
 }
+
+/// Find the game directory, either from the `--game` option or from the Steam library, and check
+/// that it really is the game directory.
+fn find_game_directory(game: Option<PathBuf>, game_consts: &GameConsts) -> Result<PathBuf> {
+    let &GameConsts { name_short, app_id, signature_file, .. } = game_consts;
+
+    let Some(mut game) = game.or_else(|| find_game_directory_steam(app_id).ok()) else {
+        bail!("Cannot find {name_short} directory. Please supply it as the --game option.");
+    };
+    eprintln!("Using {name_short} directory: {}", game.display());
+    let mut sig = game.clone();
+    sig.push(signature_file);
+    if !sig.is_file() {
+        eprintln!("That does not look like a {name_short} directory.");
+        game.push("..");
+        eprintln!("Trying: {}", game.display());
+        sig.clone_from(&game);
+        sig.push(signature_file);
+        if sig.is_file() {
+            eprintln!("Ok.");
+        } else {
+            bail!("Cannot find {name_short} directory. Please supply it as the --game option.");
+        }
+    }
+    Ok(game)
+}
+
+/// Read the mod's descriptor and create an [`Everything`] for the mod and the game files.
+fn load_mod(modpath: &Path, config: Option<&Path>, game: &Path) -> Result<Everything> {
+    #[cfg(any(feature = "ck3", feature = "imperator", feature = "hoi4"))]
+    {
+        let modfile = if modpath.is_dir() {
+            ModFile::read(&modpath.join("descriptor.mod"))?
+        } else {
+            ModFile::read(modpath)?
+        };
+        let modpath = modfile.modpath();
+        if !modpath.exists() {
+            eprintln!("Looking for mod in {}", modpath.display());
+            bail!("Cannot find mod directory. Please make sure the .mod file is correct.");
+        }
+        eprintln!("Using mod directory: {}", modpath.display());
+
+        Everything::new(config, Some(game), &modpath, modfile.replace_paths())
+    }
+    #[cfg(feature = "vic3")]
+    {
+        let metadata = ModMetadata::read(modpath)?;
+        eprintln!("Using mod directory: {}", metadata.modpath().display());
+
+        Everything::new(config, Some(game), modpath, metadata.replace_paths())
+    }
+}
+
+/// Split a position of the form `FILE:LINE:COLUMN` into its parts.
+/// The file part may itself contain colons, as in Windows drive letters.
+fn parse_position(position: &str) -> Result<(PathBuf, u32, u32)> {
+    let mut parts = position.rsplitn(3, ':');
+    let column = parts.next().and_then(|s| s.parse().ok());
+    let line = parts.next().and_then(|s| s.parse().ok());
+    match (parts.next(), line, column) {
+        (Some(file), Some(line), Some(column)) => Ok((PathBuf::from(file), line, column)),
+        _ => bail!("Expected a position like events/my_events.txt:12:9, got {position}"),
+    }
+}
+
+/// Run the `explain-scope` subcommand.
+fn explain_scope(game_consts: &GameConsts, position: &str, mut mod_args: ModArgs) -> Result<()> {
+    let (file, line, column) = parse_position(position)?;
+    let game = find_game_directory(mod_args.game.take(), game_consts)?;
+    mod_args.config = validate_config_file(mod_args.config.take());
+    let mut everything = load_mod(&mod_args.modpath, mod_args.config.as_deref(), &game)?;
+    eprintln!();
+
+    everything.load_all();
+    let explanations = everything.explain_scope(&file, line, column);
+    // Only the explanation is wanted, not the validation reports.
+    drop(take_reports());
+
+    if explanations.is_empty() {
+        bail!("No trigger or effect was validated at {position}.");
+    }
+    for explanation in explanations {
+        println!("{explanation}");
+    }
+
+    // Properly dropping `everything` takes a noticeable amount of time, and we're exiting anyway.
+    forget(everything);
+    Ok(())
+}
+
+/// Run the `upgrade` subcommand.
+fn upgrade(game_consts: &GameConsts, old_game: PathBuf, mut mod_args: ModArgs) -> Result<()> {
+    let old_game = find_game_directory(Some(old_game), game_consts)?;
+    let game = find_game_directory(mod_args.game.take(), game_consts)?;
+    mod_args.config = validate_config_file(mod_args.config.take());
+
+    eprintln!("Loading the old version.");
+    let mut old = load_mod(&mod_args.modpath, mod_args.config.as_deref(), &old_game)?;
+    old.keep_overridden();
+    old.load_all();
+    eprintln!("Loading the new version.");
+    let mut everything = load_mod(&mod_args.modpath, mod_args.config.as_deref(), &game)?;
+    everything.keep_overridden();
+    everything.load_all();
+    eprintln!();
+
+    // Only the differences are wanted, not the reports from loading the files.
+    drop(take_reports());
+    everything.load_output_settings(true);
+    everything.load_config_filtering_rules();
+
+    everything.check_upgrade(&old);
+    emit_reports(OutputFormat::Text);
+
+    // Properly dropping `everything` takes a noticeable amount of time, and we're exiting anyway.
+    forget(old);
+    forget(everything);
+    Ok(())
+}
+
+/// Run the `render-loca` subcommand.
+fn render_loca(
+    game_consts: &GameConsts,
+    key: &str,
+    lang: Option<Language>,
+    samples: &[String],
+    markup: MarkupStyle,
+    mut mod_args: ModArgs,
+) -> Result<()> {
+    let mut options = RenderOptions { lang, markup, ..Default::default() };
+    for sample in samples {
+        let Some((code, value)) = sample.split_once('=') else {
+            bail!("Expected a sample like ROOT.Char.GetName=Alice, got {sample}");
+        };
+        options.samples.insert(code.to_string(), value.to_string());
+    }
+    let game = find_game_directory(mod_args.game.take(), game_consts)?;
+    mod_args.config = validate_config_file(mod_args.config.take());
+    let mut everything = load_mod(&mod_args.modpath, mod_args.config.as_deref(), &game)?;
+    eprintln!();
+
+    everything.load_all();
+    // Only the text is wanted, not the reports from loading the files.
+    drop(take_reports());
+
+    println!("{}", everything.render_loca(key, &options)?);
+
+    // Properly dropping `everything` takes a noticeable amount of time, and we're exiting anyway.
+    forget(everything);
+    Ok(())
+}
+
+/// Run the `export-translations` subcommand.
+fn export_translations(
+    game_consts: &GameConsts,
+    lang: Language,
+    format: Option<TranslationFormat>,
+    output: Option<&Path>,
+    translation_hashes: Option<&Path>,
+    mut mod_args: ModArgs,
+) -> Result<()> {
+    let format = format
+        .or_else(|| output.and_then(TranslationFormat::from_path))
+        .unwrap_or(TranslationFormat::Csv);
+    let game = find_game_directory(mod_args.game.take(), game_consts)?;
+    mod_args.config = validate_config_file(mod_args.config.take());
+    let mut everything = load_mod(&mod_args.modpath, mod_args.config.as_deref(), &game)?;
+    eprintln!();
+
+    // Validating finds out where each localization key is used.
+    everything.collect_loca_usage();
+    everything.load_all();
+    everything.validate_all();
+    // Only the translations are wanted, not the validation reports.
+    drop(take_reports());
+
+    let content = everything.export_translations(lang, format, translation_hashes)?;
+    if let Some(output) = output {
+        write(output, content)?;
+        eprintln!("Wrote {}", output.display());
+    } else {
+        print!("{content}");
+    }
+
+    // Properly dropping `everything` takes a noticeable amount of time, and we're exiting anyway.
+    forget(everything);
+    Ok(())
+}
+
+/// Run the `import-translations` subcommand.
+fn import_translations(
+    game_consts: &GameConsts,
+    lang: Language,
+    format: Option<TranslationFormat>,
+    input: &Path,
+    mut mod_args: ModArgs,
+) -> Result<()> {
+    let Some(format) = format.or_else(|| TranslationFormat::from_path(input)) else {
+        bail!(
+            "Cannot tell the format of {}. Please supply it as the --format option.",
+            input.display()
+        );
+    };
+    let game = find_game_directory(mod_args.game.take(), game_consts)?;
+    mod_args.config = validate_config_file(mod_args.config.take());
+    let mut everything = load_mod(&mod_args.modpath, mod_args.config.as_deref(), &game)?;
+    eprintln!();
+
+    everything.load_all();
+    // Only the import is wanted, not the reports from loading the files.
+    drop(take_reports());
+
+    let written = everything.import_translations(lang, format, input)?;
+    if written.is_empty() {
+        eprintln!("Nothing to import.");
+    }
+    for path in written {
+        eprintln!("Wrote {}", path.display());
+    }
+
+    // Properly dropping `everything` takes a noticeable amount of time, and we're exiting anyway.
+    forget(everything);
+    Ok(())
+}